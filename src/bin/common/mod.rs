@@ -0,0 +1,101 @@
+//! Command line parsing shared by the binaries.
+
+use std::ops::RangeInclusive;
+use std::process;
+use std::str::FromStr;
+use xgboost_predictor::errors::*;
+
+pub struct Args {
+    positional: Vec<String>,
+    /// `--name value` and `--name=value` options, flags have an empty value
+    options: Vec<(String, String)>,
+}
+
+impl Args {
+    /// Options which are not in `flags` take a value, `-h` and `--help` print `usage`
+    /// and exit
+    pub fn parse<I: Iterator<Item = String>>(
+        mut args: I,
+        flags: &[&str],
+        usage: &str,
+    ) -> Result<Args> {
+        let mut parsed = Args {
+            positional: vec![],
+            options: vec![],
+        };
+        while let Some(arg) = args.next() {
+            if arg == "-h" || arg == "--help" {
+                println!("{}", usage);
+                process::exit(0);
+            }
+            if !arg.starts_with("--") {
+                parsed.positional.push(arg);
+                continue;
+            }
+            let (name, value) = match arg.find('=') {
+                Some(i) => (arg[..i].to_string(), Some(arg[i + 1..].to_string())),
+                None => (arg.clone(), None),
+            };
+            let value = if flags.contains(&name.as_str()) {
+                String::new()
+            } else {
+                match value.or_else(|| args.next()) {
+                    Some(value) => value,
+                    None => bail!("missing value of {}", name),
+                }
+            };
+            parsed.options.push((name, value));
+        }
+        return Ok(parsed);
+    }
+
+    /// Returns the positional arguments, `names` describes them when there are not
+    /// `count` of them
+    pub fn positional(&self, count: RangeInclusive<usize>, names: &str) -> Result<&[String]> {
+        if !count.contains(&self.positional.len()) {
+            bail!("expected {}", names);
+        }
+        return Ok(&self.positional);
+    }
+
+    /// Checks that all options are `known`
+    pub fn check_options(&self, known: &[&str]) -> Result<()> {
+        if let Some((unknown, _)) = self
+            .options
+            .iter()
+            .find(|(n, _)| !known.contains(&n.as_str()))
+        {
+            bail!("unknown option {}", unknown);
+        }
+        return Ok(());
+    }
+
+    /// Returns the last value of the option `name`
+    pub fn option(&self, name: &str) -> Option<&str> {
+        return self
+            .options
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str());
+    }
+
+    /// Returns the last value of the option `name` parsed as a number
+    pub fn number<T: FromStr>(&self, name: &str) -> Result<Option<T>> {
+        return match self.option(name) {
+            Some(value) => Ok(Some(parse_number(name, value)?)),
+            None => Ok(None),
+        };
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        return self.options.iter().any(|(n, _)| n == name);
+    }
+}
+
+fn parse_number<T: FromStr>(name: &str, value: &str) -> Result<T> {
+    return match value.parse() {
+        Ok(value) => Ok(value),
+        Err(_) => bail!("invalid value of {}: '{}'", name, value),
+    };
+}
@@ -0,0 +1,277 @@
+//! Inspects and converts xgboost models.
+
+#![allow(clippy::needless_return)]
+
+#[macro_use]
+extern crate error_chain;
+
+mod common;
+
+use crate::common::Args;
+use std::cmp::Ordering;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::process;
+use xgboost_predictor::errors::*;
+use xgboost_predictor::fvec::FVecMap;
+use xgboost_predictor::model_format::{self, Compression, ModelFormat};
+use xgboost_predictor::predictor::{DumpFormat, ImportanceType, Predictor};
+
+const USAGE: &str = "Usage: xgboost-model COMMAND [OPTIONS] MODEL
+
+Reads MODEL in any format read by the crate: binary, JSON or UBJSON, gzip or zstd
+compressed with the matching features.
+
+Commands:
+  summary MODEL                print the format, objective, trees and features of the model
+  dump MODEL                   print the trees as xgboost's `dump_model`
+    --format text|json|dot     (default: text)
+    --tree N                   print the tree N only
+    --with-stats               add the gain of splits and the cover of nodes
+    --feature-names            print the feature names of the model instead of `f{index}`
+  importance MODEL             print the importance of each used feature, highest first
+    --type weight|gain|cover|total_gain|total_cover
+                               (default: weight)
+  convert MODEL OUTPUT         write the model to OUTPUT, which can't be UBJSON (.ubj)
+    --to binary|json|compact   (default: json for .json files, else binary)
+
+Options:
+  -h, --help                   print this help";
+
+type Model = Predictor<FVecMap<f32>>;
+
+/// Reads the model at `path` with the format it was saved in
+fn load(path: &str) -> Result<(Model, ModelFormat, Compression)> {
+    let bytes = fs::read(path).chain_err(|| format!("failed to read {}", path))?;
+    let compression = Compression::detect(&bytes);
+    let bytes = model_format::decompress(&bytes)?;
+    let mut format = ModelFormat::detect(&bytes);
+    let read = match format {
+        ModelFormat::Binary => Predictor::read_from_buffered(&mut &bytes[..]),
+        ModelFormat::Json => Predictor::read_from_json(&mut &bytes[..]),
+        ModelFormat::Ubjson => Predictor::read_from_ubjson(&mut &bytes[..]),
+    };
+    // binary models may start like JSON or UBJSON, as in `Predictor::from_bytes`
+    let read = match read {
+        Err(e) if format != ModelFormat::Binary => {
+            format = ModelFormat::Binary;
+            Predictor::read_from_buffered(&mut &bytes[..]).map_err(|_| e)
+        }
+        read => read,
+    };
+    let model = read.chain_err(|| format!("failed to load {}", path))?;
+    return Ok((model, format, compression));
+}
+
+fn feature_name(model: &Model, index: usize) -> String {
+    return match model.feature_names().get(index) {
+        Some(name) => name.clone(),
+        None => format!("f{}", index),
+    };
+}
+
+fn summary(args: &Args, out: &mut dyn Write) -> Result<()> {
+    args.check_options(&[])?;
+    let path = &args.positional(1..=1, "MODEL")?[0];
+    let (model, format, compression) = load(path)?;
+    let summary = model.summary();
+
+    let format = match compression {
+        Compression::None => format!("{:?}", format),
+        _ => format!("{:?}, {:?} compressed", format, compression),
+    };
+    writeln!(out, "format: {}", format.to_lowercase())?;
+    writeln!(out, "version: {}", model.version())?;
+    writeln!(out, "fingerprint: {:016x}", model.fingerprint())?;
+    writeln!(out, "booster: {}", summary.booster)?;
+    writeln!(out, "objective: {}", summary.objective)?;
+    writeln!(out, "base_score (margin): {}", summary.base_score)?;
+    writeln!(out, "features: {}", summary.num_feature)?;
+    writeln!(out, "output groups: {}", summary.num_output_group)?;
+    writeln!(out, "boosted rounds: {}", summary.num_boosted_rounds)?;
+    if let Some(best_iteration) = model.best_iteration() {
+        writeln!(out, "best iteration: {}", best_iteration)?;
+    }
+    writeln!(out, "trees: {}", summary.num_trees)?;
+    if summary.num_trees > 0 {
+        let per_group: Vec<String> = summary
+            .num_trees_per_group
+            .iter()
+            .map(|count| count.to_string())
+            .collect();
+        writeln!(out, "trees per group: {}", per_group.join(", "))?;
+        writeln!(out, "nodes: {}", summary.num_nodes)?;
+        writeln!(out, "leaves: {}", summary.num_leaves)?;
+        writeln!(out, "max depth: {}", summary.max_depth)?;
+        writeln!(out, "depth histogram:")?;
+        for (depth, count) in summary.depth_histogram.iter().enumerate() {
+            if *count > 0 {
+                writeln!(out, "  {}: {}", depth, count)?;
+            }
+        }
+        let used: Vec<String> = summary
+            .used_features
+            .iter()
+            .map(|index| feature_name(&model, *index))
+            .collect();
+        writeln!(
+            out,
+            "features used: {} of {}: {}",
+            used.len(),
+            summary.num_feature,
+            used.join(", ")
+        )?;
+    }
+    return Ok(());
+}
+
+fn dump(args: &Args, out: &mut dyn Write) -> Result<()> {
+    args.check_options(&["--format", "--tree", "--with-stats", "--feature-names"])?;
+    let path = &args.positional(1..=1, "MODEL")?[0];
+    let format = match args.option("--format").unwrap_or("text") {
+        "text" => DumpFormat::Text,
+        "json" => DumpFormat::Json,
+        "dot" => DumpFormat::Dot,
+        other => bail!("unknown dump format '{}'", other),
+    };
+    let tree: Option<usize> = args.number("--tree")?;
+    let (model, _, _) = load(path)?;
+    let mut dumps = model.dump_model_with_format(
+        format,
+        args.flag("--with-stats"),
+        args.flag("--feature-names"),
+    )?;
+    if let Some(tree) = tree {
+        if tree >= dumps.len() {
+            bail!(
+                "tree index {} is out of range for {} trees",
+                tree,
+                dumps.len()
+            );
+        }
+        dumps = vec![dumps.swap_remove(tree)];
+    }
+
+    match format {
+        DumpFormat::Text => {
+            for (i, dump) in dumps.iter().enumerate() {
+                write!(out, "booster[{}]:\n{}", tree.unwrap_or(i), dump)?;
+            }
+        }
+        DumpFormat::Json => {
+            let dumps: Vec<&str> = dumps.iter().map(|dump| dump.trim_end()).collect();
+            writeln!(out, "[\n{}\n]", dumps.join(",\n"))?;
+        }
+        DumpFormat::Dot => {
+            for dump in dumps.iter() {
+                write!(out, "{}", dump)?;
+            }
+        }
+    }
+    return Ok(());
+}
+
+fn importance(args: &Args, out: &mut dyn Write) -> Result<()> {
+    args.check_options(&["--type"])?;
+    let path = &args.positional(1..=1, "MODEL")?[0];
+    let importance_type = match args.option("--type").unwrap_or("weight") {
+        "weight" => ImportanceType::Weight,
+        "gain" => ImportanceType::Gain,
+        "cover" => ImportanceType::Cover,
+        "total_gain" => ImportanceType::TotalGain,
+        "total_cover" => ImportanceType::TotalCover,
+        other => bail!("unknown importance type '{}'", other),
+    };
+    let (model, _, _) = load(path)?;
+    let mut scores: Vec<(String, f32)> = model
+        .feature_importance_by_name(importance_type)?
+        .into_iter()
+        .collect();
+    scores.sort_by(|(name_a, a), (name_b, b)| {
+        b.partial_cmp(a)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| name_a.cmp(name_b))
+    });
+    for (name, score) in scores {
+        writeln!(out, "{}\t{}", name, score)?;
+    }
+    return Ok(());
+}
+
+fn convert(args: &Args) -> Result<()> {
+    args.check_options(&["--to"])?;
+    let positional = args.positional(2..=2, "MODEL and OUTPUT")?;
+    let (path, output) = (&positional[0], &positional[1]);
+    let to = match args.option("--to") {
+        Some(to) => to,
+        None if output.ends_with(".json") => "json",
+        None => "binary",
+    };
+    if !["binary", "json", "compact"].contains(&to) {
+        bail!("unknown model format '{}'", to);
+    }
+    if output.ends_with(".ubj") {
+        bail!(
+            "writing UBJSON models is not supported, {} would not be UBJSON",
+            output
+        );
+    }
+    let (model, _, _) = load(path)?;
+    let file = File::create(output).chain_err(|| format!("failed to create {}", output))?;
+    let mut writer = BufWriter::new(file);
+    match to {
+        "binary" => model.write_to(&mut writer)?,
+        "json" => model.write_json(&mut writer)?,
+        _ => model.write_compact(&mut writer)?,
+    }
+    writer.flush()?;
+    return Ok(());
+}
+
+fn run(command: &str, args: &Args) -> Result<()> {
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    match command {
+        "summary" => summary(args, &mut out)?,
+        "dump" => dump(args, &mut out)?,
+        "importance" => importance(args, &mut out)?,
+        _ => convert(args)?,
+    }
+    out.flush()?;
+    return Ok(());
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let command = args.next().unwrap_or_default();
+    let flags = ["--with-stats", "--feature-names"];
+    let parsed = match command.as_str() {
+        "-h" | "--help" => {
+            println!("{}", USAGE);
+            return;
+        }
+        "summary" | "dump" | "importance" | "convert" => Args::parse(args, &flags, USAGE),
+        "" => Err("missing command".into()),
+        other => Err(format!("unknown command '{}'", other).into()),
+    };
+    let result = parsed
+        .map_err(|e| (e, 2))
+        .and_then(|args| run(&command, &args).map_err(|e| (e, 1)));
+    if let Err((e, code)) = result {
+        // the reader of the output went away, e.g. `head`
+        if let ErrorKind::Io(io_error) = e.kind() {
+            if io_error.kind() == io::ErrorKind::BrokenPipe {
+                return;
+            }
+        }
+        eprintln!("error: {}", e);
+        for cause in e.iter().skip(1) {
+            eprintln!("caused by: {}", cause);
+        }
+        if code == 2 {
+            eprintln!("\n{}", USAGE);
+        }
+        process::exit(code);
+    }
+}
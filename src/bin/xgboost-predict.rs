@@ -0,0 +1,332 @@
+//! Scores libsvm or CSV rows with an xgboost model.
+
+#![allow(clippy::needless_return)]
+
+#[macro_use]
+extern crate error_chain;
+
+mod common;
+
+use crate::common::Args;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process;
+use std::thread;
+use xgboost_predictor::errors::*;
+use xgboost_predictor::fvec::FVecMap;
+use xgboost_predictor::predictor::Predictor;
+
+const USAGE: &str = "Usage: xgboost-predict [OPTIONS] MODEL [INPUT]
+
+Scores the rows of INPUT (stdin if missing or `-`) with MODEL, saved in any format read
+by the crate: binary, JSON or UBJSON, gzip or zstd compressed with the matching features.
+
+Options:
+  --input-format libsvm|csv    format of the rows (default: csv for .csv files, else libsvm)
+  --csv-header                 skip the first line of CSV input
+  --label-column N             CSV column of the label, left out of the features
+  --predict value|margin|leaf|contrib
+                               what to output per row (default: value)
+  --output PATH                file to write to (default: stdout)
+  --output-format csv|json     one CSV line or JSON array per row (default: csv)
+  --ntree-limit N              use the first N boosting rounds only (default: 0, all)
+  --missing VALUE              value treated as missing (default: NaN)
+  --threads N                  number of scoring threads (default: available cores)
+  -h, --help                   print this help";
+
+/// Rows parsed and scored together, split between threads
+const BATCH_SIZE: usize = 4096;
+
+#[derive(Clone, Copy, PartialEq)]
+enum InputFormat {
+    Libsvm,
+    Csv,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PredictType {
+    Value,
+    Margin,
+    Leaf,
+    Contrib,
+}
+
+struct Options {
+    model: String,
+    input: String,
+    input_format: Option<InputFormat>,
+    csv_header: bool,
+    label_column: Option<usize>,
+    predict_type: PredictType,
+    output: Option<String>,
+    output_format: OutputFormat,
+    ntree_limit: usize,
+    missing: f32,
+    threads: usize,
+}
+
+impl Options {
+    fn parse<I: Iterator<Item = String>>(args: I) -> Result<Options> {
+        let args = Args::parse(args, &["--csv-header"], USAGE)?;
+        args.check_options(&[
+            "--input-format",
+            "--csv-header",
+            "--label-column",
+            "--predict",
+            "--output",
+            "--output-format",
+            "--ntree-limit",
+            "--missing",
+            "--threads",
+        ])?;
+        let positional = args.positional(1..=2, "MODEL and an optional INPUT")?;
+        let input_format = match args.option("--input-format") {
+            Some("libsvm") => Some(InputFormat::Libsvm),
+            Some("csv") => Some(InputFormat::Csv),
+            Some(other) => bail!("unknown input format '{}'", other),
+            None => None,
+        };
+        let predict_type = match args.option("--predict").unwrap_or("value") {
+            "value" => PredictType::Value,
+            "margin" => PredictType::Margin,
+            "leaf" => PredictType::Leaf,
+            "contrib" => PredictType::Contrib,
+            other => bail!("unknown prediction type '{}'", other),
+        };
+        let output_format = match args.option("--output-format").unwrap_or("csv") {
+            "csv" => OutputFormat::Csv,
+            "json" => OutputFormat::Json,
+            other => bail!("unknown output format '{}'", other),
+        };
+        let threads = match args.number("--threads")? {
+            Some(0) => bail!("--threads must be at least 1"),
+            Some(threads) => threads,
+            None => thread::available_parallelism().map_or(1, |n| n.get()),
+        };
+        return Ok(Options {
+            model: positional[0].clone(),
+            input: positional.get(1).map_or("-", |input| input).to_string(),
+            input_format,
+            csv_header: args.flag("--csv-header"),
+            label_column: args.number("--label-column")?,
+            predict_type,
+            output: args.option("--output").map(String::from),
+            output_format,
+            ntree_limit: args.number("--ntree-limit")?.unwrap_or(0),
+            missing: args.number("--missing")?.unwrap_or(f32::NAN),
+            threads,
+        });
+    }
+
+    fn input_format(&self) -> InputFormat {
+        return match self.input_format {
+            Some(input_format) => input_format,
+            None if self.input.ends_with(".csv") => InputFormat::Csv,
+            None => InputFormat::Libsvm,
+        };
+    }
+
+    fn is_missing(&self, value: f32) -> bool {
+        return value.is_nan() || value == self.missing;
+    }
+
+    /// Parses the features of a row, `line_number` starting at 1
+    fn parse_row(&self, line: &str, line_number: usize) -> Result<FVecMap<f32>> {
+        let mut row = FVecMap::new();
+        match self.input_format() {
+            InputFormat::Libsvm => {
+                // the label comes first, then `index:value` pairs and possibly `qid:id`
+                for token in line.split_whitespace().skip(1) {
+                    let (index, value) = match token.find(':') {
+                        Some(i) => (&token[..i], &token[i + 1..]),
+                        None => bail!("line {}: invalid feature '{}'", line_number, token),
+                    };
+                    if index == "qid" {
+                        continue;
+                    }
+                    let index = index
+                        .parse::<usize>()
+                        .chain_err(|| format!("line {}: invalid index '{}'", line_number, index))?;
+                    let value = parse_value(value, line_number)?;
+                    if !self.is_missing(value) {
+                        row.insert(index, value);
+                    }
+                }
+            }
+            InputFormat::Csv => {
+                let cells = line
+                    .split(',')
+                    .enumerate()
+                    .filter(|(column, _)| Some(*column) != self.label_column)
+                    .map(|(_, cell)| cell.trim());
+                for (index, cell) in cells.enumerate() {
+                    if cell.is_empty() {
+                        continue;
+                    }
+                    let value = parse_value(cell, line_number)?;
+                    if !self.is_missing(value) {
+                        row.insert(index, value);
+                    }
+                }
+            }
+        }
+        return Ok(row);
+    }
+}
+
+fn parse_value(value: &str, line_number: usize) -> Result<f32> {
+    return value
+        .parse()
+        .chain_err(|| format!("line {}: invalid value '{}'", line_number, value));
+}
+
+/// Formats the output of a row, non-finite values are `null` in JSON
+fn format_output<T: ToString>(values: &[T], output_format: OutputFormat) -> String {
+    let cells = values.iter().map(|v| v.to_string());
+    return match output_format {
+        OutputFormat::Csv => cells.collect::<Vec<String>>().join(","),
+        OutputFormat::Json => {
+            let cells: Vec<String> = cells
+                .map(|cell| match cell.as_str() {
+                    "NaN" | "inf" | "-inf" => String::from("null"),
+                    _ => cell,
+                })
+                .collect();
+            format!("[{}]", cells.join(","))
+        }
+    };
+}
+
+fn score(
+    predictor: &Predictor<FVecMap<f32>>,
+    options: &Options,
+    line: &str,
+    line_number: usize,
+) -> Result<String> {
+    let row = options.parse_row(line, line_number)?;
+    let ntree_limit = options.ntree_limit;
+    let output_format = options.output_format;
+    return Ok(match options.predict_type {
+        PredictType::Value => format_output(
+            &predictor.try_predict(&row, false, ntree_limit)?,
+            output_format,
+        ),
+        PredictType::Margin => format_output(
+            &predictor.try_predict(&row, true, ntree_limit)?,
+            output_format,
+        ),
+        PredictType::Leaf => format_output(
+            &predictor.try_predict_leaf(&row, ntree_limit)?,
+            output_format,
+        ),
+        PredictType::Contrib => format_output(
+            &predictor.predict_contributions(&row, ntree_limit)?,
+            output_format,
+        ),
+    });
+}
+
+/// Scores the numbered lines of a batch on `options.threads` threads, keeping their order
+fn score_batch(
+    predictor: &Predictor<FVecMap<f32>>,
+    options: &Options,
+    batch: &[(usize, String)],
+) -> Result<Vec<String>> {
+    let chunk_size = batch.len().div_ceil(options.threads).max(1);
+    let chunks: Vec<Result<Vec<String>>> = thread::scope(|scope| {
+        let handles: Vec<_> = batch
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|(line_number, line)| score(predictor, options, line, *line_number))
+                        .collect()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| match h.join() {
+                Ok(chunk) => chunk,
+                Err(_) => Err("a scoring thread panicked".into()),
+            })
+            .collect()
+    });
+    let mut outputs = Vec::with_capacity(batch.len());
+    for chunk in chunks {
+        outputs.extend(chunk?);
+    }
+    return Ok(outputs);
+}
+
+fn run(options: &Options) -> Result<()> {
+    let predictor: Predictor<FVecMap<f32>> = Predictor::from_path(&options.model)?;
+    let input: Box<dyn BufRead> = if options.input == "-" {
+        Box::new(BufReader::new(io::stdin()))
+    } else {
+        let file =
+            File::open(&options.input).chain_err(|| format!("failed to open {}", options.input))?;
+        Box::new(BufReader::new(file))
+    };
+    let output: Box<dyn Write> = match &options.output {
+        None => Box::new(io::stdout()),
+        Some(path) => {
+            Box::new(File::create(path).chain_err(|| format!("failed to create {}", path))?)
+        }
+    };
+    let mut output = BufWriter::new(output);
+
+    let skip_header = options.csv_header && options.input_format() == InputFormat::Csv;
+    let mut lines = input.lines().enumerate().skip(skip_header as usize);
+    loop {
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        for (i, line) in lines.by_ref() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                batch.push((i + 1, line));
+            }
+            if batch.len() == BATCH_SIZE {
+                break;
+            }
+        }
+        if batch.is_empty() {
+            break;
+        }
+        for scored in score_batch(&predictor, options, &batch)? {
+            writeln!(output, "{}", scored)?;
+        }
+    }
+    output.flush()?;
+    return Ok(());
+}
+
+fn main() {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(e) = run(&options) {
+        // the reader of the output went away, e.g. `head`
+        if let ErrorKind::Io(io_error) = e.kind() {
+            if io_error.kind() == io::ErrorKind::BrokenPipe {
+                return;
+            }
+        }
+        eprintln!("error: {}", e);
+        for cause in e.iter().skip(1) {
+            eprintln!("caused by: {}", cause);
+        }
+        process::exit(1);
+    }
+}
@@ -0,0 +1,374 @@
+//! Compact model format, evaluated in place from its bytes.
+//!
+//! A compact model is read without copying its trees, so a memory-mapped file is shared
+//! through the page cache by every process serving it. It holds tree models with scalar
+//! leaves, numerical splits and a single root per tree. All values are little-endian and
+//! 4 bytes wide:
+//!
+//! ```text
+//! magic "XGBC", version, num_feature, num_output_group, base_score (margin),
+//! num_trees, num_rounds, num_nodes, objective length, objective padded to 4 bytes,
+//! iteration_indptr[num_rounds + 1], tree_info[num_trees], tree_weight[num_trees],
+//! tree_offsets[num_trees + 1], nodes[num_nodes]
+//! ```
+//!
+//! Nodes of tree `i` are `tree_offsets[i]..tree_offsets[i + 1]`, in the order of the
+//! original tree. A node is its left child (-1 for leaves), its right child, its split
+//! index with missing values going left when the highest bit is set, and its split
+//! condition or leaf value.
+
+use crate::errors::*;
+use crate::functions::{get_obj_function, ObjFunction};
+use crate::fvec::FVec;
+use crate::model_writer::ModelWriter;
+use crate::predictor::Predictor;
+use std::cmp;
+use std::convert::TryInto;
+use std::ops::Range;
+use std::sync::Arc;
+
+const MAGIC: &[u8; 4] = b"XGBC";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 36;
+const NODE_SIZE: usize = 16;
+
+/// Writes `predictor` in the compact format
+pub(crate) fn write_compact<F: FVec, T: ModelWriter>(
+    predictor: &Predictor<F>,
+    writer: &mut T,
+) -> Result<()> {
+    if predictor.booster() == "gblinear" {
+        bail!("linear models can't be saved in the compact format");
+    }
+    let trees = predictor.trees();
+    let mut tree_offsets = vec![0i32];
+    for tree in trees.iter() {
+        if tree.num_roots() > 1 || tree.size_leaf_vector() > 1 {
+            bail!("trees with several roots or vector leaves can't be saved in the compact format");
+        }
+        if tree.nodes().iter().any(|node| node.is_categorical()) {
+            bail!("categorical splits can't be saved in the compact format");
+        }
+        tree_offsets.push(tree_offsets[tree_offsets.len() - 1] + tree.num_nodes() as i32);
+    }
+    let iteration_indptr: Vec<i32> = predictor
+        .iteration_indptr()
+        .iter()
+        .map(|end| *end as i32)
+        .collect();
+    let objective = predictor.objective().as_bytes();
+
+    writer.write_all(MAGIC)?;
+    writer.write_int_vec(&[
+        VERSION as i32,
+        predictor.num_feature() as i32,
+        predictor.num_output_group() as i32,
+    ])?;
+    writer.write_f32_le(predictor.base_score())?;
+    writer.write_int_vec(&[
+        trees.len() as i32,
+        iteration_indptr.len() as i32 - 1,
+        tree_offsets[tree_offsets.len() - 1],
+        objective.len() as i32,
+    ])?;
+    writer.write_all(objective)?;
+    writer.write_all(&[0u8; 3][..padding(objective.len())])?;
+    writer.write_int_vec(&iteration_indptr)?;
+    let tree_info: Vec<i32> = trees.iter().map(|tree| tree.group() as i32).collect();
+    writer.write_int_vec(&tree_info)?;
+    let tree_weight: Vec<f32> = trees.iter().map(|tree| tree.weight()).collect();
+    writer.write_float_vec(&tree_weight)?;
+    writer.write_int_vec(&tree_offsets)?;
+    for tree in trees.iter() {
+        for nid in 0..tree.num_nodes() {
+            let node = tree.node(nid).unwrap();
+            let (left, right, split, value) = match (node.left_child(), node.right_child()) {
+                (Some(left), Some(right)) => (
+                    left as i32,
+                    right as i32,
+                    node.split_index().unwrap() as u32 | ((node.default_left() as u32) << 31),
+                    node.split_condition().unwrap(),
+                ),
+                // deleted nodes have neither children nor value
+                _ => (-1, -1, 0, node.leaf_value().unwrap_or(0f32)),
+            };
+            writer.write_int_vec(&[left, right, split as i32])?;
+            writer.write_f32_le(value)?;
+        }
+    }
+    return Ok(());
+}
+
+fn padding(len: usize) -> usize {
+    return (4 - len % 4) % 4;
+}
+
+/// Tree model in the compact format, evaluated from the bytes `B` such as a `Vec<u8>` or
+/// a memory-mapped file
+pub struct CompactModel<B: AsRef<[u8]>> {
+    data: B,
+    num_feature: usize,
+    num_output_group: usize,
+    base_score: f32,
+    num_trees: usize,
+    num_rounds: usize,
+    objective: String,
+    obj_func: Arc<dyn ObjFunction>,
+    /// byte offsets of the sections
+    iteration_indptr: usize,
+    tree_info: usize,
+    tree_weight: usize,
+    tree_offsets: usize,
+    nodes: usize,
+}
+
+#[cfg(feature = "mmap")]
+impl CompactModel<memmap2::Mmap> {
+    /// Memory-maps the compact model saved at `path`.
+    /// The file must not be modified while the model is in use.
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<CompactModel<memmap2::Mmap>> {
+        return CompactModel::open_with_obj_function(path, None);
+    }
+
+    /// Memory-maps the compact model saved at `path`, transforming outputs with
+    /// `obj_func` instead of the objective function the model was saved with
+    pub fn open_with_obj_function<P: AsRef<std::path::Path>>(
+        path: P,
+        obj_func: Option<Arc<dyn ObjFunction>>,
+    ) -> Result<CompactModel<memmap2::Mmap>> {
+        let file = std::fs::File::open(path)?;
+        // the mapping is read-only, the caller guarantees the file is left untouched
+        let data = unsafe { memmap2::Mmap::map(&file)? };
+        return CompactModel::from_bytes_with_obj_function(data, obj_func);
+    }
+}
+
+impl<B: AsRef<[u8]>> CompactModel<B> {
+    /// Reads the compact model held by `data`. The whole model is checked once, so
+    /// predictions never read past its bytes nor loop.
+    pub fn from_bytes(data: B) -> Result<CompactModel<B>> {
+        return CompactModel::from_bytes_with_obj_function(data, None);
+    }
+
+    /// Reads the compact model held by `data`, transforming outputs with `obj_func`
+    /// instead of the objective function the model was saved with
+    pub fn from_bytes_with_obj_function(
+        data: B,
+        obj_func: Option<Arc<dyn ObjFunction>>,
+    ) -> Result<CompactModel<B>> {
+        let bytes = data.as_ref();
+        if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+            bail!("not a compact model");
+        }
+        let header = |i: usize| read_u32(bytes, 4 * i) as usize;
+        if header(1) != VERSION as usize {
+            bail!("unsupported compact model version {}", header(1));
+        }
+        let (num_feature, num_output_group, base_score) =
+            (header(2), header(3), read_f32(bytes, 16));
+        let (num_trees, num_rounds, num_nodes, objective_len) =
+            (header(5), header(6), header(7), header(8));
+
+        let iteration_indptr = HEADER_SIZE + objective_len + padding(objective_len);
+        let tree_info = iteration_indptr + 4 * (num_rounds + 1);
+        let tree_weight = tree_info + 4 * num_trees;
+        let tree_offsets = tree_weight + 4 * num_trees;
+        let nodes = tree_offsets + 4 * (num_trees + 1);
+        let size = nodes + NODE_SIZE * num_nodes;
+        if bytes.len() != size {
+            bail!(
+                "compact model of {} bytes but its header gives {} bytes",
+                bytes.len(),
+                size
+            );
+        }
+        let objective =
+            String::from_utf8(bytes[HEADER_SIZE..HEADER_SIZE + objective_len].to_vec())?;
+        let obj_func = match obj_func {
+            Some(obj_func) => obj_func,
+            None => get_obj_function(objective.as_bytes().to_vec())?,
+        };
+
+        let model = CompactModel {
+            data,
+            num_feature,
+            num_output_group,
+            base_score,
+            num_trees,
+            num_rounds,
+            objective,
+            obj_func,
+            iteration_indptr,
+            tree_info,
+            tree_weight,
+            tree_offsets,
+            nodes,
+        };
+        model.check(num_nodes)?;
+        return Ok(model);
+    }
+
+    fn check(&self, num_nodes: usize) -> Result<()> {
+        let indptr: Vec<usize> = (0..=self.num_rounds).map(|r| self.round_start(r)).collect();
+        if indptr[0] != 0
+            || indptr[self.num_rounds] != self.num_trees
+            || indptr.windows(2).any(|w| w[0] > w[1])
+        {
+            bail!("invalid boosting rounds in compact model");
+        }
+        let offsets: Vec<usize> = (0..=self.num_trees).map(|i| self.tree_start(i)).collect();
+        if offsets[0] != 0
+            || offsets[self.num_trees] != num_nodes
+            || offsets.windows(2).any(|w| w[0] >= w[1])
+        {
+            bail!("invalid tree offsets in compact model");
+        }
+        for i in 0..self.num_trees {
+            if self.group(i) >= self.num_output_group {
+                bail!("tree {} has an invalid output group", i);
+            }
+            // every node is reached at most once, so walking the tree always ends on a leaf
+            let size = offsets[i + 1] - offsets[i];
+            let mut visited = vec![false; size];
+            let mut stack = vec![0usize];
+            while let Some(nid) = stack.pop() {
+                if visited[nid] {
+                    bail!("node {} of tree {} is reached twice", nid, i);
+                }
+                visited[nid] = true;
+                let node = self.node(offsets[i] + nid);
+                let (left, right) = (read_i32(node, 0), read_i32(node, 4));
+                if left < 0 {
+                    continue;
+                }
+                for child in [left, right].iter() {
+                    if *child < 0 || *child as usize >= size {
+                        bail!("node {} of tree {} has invalid children", nid, i);
+                    }
+                    stack.push(*child as usize);
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    fn bytes(&self) -> &[u8] {
+        return self.data.as_ref();
+    }
+
+    fn round_start(&self, round: usize) -> usize {
+        return read_u32(self.bytes(), self.iteration_indptr + 4 * round) as usize;
+    }
+
+    fn group(&self, tree_index: usize) -> usize {
+        return read_u32(self.bytes(), self.tree_info + 4 * tree_index) as usize;
+    }
+
+    fn tree_weight(&self, tree_index: usize) -> f32 {
+        return read_f32(self.bytes(), self.tree_weight + 4 * tree_index);
+    }
+
+    fn tree_start(&self, tree_index: usize) -> usize {
+        return read_u32(self.bytes(), self.tree_offsets + 4 * tree_index) as usize;
+    }
+
+    fn node(&self, index: usize) -> &[u8] {
+        let begin = self.nodes + NODE_SIZE * index;
+        return &self.bytes()[begin..begin + NODE_SIZE];
+    }
+
+    /// Number of features the model was trained with
+    pub fn num_feature(&self) -> usize {
+        return self.num_feature;
+    }
+
+    /// Number of values predicted per instance
+    pub fn num_output_group(&self) -> usize {
+        return self.num_output_group;
+    }
+
+    pub fn num_trees(&self) -> usize {
+        return self.num_trees;
+    }
+
+    pub fn num_boosted_rounds(&self) -> usize {
+        return self.num_rounds;
+    }
+
+    /// Name of the objective the model was saved with
+    pub fn objective(&self) -> &str {
+        return &self.objective;
+    }
+
+    /// Global bias added to all predictions, in margin space
+    pub fn base_score(&self) -> f32 {
+        return self.base_score;
+    }
+
+    /// Trees of the first `ntree_limit` boosting rounds, 0 means all rounds
+    fn tree_range(&self, ntree_limit: usize) -> Range<usize> {
+        let end = if ntree_limit == 0 {
+            self.num_rounds
+        } else {
+            cmp::min(ntree_limit, self.num_rounds)
+        };
+        return 0..self.round_start(end);
+    }
+
+    /// Walks the tree `tree_index` down to the leaf reached by `feat`, returning the
+    /// leaf index and its node
+    fn leaf<F: FVec>(&self, tree_index: usize, feat: &F) -> (usize, &[u8]) {
+        let begin = self.tree_start(tree_index);
+        let mut nid = 0;
+        loop {
+            let node = self.node(begin + nid);
+            let left = read_i32(node, 0);
+            if left < 0 {
+                return (nid, node);
+            }
+            let split = read_u32(node, 8);
+            let go_left = match feat.fvalue((split & 0x7fff_ffff) as usize) {
+                None => split >> 31 != 0,
+                Some(fvalue) => fvalue < read_f32(node, 12),
+            };
+            nid = if go_left { left } else { read_i32(node, 4) } as usize;
+        }
+    }
+
+    /// Generates predictions for given feature vector, as `Predictor::predict` does
+    pub fn predict<F: FVec>(&self, feat: &F, output_margin: bool, ntree_limit: usize) -> Vec<f32> {
+        let mut preds = vec![0f32; self.num_output_group];
+        for i in self.tree_range(ntree_limit) {
+            let (_, leaf) = self.leaf(i, feat);
+            preds[self.group(i)] += self.tree_weight(i) * read_f32(leaf, 12);
+        }
+        for pred in preds.iter_mut() {
+            *pred += self.base_score;
+        }
+        return if !output_margin {
+            self.obj_func.vector(&preds)
+        } else {
+            preds
+        };
+    }
+
+    /// Predicts the leaf index of each tree, as `Predictor::predict_leaf` does
+    pub fn predict_leaf<F: FVec>(&self, feat: &F, ntree_limit: usize) -> Vec<usize> {
+        return self
+            .tree_range(ntree_limit)
+            .map(|i| self.leaf(i, feat).0)
+            .collect();
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    return u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+}
+
+fn read_i32(bytes: &[u8], offset: usize) -> i32 {
+    return read_u32(bytes, offset) as i32;
+}
+
+fn read_f32(bytes: &[u8], offset: usize) -> f32 {
+    return f32::from_bits(read_u32(bytes, offset));
+}
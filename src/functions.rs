@@ -1,4 +1,6 @@
 use crate::errors::*;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
 
 pub enum FunctionType {
     RankPairwise,
@@ -7,34 +9,106 @@ pub enum FunctionType {
     MultiSoftmax,
     MultiSoftprob,
     RegLinear,
+    RegSquaredError,
+    RegQuantileError,
+    CountPoisson,
 }
 
-/// interface of objective function
-pub struct ObjFunction {
-    pub vector: fn(&Vec<f32>) -> Vec<f32>,
-    pub scalar: fn(f32) -> f32,
+/// Interface of objective function, transforms margins into predictions
+pub trait ObjFunction: Send + Sync {
+    /// Transforms margins of all output groups of a single instance
+    fn vector(&self, preds: &[f32]) -> Vec<f32>;
+
+    /// Transforms the margin of a single output model
+    fn scalar(&self, pred: f32) -> f32;
+
+    /// Transforms `base_score` saved by xgboost 1.0+ into margin
+    fn prob_to_margin(&self, base_score: f32) -> f32 {
+        return base_score;
+    }
+
+    /// Transforms a margin back into `base_score` as saved by xgboost 1.0+,
+    /// the inverse of `prob_to_margin`
+    fn margin_to_prob(&self, base_score: f32) -> f32 {
+        return base_score;
+    }
+}
+
+/// Objective functions shipped with the crate
+struct BuiltinObjFunction {
+    vector: fn(&[f32]) -> Vec<f32>,
+    scalar: fn(f32) -> f32,
+    prob_to_margin: fn(f32) -> f32,
+    margin_to_prob: fn(f32) -> f32,
+}
+
+impl ObjFunction for BuiltinObjFunction {
+    fn vector(&self, preds: &[f32]) -> Vec<f32> {
+        return (self.vector)(preds);
+    }
+
+    fn scalar(&self, pred: f32) -> f32 {
+        return (self.scalar)(pred);
+    }
+
+    fn prob_to_margin(&self, base_score: f32) -> f32 {
+        return (self.prob_to_margin)(base_score);
+    }
+
+    fn margin_to_prob(&self, base_score: f32) -> f32 {
+        return (self.margin_to_prob)(base_score);
+    }
+}
+
+type Registry = RwLock<HashMap<String, Arc<dyn ObjFunction>>>;
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    return REGISTRY.get_or_init(|| RwLock::new(HashMap::new()));
+}
+
+/// Registers an objective function under `name`, used by models saved with this objective
+/// name. Registered functions take precedence over the builtin ones.
+pub fn register_obj_function(name: &str, obj_func: Arc<dyn ObjFunction>) {
+    registry()
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(name.to_string(), obj_func);
+}
+
+/// Removes an objective function registered with `register_obj_function`
+pub fn unregister_obj_function(name: &str) -> Option<Arc<dyn ObjFunction>> {
+    return registry()
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(name);
 }
 
 fn sigmoid(x: f32) -> f32 {
     1f32 / (1f32 + (-x).exp())
 }
 
-fn dump_vec(preds: &Vec<f32>) -> Vec<f32> {
-    return preds.clone();
+fn dump_vec(preds: &[f32]) -> Vec<f32> {
+    return preds.to_vec();
 }
 
 fn dump(pred: f32) -> f32 {
     return pred;
 }
 
+/// Inverse of sigmoid.
+fn logit(x: f32) -> f32 {
+    -(1f32 / x - 1f32).ln()
+}
+
 /// Logistic regression.
-fn logistic_vec(preds: &Vec<f32>) -> Vec<f32> {
-    return preds.into_iter().map(|x| sigmoid(*x)).collect();
+fn logistic_vec(preds: &[f32]) -> Vec<f32> {
+    return preds.iter().map(|x| sigmoid(*x)).collect();
 }
 
 /// Multiclass classification.
-fn multiclass_vec(preds: &Vec<f32>) -> Vec<f32> {
-    match preds.get(0) {
+fn multiclass_vec(preds: &[f32]) -> Vec<f32> {
+    match preds.first() {
         Option::Some(init) => {
             let (max_index, _max) =
                 preds
@@ -47,48 +121,104 @@ fn multiclass_vec(preds: &Vec<f32>) -> Vec<f32> {
             return vec![max_index as f32; 1];
         }
         // empty vector
-        Option::None => preds.clone(),
+        Option::None => preds.to_vec(),
     }
 }
 
-fn unimplemented(_pred: f32) -> f32 {
-    std::unimplemented!();
+/// Poisson regression, the margin is the log of the mean.
+fn exp_vec(preds: &[f32]) -> Vec<f32> {
+    return preds.iter().map(|x| x.exp()).collect();
+}
+
+fn exp(pred: f32) -> f32 {
+    return pred.exp();
+}
+
+fn ln(x: f32) -> f32 {
+    return x.ln();
+}
+
+/// Multiclass classification of a single margin, its class is the only one.
+fn single_class(_pred: f32) -> f32 {
+    return 0f32;
+}
+
+/// Multiclass classification (predicted probability) of a single margin.
+fn single_class_prob(_pred: f32) -> f32 {
+    return 1f32;
 }
 
 ///  Multiclass classification (predicted probability).
-fn multiclass_pred_prob_vec(preds: &Vec<f32>) -> Vec<f32> {
-    match preds.get(0) {
+fn multiclass_pred_prob_vec(preds: &[f32]) -> Vec<f32> {
+    match preds.first() {
         Option::Some(init) => {
             let max = preds.iter().fold(*init, |a, b| b.max(a));
             let sum: f32 = preds.iter().map(|x| (x - max).exp()).sum();
             return preds.iter().map(|x| (x - max).exp() / sum).collect();
         }
         // empty vector
-        Option::None => preds.clone(),
+        Option::None => preds.to_vec(),
     }
 }
 
-pub fn get_classify_function(tp: FunctionType) -> ObjFunction {
-    match tp {
-        FunctionType::RankPairwise | FunctionType::BinaryLogitraw | FunctionType::RegLinear => {
-            ObjFunction {
-                vector: dump_vec,
-                scalar: dump,
-            }
-        }
-        FunctionType::BinaryLogistic => ObjFunction {
+pub fn get_classify_function(tp: FunctionType) -> Arc<dyn ObjFunction> {
+    let func = match tp {
+        FunctionType::RankPairwise
+        | FunctionType::RegLinear
+        | FunctionType::RegSquaredError
+        | FunctionType::RegQuantileError => BuiltinObjFunction {
+            vector: dump_vec,
+            scalar: dump,
+            prob_to_margin: dump,
+            margin_to_prob: dump,
+        },
+        FunctionType::BinaryLogitraw => BuiltinObjFunction {
+            vector: dump_vec,
+            scalar: dump,
+            prob_to_margin: logit,
+            margin_to_prob: sigmoid,
+        },
+        FunctionType::BinaryLogistic => BuiltinObjFunction {
             vector: logistic_vec,
             scalar: sigmoid,
+            prob_to_margin: logit,
+            margin_to_prob: sigmoid,
+        },
+        FunctionType::CountPoisson => BuiltinObjFunction {
+            vector: exp_vec,
+            scalar: exp,
+            prob_to_margin: ln,
+            margin_to_prob: exp,
         },
-        FunctionType::MultiSoftmax => ObjFunction {
+        FunctionType::MultiSoftmax => BuiltinObjFunction {
             vector: multiclass_vec,
-            scalar: unimplemented,
+            scalar: single_class,
+            prob_to_margin: dump,
+            margin_to_prob: dump,
         },
-        FunctionType::MultiSoftprob => ObjFunction {
+        FunctionType::MultiSoftprob => BuiltinObjFunction {
             vector: multiclass_pred_prob_vec,
-            scalar: unimplemented,
+            scalar: single_class_prob,
+            prob_to_margin: dump,
+            margin_to_prob: dump,
         },
+    };
+    return Arc::new(func);
+}
+
+/// Looks up the objective function saved as `obj_name`, registered functions first
+pub fn get_obj_function(obj_name: Vec<u8>) -> Result<Arc<dyn ObjFunction>> {
+    if let Ok(name) = std::str::from_utf8(&obj_name) {
+        let registered = registry()
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(name)
+            .cloned();
+        if let Some(obj_func) = registered {
+            return Ok(obj_func);
+        }
     }
+    return Ok(get_classify_function(get_classify_func_type(obj_name)?));
 }
 
 pub fn get_classify_func_type(obj_name: Vec<u8>) -> Result<FunctionType> {
@@ -99,6 +229,9 @@ pub fn get_classify_func_type(obj_name: Vec<u8>) -> Result<FunctionType> {
         b"multi:softmax" => Ok(FunctionType::MultiSoftmax),
         b"multi:softprob" => Ok(FunctionType::MultiSoftprob),
         b"reg:linear" => Ok(FunctionType::RegLinear),
+        b"reg:squarederror" => Ok(FunctionType::RegSquaredError),
+        b"reg:quantileerror" => Ok(FunctionType::RegQuantileError),
+        b"count:poisson" => Ok(FunctionType::CountPoisson),
         _ => Err(Error::from_kind(ErrorKind::UnsupportedObjFunctionType(
             String::from_utf8(obj_name)?,
         ))),
@@ -107,12 +240,47 @@ pub fn get_classify_func_type(obj_name: Vec<u8>) -> Result<FunctionType> {
 
 #[cfg(test)]
 mod tests {
-    use crate::functions::get_classify_function;
     use crate::functions::FunctionType::BinaryLogistic;
+    use crate::functions::{
+        get_classify_function, get_obj_function, register_obj_function, unregister_obj_function,
+        FunctionType, ObjFunction,
+    };
+    use std::sync::Arc;
 
     #[test]
     fn test_get_classify_function() {
         let func = get_classify_function(BinaryLogistic);
-        (func.vector)(&vec![1.0f32, 4.6f32]);
+        func.vector(&[1.0f32, 4.6f32]);
+    }
+
+    #[test]
+    fn test_multiclass_scalar() {
+        for tp in [FunctionType::MultiSoftmax, FunctionType::MultiSoftprob] {
+            let func = get_classify_function(tp);
+            assert_eq!(vec![func.scalar(0.3f32)], func.vector(&[0.3f32]));
+        }
+    }
+
+    struct Doubled;
+
+    impl ObjFunction for Doubled {
+        fn vector(&self, preds: &[f32]) -> Vec<f32> {
+            return preds.iter().map(|x| self.scalar(*x)).collect();
+        }
+
+        fn scalar(&self, pred: f32) -> f32 {
+            return 2f32 * pred;
+        }
+    }
+
+    #[test]
+    fn test_register_obj_function() {
+        assert!(get_obj_function(b"test:doubled".to_vec()).is_err());
+        register_obj_function("test:doubled", Arc::new(Doubled));
+        let func = get_obj_function(b"test:doubled".to_vec()).unwrap();
+        assert_eq!(func.vector(&[1f32, 2f32]), vec![2f32, 4f32]);
+        assert_eq!(func.prob_to_margin(0.5f32), 0.5f32);
+        assert!(unregister_obj_function("test:doubled").is_some());
+        assert!(get_obj_function(b"test:doubled".to_vec()).is_err());
     }
 }
@@ -1,15 +1,15 @@
+use crate::errors::*;
 use std::collections::HashMap;
 use std::f32;
 
 pub trait ToFloat {
-    #[inline]
     fn to_double(&self) -> f32;
 }
 
 impl ToFloat for f32 {
     #[inline]
     fn to_double(&self) -> f32 {
-        return self.clone();
+        return *self;
     }
 }
 
@@ -24,9 +24,19 @@ impl ToFloat for f64 {
 pub trait FVec {
     /// get value for index
     fn fvalue(&self, index: usize) -> Option<f32>;
+
+    /// number of values of a dense vector, `None` for sparse vectors
+    fn dense_len(&self) -> Option<usize> {
+        return None;
+    }
+
+    /// largest index holding a value of a sparse vector, `None` if empty or unknown
+    fn max_index(&self) -> Option<usize> {
+        return None;
+    }
 }
 
-pub type FVecMap<T: ToFloat> = HashMap<usize, T>;
+pub type FVecMap<T> = HashMap<usize, T>;
 
 /// Feature vector based on vec
 pub struct FVecArray<T: ToFloat> {
@@ -34,10 +44,24 @@ pub struct FVecArray<T: ToFloat> {
     treats_zero_as_none: bool,
 }
 
+impl<T: ToFloat> FVecArray<T> {
+    /// `treats_zero_as_none` makes zeros missing values, as in sparse inputs
+    pub fn new(values: Vec<T>, treats_zero_as_none: bool) -> FVecArray<T> {
+        return FVecArray {
+            values,
+            treats_zero_as_none,
+        };
+    }
+}
+
 impl<T: ToFloat> FVec for FVecMap<T> {
     fn fvalue(&self, index: usize) -> Option<f32> {
         return Some(self.get(&index)?.to_double());
     }
+
+    fn max_index(&self) -> Option<usize> {
+        return self.keys().max().copied();
+    }
 }
 
 impl<T: ToFloat> FVec for FVecArray<T> {
@@ -53,4 +77,45 @@ impl<T: ToFloat> FVec for FVecArray<T> {
             }
         }
     }
+
+    fn dense_len(&self) -> Option<usize> {
+        return Some(self.values.len());
+    }
+}
+
+/// Feature vector of named values, names are resolved to feature indices once
+pub struct FVecNamed {
+    values: HashMap<usize, f32>,
+}
+
+impl FVecNamed {
+    /// Builds the feature vector from `(name, value)` pairs, `feature_index` maps feature
+    /// names to indices (see `Predictor::feature_index`)
+    pub fn new<S, T, I>(feature_index: &HashMap<String, usize>, values: I) -> Result<FVecNamed>
+    where
+        S: AsRef<str>,
+        T: ToFloat,
+        I: IntoIterator<Item = (S, T)>,
+    {
+        let values = values
+            .into_iter()
+            .map(|(name, value)| match feature_index.get(name.as_ref()) {
+                Some(index) => Ok((*index, value.to_double())),
+                None => Err(Error::from_kind(ErrorKind::UnknownFeatureName(
+                    name.as_ref().to_string(),
+                ))),
+            })
+            .collect::<Result<HashMap<usize, f32>>>()?;
+        return Ok(FVecNamed { values });
+    }
+}
+
+impl FVec for FVecNamed {
+    fn fvalue(&self, index: usize) -> Option<f32> {
+        return self.values.get(&index).copied();
+    }
+
+    fn max_index(&self) -> Option<usize> {
+        return self.values.keys().max().copied();
+    }
 }
@@ -0,0 +1,235 @@
+/// Format of model dumps
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DumpFormat {
+    /// xgboost's text format, `0:[f29<-9.53674316e-07] yes=1,no=2,missing=1`
+    Text,
+    /// xgboost's `dump_format="json"`, nested nodes with their children
+    Json,
+    /// Graphviz DOT graph, as xgboost's `to_graphviz`
+    Dot,
+}
+
+/// Type of a feature, decides how its splits are dumped
+#[derive(Clone, Copy, PartialEq)]
+pub enum FeatureType {
+    /// `i`, a 0/1 feature split on its presence
+    Indicator,
+    /// `q` or `float`
+    Quantitative,
+    /// `int`, thresholds are rounded up to integers
+    Integer,
+    /// `c`
+    Categorical,
+}
+
+impl FeatureType {
+    fn from_name(name: &str) -> FeatureType {
+        return match name {
+            "i" => FeatureType::Indicator,
+            "int" => FeatureType::Integer,
+            "c" => FeatureType::Categorical,
+            _ => FeatureType::Quantitative,
+        };
+    }
+}
+
+/// Names and types of the features used in dumps, as xgboost's feature map
+#[derive(Default)]
+pub struct FeatureMap {
+    features: Vec<(String, FeatureType)>,
+}
+
+impl FeatureMap {
+    /// Builds the feature map of `num_feature` features, features without a name are called
+    /// `f{index}` and features without a type are quantitative
+    pub fn new(names: &[String], types: &[String], num_feature: usize) -> FeatureMap {
+        let features = (0..num_feature)
+            .map(|i| {
+                let name = names.get(i).cloned().unwrap_or_else(|| format!("f{}", i));
+                let tp = types
+                    .get(i)
+                    .map_or(FeatureType::Quantitative, |tp| FeatureType::from_name(tp));
+                (name, tp)
+            })
+            .collect();
+        return FeatureMap { features };
+    }
+
+    /// Name of the feature `index`, `f{index}` if it is not in the map
+    pub fn name(&self, index: usize) -> String {
+        return match self.features.get(index) {
+            Some((name, _)) => name.clone(),
+            None => format!("f{}", index),
+        };
+    }
+
+    /// Type of the feature `index`, quantitative if it is not in the map
+    pub fn feature_type(&self, index: usize) -> FeatureType {
+        return self
+            .features
+            .get(index)
+            .map_or(FeatureType::Quantitative, |(_, tp)| *tp);
+    }
+}
+
+/// Condition of a split as shown in dumps
+pub enum DumpCondition {
+    /// split on the presence of an indicator feature
+    Indicator,
+    /// `fvalue < threshold` goes left
+    Threshold(String),
+    /// the chosen categories go right
+    Categories(Vec<usize>),
+}
+
+/// Split node as shown in dumps
+pub struct DumpSplit {
+    pub fname: String,
+    pub condition: DumpCondition,
+    pub left: i32,
+    pub right: i32,
+    pub missing: i32,
+}
+
+impl DumpSplit {
+    /// Children taken when the condition holds and when it does not
+    pub fn yes_no(&self) -> (i32, i32) {
+        return match self.condition {
+            // the feature is present when the value is not missing
+            DumpCondition::Indicator => {
+                let yes = if self.missing == self.left {
+                    self.right
+                } else {
+                    self.left
+                };
+                (yes, self.missing)
+            }
+            DumpCondition::Threshold(_) => (self.left, self.right),
+            DumpCondition::Categories(_) => (self.right, self.left),
+        };
+    }
+
+    /// Text of the split, `[f29<-9.53674316e-07] yes=1,no=2,missing=1`
+    pub fn to_text(&self) -> String {
+        let (yes, no) = self.yes_no();
+        return match &self.condition {
+            DumpCondition::Indicator => format!("[{}] yes={},no={}", self.fname, yes, no),
+            DumpCondition::Threshold(cond) => format!(
+                "[{}<{}] yes={},no={},missing={}",
+                self.fname, cond, yes, no, self.missing
+            ),
+            DumpCondition::Categories(cats) => format!(
+                "[{}:{{{}}}] yes={},no={},missing={}",
+                self.fname,
+                join(cats, ","),
+                yes,
+                no,
+                self.missing
+            ),
+        };
+    }
+
+    /// JSON members of the split following `nodeid` and `depth`
+    pub fn to_json(&self) -> String {
+        let (yes, no) = self.yes_no();
+        let fname = serde_json::Value::from(self.fname.as_str());
+        let cond = match &self.condition {
+            DumpCondition::Indicator => {
+                return format!("\"split\": {}, \"yes\": {}, \"no\": {}", fname, yes, no)
+            }
+            DumpCondition::Threshold(cond) => json_number(cond.clone()),
+            DumpCondition::Categories(cats) => format!("[{}]", join(cats, ", ")),
+        };
+        return format!(
+            "\"split\": {}, \"split_condition\": {}, \"yes\": {}, \"no\": {}, \"missing\": {}",
+            fname, cond, yes, no, self.missing
+        );
+    }
+
+    /// Label of the split node in DOT graphs
+    pub fn to_label(&self) -> String {
+        return match &self.condition {
+            DumpCondition::Indicator => self.fname.clone(),
+            DumpCondition::Threshold(cond) => format!("{}<{}", self.fname, cond),
+            DumpCondition::Categories(cats) => format!("{}:{{{}}}", self.fname, join(cats, ",")),
+        };
+    }
+}
+
+fn join<T: ToString>(values: &[T], separator: &str) -> String {
+    return values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<String>>()
+        .join(separator);
+}
+
+/// Escapes a label of a DOT graph
+pub fn escape_dot(label: &str) -> String {
+    return label.replace('\\', "\\\\").replace('"', "\\\"");
+}
+
+/// Formats `value` as C++ streams do with `setprecision(precision)`, i.e. `%.{precision}g`.
+/// xgboost dumps tree values with a precision of 9, enough to read back the same `f32`.
+pub fn format_float(value: f32, precision: usize) -> String {
+    if value.is_nan() {
+        return String::from("nan");
+    }
+    if value.is_infinite() {
+        return String::from(if value > 0f32 { "inf" } else { "-inf" });
+    }
+    if value == 0f32 {
+        return String::from(if value.is_sign_negative() { "-0" } else { "0" });
+    }
+    let precision = std::cmp::max(precision, 1);
+    let value = value as f64;
+    let scientific = format!("{:.*e}", precision - 1, value);
+    let (mantissa, exponent) = scientific.split_at(scientific.find('e').unwrap());
+    let exponent: i32 = exponent[1..].parse().unwrap();
+    return if exponent < -4 || exponent >= precision as i32 {
+        format!(
+            "{}e{}{:02}",
+            strip_zeros(mantissa),
+            if exponent < 0 { '-' } else { '+' },
+            exponent.abs()
+        )
+    } else {
+        let decimals = (precision as i32 - 1 - exponent) as usize;
+        strip_zeros(&format!("{:.*}", decimals, value)).to_string()
+    };
+}
+
+/// Turns a number formatted by `format_float` into JSON, which has no NaN or infinity
+pub fn json_number(number: String) -> String {
+    return match number.as_str() {
+        "nan" | "inf" | "-inf" => String::from("null"),
+        _ => number,
+    };
+}
+
+/// Removes trailing zeros of the fractional part, and the decimal point if nothing is left
+fn strip_zeros(number: &str) -> &str {
+    if !number.contains('.') {
+        return number;
+    }
+    return number.trim_end_matches('0').trim_end_matches('.');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_float() {
+        assert_eq!(format_float(-9.536743e-07, 9), "-9.53674316e-07");
+        assert_eq!(format_float(1.7121772, 9), "1.71217716");
+        assert_eq!(format_float(0.5, 9), "0.5");
+        assert_eq!(format_float(-2f32, 9), "-2");
+        assert_eq!(format_float(0f32, 9), "0");
+        assert_eq!(format_float(1234.5, 9), "1234.5");
+        assert_eq!(format_float(1.0e10, 9), "1e+10");
+        assert_eq!(format_float(0.0001, 9), "9.99999975e-05");
+        assert_eq!(format_float(0.12345678, 6), "0.123457");
+        assert_eq!(format_float(999999.9, 6), "1e+06");
+    }
+}
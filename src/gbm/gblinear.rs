@@ -1,7 +1,15 @@
 use crate::errors::*;
 use crate::fvec::FVec;
-use crate::gbm::grad_booster::GradBooster;
+use crate::gbm::dump::{format_float, json_number, DumpFormat, FeatureMap};
+use crate::gbm::gbtree::GBTree;
+use crate::gbm::grad_booster::{GradBooster, ImportanceType};
+use crate::gbm::Tree;
+use crate::json;
 use crate::model_reader::ModelReader;
+use crate::model_writer::ModelWriter;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::Write;
 
 struct ModelParam {
     /// number of features
@@ -35,7 +43,7 @@ pub struct GBLinear {
 }
 
 impl GBLinear {
-    pub fn read_from<T: ModelReader>(with_pbuffer: bool, reader: &mut T) -> Result<Self> {
+    pub fn read_from<T: ModelReader>(_with_pbuffer: bool, reader: &mut T) -> Result<Self> {
         let mparam = ModelParam::read_from(reader)?;
         // read padding
         reader.read_i32_le()?;
@@ -44,6 +52,28 @@ impl GBLinear {
         Ok(GBLinear { mparam, weights })
     }
 
+    /// Reads the `model` object of a `gblinear` booster saved in the JSON model format
+    pub fn read_from_json(
+        model: &Value,
+        num_feature: usize,
+        num_output_group: usize,
+    ) -> Result<Self> {
+        let mparam = ModelParam {
+            num_feature,
+            num_output_group,
+        };
+        let weights = json::f32_vec(json::field(model, "weights")?)?;
+        if weights.len() != (num_feature + 1) * num_output_group {
+            bail!(
+                "expected {} linear weights but got {}",
+                (num_feature + 1) * num_output_group,
+                weights.len()
+            );
+        }
+
+        Ok(GBLinear { mparam, weights })
+    }
+
     fn bias(&self, gid: usize) -> f32 {
         self.weight(self.mparam.num_feature, gid)
     }
@@ -53,7 +83,7 @@ impl GBLinear {
     }
 
     fn pred<F: FVec>(&self, feat: &F, gid: usize) -> f32 {
-        let mut psum = self.bias(gid) as f32;
+        let mut psum = self.bias(gid);
         for fid in 0..self.mparam.num_feature {
             match feat.fvalue(fid) {
                 None => {}
@@ -67,20 +97,187 @@ impl GBLinear {
 }
 
 impl<F: FVec> GradBooster<F> for GBLinear {
-    fn predict(&self, feat: &F, ntree_limit: usize) -> Vec<f32> {
+    fn predict(&self, feat: &F, _root_index: usize, _iteration_range: (usize, usize)) -> Vec<f32> {
         (0..self.mparam.num_output_group)
             .map(|gid| self.pred(feat, gid))
             .collect()
     }
 
-    fn predict_single(&self, feat: &F, ntree_limit: usize) -> f32 {
+    fn predict_single(
+        &self,
+        feat: &F,
+        _root_index: usize,
+        _iteration_range: (usize, usize),
+    ) -> f32 {
         if self.mparam.num_output_group != 1 {
             panic!("Can't invoke predict_single() because this model outputs multiple values");
         }
         self.pred(feat, 0)
     }
 
-    fn predict_leaf(&self, feat: &F, ntree_limit: usize) -> Vec<usize> {
-        unimplemented!("gblinear does not support predict leaf index")
+    fn predict_leaf(
+        &self,
+        _feat: &F,
+        _root_index: usize,
+        _iteration_range: (usize, usize),
+    ) -> Result<Vec<usize>> {
+        bail!("gblinear does not support predict leaf index, it has no trees")
+    }
+
+    fn predict_contributions(
+        &self,
+        feat: &F,
+        _root_index: usize,
+        _iteration_range: (usize, usize),
+    ) -> Result<Vec<f32>> {
+        let num_feature = self.mparam.num_feature;
+        let mut contribs = vec![0f32; self.mparam.num_output_group * (num_feature + 1)];
+        for (gid, group) in contribs.chunks_mut(num_feature + 1).enumerate() {
+            for (fid, contrib) in group[..num_feature].iter_mut().enumerate() {
+                if let Some(fvalue) = feat.fvalue(fid) {
+                    *contrib = fvalue * self.weight(fid, gid);
+                }
+            }
+            group[num_feature] = self.bias(gid);
+        }
+        Ok(contribs)
+    }
+
+    fn predict_staged(&self, _feat: &F, _root_index: usize) -> Result<Vec<Vec<f32>>> {
+        bail!("gblinear does not support staged prediction, it has no boosting rounds")
+    }
+
+    fn num_roots(&self) -> usize {
+        1
+    }
+
+    fn num_boosted_rounds(&self) -> usize {
+        // all rounds update the same weights, a linear model can't be limited to some of them
+        0
+    }
+
+    fn name(&self) -> &'static str {
+        "gblinear"
+    }
+
+    fn num_output_group(&self) -> usize {
+        self.mparam.num_output_group
+    }
+
+    fn num_trees(&self) -> usize {
+        0
+    }
+
+    fn tree(&self, _index: usize) -> Option<Tree<'_>> {
+        None
+    }
+
+    fn trees(&self) -> Vec<Tree<'_>> {
+        vec![]
+    }
+
+    fn iteration_indptr(&self) -> &[usize] {
+        &[0]
+    }
+
+    fn linear_weights(&self) -> Option<&[f32]> {
+        Some(&self.weights)
+    }
+
+    fn slice(&self, _begin: usize, _end: usize, _step: usize) -> Result<Box<dyn GradBooster<F>>> {
+        bail!("gblinear has no trees to slice")
+    }
+
+    fn drop_trees(&self, _tree_indices: &[usize]) -> Result<Box<dyn GradBooster<F>>> {
+        bail!("gblinear has no trees to drop")
+    }
+
+    fn keep_output_groups(&self, groups: &[usize]) -> Result<Box<dyn GradBooster<F>>> {
+        let mparam = ModelParam {
+            num_feature: self.mparam.num_feature,
+            num_output_group: groups.len(),
+        };
+        let weights = (0..=self.mparam.num_feature)
+            .flat_map(|fid| groups.iter().map(move |gid| self.weight(fid, *gid)))
+            .collect();
+        Ok(Box::new(GBLinear { mparam, weights }))
+    }
+
+    fn as_gbtree(&self) -> Option<&GBTree> {
+        None
+    }
+
+    fn write_to(&self, writer: &mut dyn Write) -> Result<()> {
+        writer.write_int_vec(&[
+            self.mparam.num_feature as i32,
+            self.mparam.num_output_group as i32,
+        ])?;
+        writer.write_int_vec(&[0i32; 32])?;
+        // the weights are saved as a vector, prefixed with its 64-bit size
+        writer.write_i64_le(self.weights.len() as i64)?;
+        writer.write_float_vec(&self.weights)
+    }
+
+    fn to_json(&self) -> Result<Value> {
+        Ok(json!({
+            "model": {
+                "boosted_rounds": 0,
+                "weights": json::from_f32_vec(&self.weights)?,
+            },
+            "name": "gblinear",
+        }))
+    }
+
+    fn feature_importance(&self, importance_type: ImportanceType) -> Result<HashMap<usize, f32>> {
+        if importance_type != ImportanceType::Weight {
+            bail!(
+                "gblinear only supports the weight importance, not {:?}",
+                importance_type
+            );
+        }
+        if self.mparam.num_output_group != 1 {
+            bail!(
+                "gblinear has {} coefficients per feature, one per output group",
+                self.mparam.num_output_group
+            );
+        }
+        Ok((0..self.mparam.num_feature)
+            .map(|fid| (fid, self.weight(fid, 0)))
+            .collect())
+    }
+
+    fn dump(
+        &self,
+        _fmap: &FeatureMap,
+        _with_stats: bool,
+        format: DumpFormat,
+    ) -> Result<Vec<String>> {
+        // xgboost prints weights with the default stream precision of 6
+        let num_output_group = self.mparam.num_output_group;
+        let bias: Vec<String> = (0..num_output_group)
+            .map(|gid| format_float(self.bias(gid), 6))
+            .collect();
+        let weights: Vec<String> = (0..self.mparam.num_feature)
+            .flat_map(|fid| (0..num_output_group).map(move |gid| (fid, gid)))
+            .map(|(fid, gid)| format_float(self.weight(fid, gid), 6))
+            .collect();
+        let result = match format {
+            DumpFormat::Text => format!(
+                "bias:\n{}\nweight:\n{}\n",
+                bias.join("\n"),
+                weights.join("\n")
+            ),
+            DumpFormat::Json => {
+                let bias: Vec<String> = bias.into_iter().map(json_number).collect();
+                let weights: Vec<String> = weights.into_iter().map(json_number).collect();
+                format!(
+                    "  {{ \"bias\": [\n      {}\n    ],\n    \"weight\": [\n      {}\n    ]\n  }}",
+                    bias.join(",\n      "),
+                    weights.join(",\n      ")
+                )
+            }
+            DumpFormat::Dot => bail!("gblinear can't be dumped as a graph"),
+        };
+        Ok(vec![result])
     }
 }
@@ -1,16 +1,28 @@
 use std::cmp;
+use std::collections::HashMap;
+use std::ops::Range;
 
 use crate::errors::*;
 use crate::fvec::FVec;
-use crate::gbm::grad_booster::GradBooster;
+use crate::gbm::dump::{DumpFormat, FeatureMap};
+use crate::gbm::grad_booster::{GradBooster, ImportanceType};
 use crate::gbm::regtree::RegTree;
+use crate::gbm::Tree;
+use crate::json;
 use crate::model_reader::ModelReader;
+use crate::model_writer::ModelWriter;
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
 
+#[derive(Clone)]
 struct ModelParam {
     /// number of trees
     num_trees: i32,
     /// number of root: default 0, means single tree
     num_roots: i32,
+    /// number of trees of each output group per boosting round,
+    /// which xgboost 1.0 and later save in the slot of `num_roots`
+    num_parallel_tree: usize,
     /// number of features to be used by trees
     num_feature: i32,
     /// size of predicton buffer allocated used for buffering
@@ -24,7 +36,7 @@ struct ModelParam {
 }
 
 impl ModelParam {
-    fn new<T: ModelReader>(reader: &mut T) -> Result<ModelParam> {
+    fn new<T: ModelReader>(reader: &mut T, major_version: u32) -> Result<ModelParam> {
         let (num_trees, num_roots, num_feature) = (
             reader.read_i32_le()?,
             reader.read_i32_le()?,
@@ -41,9 +53,16 @@ impl ModelParam {
         reader.read_to_i32_buffer(&mut reserved)?;
         // read padding
         reader.read_i32_le()?;
+        // models of xgboost 1.0 and later have a single root
+        let (num_roots, num_parallel_tree) = if major_version == 0 {
+            (num_roots, 1)
+        } else {
+            (cmp::min(num_roots, 1), cmp::max(num_roots, 1) as usize)
+        };
         return Ok(ModelParam {
             num_trees,
             num_roots,
+            num_parallel_tree,
             num_feature,
             num_pbuffer,
             num_output_group,
@@ -51,6 +70,23 @@ impl ModelParam {
         });
     }
 
+    fn write_to<T: ModelWriter + ?Sized>(&self, writer: &mut T) -> Result<()> {
+        let num_roots = if self.num_parallel_tree > 1 {
+            self.num_parallel_tree as i32
+        } else {
+            self.num_roots
+        };
+        writer.write_int_vec(&[self.num_trees, num_roots, self.num_feature])?;
+        // write padding
+        writer.write_i32_le(0)?;
+        // the prediction buffer is never saved
+        writer.write_i64_le(0)?;
+        writer.write_int_vec(&[self.num_output_group as i32, self.size_leaf_vector as i32])?;
+        writer.write_int_vec(&[0i32; 31])?;
+        // write padding
+        return writer.write_i32_le(0);
+    }
+
     pub fn pred_buffer_size(&self) -> usize {
         return self.num_pbuffer * (self.num_output_group) * (self.size_leaf_vector + 1);
     }
@@ -60,47 +96,77 @@ impl ModelParam {
 pub struct GBTree {
     mparam: ModelParam,
     trees: Vec<RegTree>,
+    /// output group of each tree
     tree_info: Vec<i32>,
-    group_trees: Vec<Vec<RegTree>>,
+    /// trees of boosting round `i` are `iteration_indptr[i]..iteration_indptr[i + 1]`
+    iteration_indptr: Vec<usize>,
     // use only in DART tree
     weight_drop: Option<Vec<f32>>,
 }
 
 impl GBTree {
-    fn parse_group_trees(
-        num_output_group: usize,
-        tree_info: &Vec<i32>,
-        trees: &Vec<RegTree>,
-    ) -> Vec<Vec<RegTree>> {
-        (0..num_output_group)
-            .map(|i| {
-                (0..tree_info.len())
-                    .filter(|j| tree_info[*j] == i as i32)
-                    .map(|j| trees[j].clone())
-                    .collect()
-            })
-            .collect()
+    /// Splits trees into boosting rounds of `trees_per_round` trees
+    fn build_iteration_indptr(num_trees: usize, trees_per_round: usize) -> Vec<usize> {
+        let trees_per_round = cmp::max(trees_per_round, 1);
+        let mut indptr: Vec<usize> = (0..num_trees).step_by(trees_per_round).collect();
+        indptr.push(num_trees);
+        indptr
     }
 
-    pub fn read_from<T: ModelReader>(
+    fn check_tree_info(tree_info: &[i32], num_trees: usize, num_output_group: usize) -> Result<()> {
+        if tree_info.len() != num_trees {
+            bail!(
+                "model has {} trees but tree_info of size {}",
+                num_trees,
+                tree_info.len()
+            );
+        }
+        if let Some(&gid) = tree_info
+            .iter()
+            .find(|gid| **gid < 0 || **gid as usize >= num_output_group)
+        {
+            bail!(
+                "tree output group {} is out of range for {} output groups",
+                gid,
+                num_output_group
+            );
+        }
+        Ok(())
+    }
+
+    /// Reads a binary model, `major_version` is the version of xgboost 1.0 and later
+    /// the model was saved with, 0 before
+    pub fn read_from<T: ModelReader + BufRead>(
         with_pbuffer: bool,
         reader: &mut T,
         is_dart: bool,
+        major_version: u32,
     ) -> Result<Self> {
-        let mparam = ModelParam::new(reader)?;
+        let mparam = ModelParam::new(reader, major_version)?;
         let trees_result: Result<Vec<RegTree>> = (0..mparam.num_trees)
             .map(|_| RegTree::read_from(reader))
             .collect();
         let trees = trees_result?;
+        let num_roots = cmp::max(mparam.num_roots, 1) as usize;
+        if let Some(tree) = trees.iter().find(|t| t.num_roots() < num_roots) {
+            bail!(
+                "tree has {} roots but model has {} roots",
+                tree.num_roots(),
+                num_roots
+            );
+        }
 
         let tree_info = reader.read_int_vec(mparam.num_trees as usize)?;
+        GBTree::check_tree_info(&tree_info, trees.len(), mparam.num_output_group)?;
 
         if mparam.num_pbuffer != 0 && with_pbuffer {
             reader.skip(8 * mparam.pred_buffer_size())?;
         }
 
-        let group_trees =
-            GBTree::parse_group_trees(mparam.num_output_group as usize, &tree_info, &trees);
+        let iteration_indptr = GBTree::build_iteration_indptr(
+            trees.len(),
+            mparam.num_output_group * mparam.num_parallel_tree,
+        );
 
         let weight_drop = if is_dart {
             // if gbtree.mparam.num_trees != 0 {
@@ -114,90 +180,550 @@ impl GBTree {
             mparam,
             trees,
             tree_info,
-            group_trees,
+            iteration_indptr,
             weight_drop,
         })
     }
 
-    fn pred<F: FVec>(
-        &self,
-        feat: &F,
-        bst_group: usize,
-        root_index: usize,
-        ntree_limit: usize,
-    ) -> f32 {
-        match &self.weight_drop {
-            None => self.pred_as_gbtree(feat, bst_group, root_index, ntree_limit),
-            Some(weight_drop) => {
-                self.pred_as_dart(feat, &weight_drop, bst_group, root_index, ntree_limit)
+    /// Reads the `model` object of a `gbtree` booster saved in the JSON model format
+    pub fn read_from_json(
+        model: &Value,
+        num_feature: usize,
+        num_output_group: usize,
+        weight_drop: Option<Vec<f32>>,
+    ) -> Result<Self> {
+        let trees = json::as_array(json::field(model, "trees")?)?
+            .iter()
+            .map(RegTree::read_from_json)
+            .collect::<Result<Vec<RegTree>>>()?;
+        let tree_info = json::i32_vec(json::field(model, "tree_info")?)?;
+        GBTree::check_tree_info(&tree_info, trees.len(), num_output_group)?;
+        if let Some(weight_drop) = &weight_drop {
+            if weight_drop.len() != trees.len() {
+                bail!(
+                    "model has {} trees but weight_drop of size {}",
+                    trees.len(),
+                    weight_drop.len()
+                );
+            }
+        }
+
+        // trees of `multi_output_tree` models output all groups at once
+        let size_leaf_vector = trees
+            .iter()
+            .map(|t| t.size_leaf_vector())
+            .max()
+            .unwrap_or(1);
+        if size_leaf_vector > 1 {
+            if let Some(tree) = trees
+                .iter()
+                .find(|t| t.size_leaf_vector() != num_output_group)
+            {
+                bail!(
+                    "multi-target tree has {} targets but model has {} output groups",
+                    tree.size_leaf_vector(),
+                    num_output_group
+                );
             }
         }
+
+        let num_parallel_tree = match json::opt_field(model, "gbtree_model_param") {
+            Some(param) => {
+                json::opt_field(param, "num_parallel_tree").map_or(Ok(1), json::as_usize)?
+            }
+            None => 1,
+        };
+        // xgboost 2.0+ saves the boundaries of boosting rounds
+        let iteration_indptr = match json::opt_field(model, "iteration_indptr") {
+            Some(indptr) if !json::as_array(indptr)?.is_empty() => {
+                let indptr = json::as_array(indptr)?
+                    .iter()
+                    .map(json::as_usize)
+                    .collect::<Result<Vec<usize>>>()?;
+                if indptr[0] != 0
+                    || indptr[indptr.len() - 1] != trees.len()
+                    || indptr.windows(2).any(|w| w[0] > w[1])
+                {
+                    bail!("invalid iteration_indptr for {} trees", trees.len());
+                }
+                indptr
+            }
+            _ => {
+                let groups_per_tree = if size_leaf_vector > 1 {
+                    1
+                } else {
+                    num_output_group
+                };
+                GBTree::build_iteration_indptr(trees.len(), groups_per_tree * num_parallel_tree)
+            }
+        };
+
+        let mparam = ModelParam {
+            num_trees: trees.len() as i32,
+            num_roots: 1,
+            num_parallel_tree: cmp::max(num_parallel_tree, 1),
+            num_feature: num_feature as i32,
+            num_pbuffer: 0,
+            num_output_group,
+            size_leaf_vector: if size_leaf_vector > 1 {
+                size_leaf_vector
+            } else {
+                0
+            },
+        };
+
+        Ok(GBTree {
+            mparam,
+            trees,
+            tree_info,
+            iteration_indptr,
+            weight_drop,
+        })
+    }
+
+    /// Whether the model was read with a prediction buffer, saved by xgboost before 0.6
+    pub fn saved_pred_buffer(&self) -> bool {
+        self.mparam.num_pbuffer != 0
+    }
+
+    /// Number of trees of each output group per boosting round, more than 1 for random forests
+    pub fn num_parallel_tree(&self) -> usize {
+        self.mparam.num_parallel_tree
     }
 
-    fn pred_as_dart<F: FVec>(
+    /// Number of boosting rounds, a round holds one tree per output group
+    /// (times `num_parallel_tree`), or a single tree with vector leaves
+    pub fn num_boosted_rounds(&self) -> usize {
+        self.iteration_indptr.len() - 1
+    }
+
+    /// Builds a model of the trees of `rounds`, each holding the trees of a boosting round.
+    /// Rounds left without trees are dropped, `group_of` maps the output group of each tree.
+    fn select<G: Fn(i32) -> i32>(
         &self,
-        feat: &F,
-        weight_drop: &Vec<f32>,
-        bst_group: usize,
-        root_index: usize,
-        ntree_limit: usize,
-    ) -> f32 {
-        let trees = self.group_trees[bst_group].clone();
-        let treeleft = if ntree_limit == 0 {
-            trees.len()
+        rounds: &[Vec<usize>],
+        num_output_group: usize,
+        group_of: G,
+    ) -> GBTree {
+        let mut trees = vec![];
+        let mut tree_info = vec![];
+        let mut weight_drop = self.weight_drop.as_ref().map(|_| vec![]);
+        let mut iteration_indptr = vec![0];
+        for round in rounds.iter().filter(|round| !round.is_empty()) {
+            for &i in round.iter() {
+                trees.push(self.trees[i].clone());
+                tree_info.push(group_of(self.tree_info[i]));
+                if let Some(weight_drop) = &mut weight_drop {
+                    weight_drop.push(self.tree_weight(i));
+                }
+            }
+            iteration_indptr.push(trees.len());
+        }
+        GBTree {
+            mparam: ModelParam {
+                num_trees: trees.len() as i32,
+                num_output_group,
+                ..self.mparam.clone()
+            },
+            trees,
+            tree_info,
+            iteration_indptr,
+            weight_drop,
+        }
+    }
+
+    /// Concatenates the trees of `models`, the outputs of each model scaled by its weight.
+    /// Boosting rounds of the first model come first.
+    pub fn merge(models: &[(&GBTree, f32)]) -> Result<GBTree> {
+        let (first, _) = match models.first() {
+            Some(model) => *model,
+            None => bail!("no models to merge"),
+        };
+        for (model, _) in models.iter() {
+            if model.mparam.num_output_group != first.mparam.num_output_group {
+                bail!(
+                    "can't merge models with {} and {} output groups",
+                    first.mparam.num_output_group,
+                    model.mparam.num_output_group
+                );
+            }
+            if (model.mparam.size_leaf_vector > 1) != (first.mparam.size_leaf_vector > 1) {
+                bail!("can't merge multi-target trees with scalar trees");
+            }
+            let num_roots = |model: &GBTree| cmp::max(model.mparam.num_roots, 1);
+            if num_roots(model) != num_roots(first) {
+                bail!(
+                    "can't merge trees with {} and {} roots",
+                    num_roots(first),
+                    num_roots(model)
+                );
+            }
+        }
+
+        let is_dart = models.iter().any(|(model, _)| model.weight_drop.is_some());
+        let mut trees = vec![];
+        let mut tree_info = vec![];
+        let mut weight_drop = if is_dart { Some(vec![]) } else { None };
+        let mut iteration_indptr = vec![0];
+        for (model, weight) in models.iter() {
+            for (i, tree) in model.trees.iter().enumerate() {
+                let mut tree = tree.clone();
+                tree.scale(*weight);
+                trees.push(tree);
+                if let Some(weight_drop) = &mut weight_drop {
+                    weight_drop.push(model.tree_weight(i));
+                }
+            }
+            tree_info.extend_from_slice(&model.tree_info);
+            let offset = iteration_indptr[iteration_indptr.len() - 1];
+            iteration_indptr.extend(model.iteration_indptr[1..].iter().map(|end| offset + end));
+        }
+        Ok(GBTree {
+            mparam: ModelParam {
+                num_trees: trees.len() as i32,
+                ..first.mparam.clone()
+            },
+            trees,
+            tree_info,
+            iteration_indptr,
+            weight_drop,
+        })
+    }
+
+    /// Tree indices of each boosting round
+    fn rounds(&self) -> Vec<Vec<usize>> {
+        self.iteration_indptr
+            .windows(2)
+            .map(|w| (w[0]..w[1]).collect())
+            .collect()
+    }
+
+    /// Converts boosting rounds `begin..end` into the range of trees.
+    /// `end == 0` means all rounds, rounds past the last one are ignored.
+    fn tree_range(&self, begin: usize, end: usize) -> Range<usize> {
+        let num_rounds = self.num_boosted_rounds();
+        let end = if end == 0 {
+            num_rounds
         } else {
-            cmp::min(ntree_limit, trees.len())
+            cmp::min(end, num_rounds)
         };
-        (0..treeleft)
-            .map(|i| weight_drop[i] * trees[i].get_leaf_value(feat, root_index))
-            .sum()
+        let begin = cmp::min(begin, end);
+        self.iteration_indptr[begin]..self.iteration_indptr[end]
+    }
+
+    fn tree_weight(&self, tree_index: usize) -> f32 {
+        match &self.weight_drop {
+            None => 1f32,
+            Some(weight_drop) => weight_drop[tree_index],
+        }
     }
 
-    fn pred_as_gbtree<F: FVec>(
+    /// Sums the outputs of the trees of boosting rounds `begin..end` for each output group
+    fn pred<F: FVec>(&self, feat: &F, root_index: usize, begin: usize, end: usize) -> Vec<f32> {
+        let mut preds = vec![0f32; self.mparam.num_output_group];
+        for i in self.tree_range(begin, end) {
+            self.add_tree_output(&mut preds, i, feat, root_index);
+        }
+        preds
+    }
+
+    /// Adds the (DART weighted) output of the tree `tree_index` to its output group
+    fn add_tree_output<F: FVec>(
         &self,
+        preds: &mut [f32],
+        tree_index: usize,
         feat: &F,
-        bst_group: usize,
         root_index: usize,
-        ntree_limit: usize,
-    ) -> f32 {
-        let trees = self.group_trees[bst_group].clone();
-        let treeleft = if ntree_limit == 0 {
-            trees.len()
+    ) {
+        let weight = self.tree_weight(tree_index);
+        let tree = &self.trees[tree_index];
+        if self.mparam.size_leaf_vector > 1 {
+            // vector leaves contribute to every group
+            let leaf = tree.get_leaf_vector(feat, root_index);
+            for (pred, value) in preds.iter_mut().zip(leaf.iter()) {
+                *pred += weight * value;
+            }
         } else {
-            cmp::min(ntree_limit, trees.len())
-        };
-        (0..treeleft)
-            .map(|i| trees[i].get_leaf_value(feat, root_index))
-            .sum()
+            preds[self.tree_info[tree_index] as usize] +=
+                weight * tree.get_leaf_value(feat, root_index);
+        }
     }
 
-    fn pred_path<F: FVec>(&self, feat: &F, root_index: usize, ntree_limit: usize) -> Vec<usize> {
-        let treeleft = if ntree_limit == 0 {
-            self.trees.len()
-        } else {
-            ntree_limit
-        };
-        (0..treeleft)
+    /// Cumulative sums of tree outputs after each boosting round
+    fn pred_staged<F: FVec>(&self, feat: &F, root_index: usize) -> Vec<Vec<f32>> {
+        let mut preds = vec![0f32; self.mparam.num_output_group];
+        (0..self.num_boosted_rounds())
+            .map(|round| {
+                for i in self.tree_range(round, round + 1) {
+                    self.add_tree_output(&mut preds, i, feat, root_index);
+                }
+                preds.clone()
+            })
+            .collect()
+    }
+
+    fn pred_path<F: FVec>(
+        &self,
+        feat: &F,
+        root_index: usize,
+        begin: usize,
+        end: usize,
+    ) -> Vec<usize> {
+        self.tree_range(begin, end)
             .map(|i| self.trees[i].get_leaf_index(feat, root_index))
             .collect()
     }
 }
 
 impl<F: FVec> GradBooster<F> for GBTree {
-    fn predict(&self, feat: &F, ntree_limit: usize) -> Vec<f32> {
-        (0..self.mparam.num_output_group)
-            .map(|gid| self.pred(feat, gid as usize, 0, ntree_limit))
-            .collect()
+    fn predict(&self, feat: &F, root_index: usize, iteration_range: (usize, usize)) -> Vec<f32> {
+        self.pred(feat, root_index, iteration_range.0, iteration_range.1)
     }
 
-    fn predict_single(&self, feat: &F, ntree_limit: usize) -> f32 {
+    fn predict_single(&self, feat: &F, root_index: usize, iteration_range: (usize, usize)) -> f32 {
         if self.mparam.num_output_group != 1 {
             panic!("Can't invoke predict_single() because this model outputs multiple values");
         }
-        self.pred(feat, 0, 0, ntree_limit)
+        self.pred(feat, root_index, iteration_range.0, iteration_range.1)[0]
+    }
+
+    fn predict_leaf(
+        &self,
+        feat: &F,
+        root_index: usize,
+        iteration_range: (usize, usize),
+    ) -> Result<Vec<usize>> {
+        Ok(self.pred_path(feat, root_index, iteration_range.0, iteration_range.1))
+    }
+
+    fn predict_contributions(
+        &self,
+        feat: &F,
+        root_index: usize,
+        iteration_range: (usize, usize),
+    ) -> Result<Vec<f32>> {
+        if self.mparam.size_leaf_vector > 1 {
+            bail!("contributions of multi-target trees are not supported");
+        }
+        let num_columns = self.mparam.num_feature as usize + 1;
+        let mut contribs = vec![0f32; self.mparam.num_output_group * num_columns];
+        for i in self.tree_range(iteration_range.0, iteration_range.1) {
+            let begin = self.tree_info[i] as usize * num_columns;
+            self.trees[i].add_contributions(
+                feat,
+                root_index,
+                self.tree_weight(i),
+                &mut contribs[begin..begin + num_columns],
+            );
+        }
+        Ok(contribs)
+    }
+
+    fn predict_staged(&self, feat: &F, root_index: usize) -> Result<Vec<Vec<f32>>> {
+        Ok(self.pred_staged(feat, root_index))
+    }
+
+    fn num_roots(&self) -> usize {
+        // 0 is saved by models with a single root
+        cmp::max(self.mparam.num_roots, 1) as usize
+    }
+
+    fn num_boosted_rounds(&self) -> usize {
+        GBTree::num_boosted_rounds(self)
+    }
+
+    fn name(&self) -> &'static str {
+        if self.weight_drop.is_some() {
+            "dart"
+        } else {
+            "gbtree"
+        }
+    }
+
+    fn num_output_group(&self) -> usize {
+        self.mparam.num_output_group
+    }
+
+    fn num_trees(&self) -> usize {
+        self.trees.len()
+    }
+
+    fn tree(&self, index: usize) -> Option<Tree<'_>> {
+        let tree = self.trees.get(index)?;
+        Some(Tree::new(
+            tree,
+            self.tree_info[index] as usize,
+            self.tree_weight(index),
+        ))
+    }
+
+    fn trees(&self) -> Vec<Tree<'_>> {
+        self.trees
+            .iter()
+            .enumerate()
+            .map(|(i, tree)| Tree::new(tree, self.tree_info[i] as usize, self.tree_weight(i)))
+            .collect()
+    }
+
+    fn iteration_indptr(&self) -> &[usize] {
+        &self.iteration_indptr
+    }
+
+    fn linear_weights(&self) -> Option<&[f32]> {
+        None
+    }
+
+    fn slice(&self, begin: usize, end: usize, step: usize) -> Result<Box<dyn GradBooster<F>>> {
+        let num_rounds = self.num_boosted_rounds();
+        let end = if end == 0 {
+            num_rounds
+        } else {
+            cmp::min(end, num_rounds)
+        };
+        if begin > end {
+            return Err(Error::from_kind(ErrorKind::InvalidIterationRange(
+                begin, end,
+            )));
+        }
+        if step == 0 {
+            bail!("slice step must be positive");
+        }
+        let rounds = self.rounds();
+        let rounds: Vec<Vec<usize>> = (begin..end)
+            .step_by(step)
+            .map(|r| rounds[r].clone())
+            .collect();
+        Ok(Box::new(self.select(
+            &rounds,
+            self.mparam.num_output_group,
+            |gid| gid,
+        )))
+    }
+
+    fn drop_trees(&self, tree_indices: &[usize]) -> Result<Box<dyn GradBooster<F>>> {
+        if let Some(i) = tree_indices.iter().find(|i| **i >= self.trees.len()) {
+            bail!("tree {} is out of range for {} trees", i, self.trees.len());
+        }
+        let rounds: Vec<Vec<usize>> = self
+            .rounds()
+            .into_iter()
+            .map(|round| {
+                round
+                    .into_iter()
+                    .filter(|i| !tree_indices.contains(i))
+                    .collect()
+            })
+            .collect();
+        Ok(Box::new(self.select(
+            &rounds,
+            self.mparam.num_output_group,
+            |gid| gid,
+        )))
+    }
+
+    fn keep_output_groups(&self, groups: &[usize]) -> Result<Box<dyn GradBooster<F>>> {
+        if self.mparam.size_leaf_vector > 1 {
+            bail!("output groups of multi-target trees can't be separated");
+        }
+        let rounds: Vec<Vec<usize>> = self
+            .rounds()
+            .into_iter()
+            .map(|round| {
+                round
+                    .into_iter()
+                    .filter(|i| groups.contains(&(self.tree_info[*i] as usize)))
+                    .collect()
+            })
+            .collect();
+        Ok(Box::new(self.select(&rounds, groups.len(), |gid| {
+            groups.iter().position(|g| *g == gid as usize).unwrap() as i32
+        })))
+    }
+
+    fn as_gbtree(&self) -> Option<&GBTree> {
+        Some(self)
+    }
+
+    fn write_to(&self, writer: &mut dyn Write) -> Result<()> {
+        self.mparam.write_to(writer)?;
+        for tree in self.trees.iter() {
+            tree.write_to(writer)?;
+        }
+        writer.write_int_vec(&self.tree_info)?;
+        if let Some(weight_drop) = &self.weight_drop {
+            writer.write_i64_le(weight_drop.len() as i64)?;
+            writer.write_float_vec(weight_drop)?;
+        }
+        Ok(())
+    }
+
+    fn to_json(&self) -> Result<Value> {
+        let trees = self
+            .trees
+            .iter()
+            .enumerate()
+            .map(|(i, tree)| tree.to_json(i))
+            .collect::<Result<Vec<Value>>>()?;
+        let groups_per_tree = if self.mparam.size_leaf_vector > 1 {
+            1
+        } else {
+            self.mparam.num_output_group
+        };
+        let trees_per_round = self.iteration_indptr.get(1).cloned().unwrap_or(0);
+        let num_parallel_tree = cmp::max(trees_per_round / cmp::max(groups_per_tree, 1), 1);
+        let gbtree = json!({
+            "model": {
+                "gbtree_model_param": {
+                    "num_parallel_tree": json::param(num_parallel_tree),
+                    "num_trees": json::param(self.trees.len()),
+                },
+                "iteration_indptr": self.iteration_indptr,
+                "tree_info": self.tree_info,
+                "trees": trees,
+            },
+            "name": "gbtree",
+        });
+        Ok(match &self.weight_drop {
+            None => gbtree,
+            Some(weight_drop) => json!({
+                "gbtree": gbtree,
+                "name": "dart",
+                "weight_drop": json::from_f32_vec(weight_drop)?,
+            }),
+        })
+    }
+
+    fn feature_importance(&self, importance_type: ImportanceType) -> Result<HashMap<usize, f32>> {
+        // (number of splits, total gain, total cover) of each feature
+        let mut stats: HashMap<usize, (usize, f64, f64)> = HashMap::new();
+        for tree in self.trees.iter() {
+            for (split_index, gain, cover) in tree.split_stats() {
+                let stat = stats.entry(split_index).or_insert((0, 0f64, 0f64));
+                stat.0 += 1;
+                stat.1 += gain as f64;
+                stat.2 += cover as f64;
+            }
+        }
+        Ok(stats
+            .into_iter()
+            .map(|(fid, (count, gain, cover))| {
+                let score = match importance_type {
+                    ImportanceType::Weight => count as f64,
+                    ImportanceType::Gain => gain / count as f64,
+                    ImportanceType::Cover => cover / count as f64,
+                    ImportanceType::TotalGain => gain,
+                    ImportanceType::TotalCover => cover,
+                };
+                (fid, score as f32)
+            })
+            .collect())
     }
 
-    fn predict_leaf(&self, feat: &F, ntree_limit: usize) -> Vec<usize> {
-        self.pred_path(feat, 0, ntree_limit)
+    fn dump(&self, fmap: &FeatureMap, with_stats: bool, format: DumpFormat) -> Result<Vec<String>> {
+        Ok(self
+            .trees
+            .iter()
+            .map(|tree| tree.dump(fmap, with_stats, format))
+            .collect())
     }
 }
@@ -1,30 +1,152 @@
 use crate::errors::*;
 use crate::fvec::FVec;
+use crate::gbm::dump::{DumpFormat, FeatureMap};
 use crate::gbm::gblinear::GBLinear;
 use crate::gbm::gbtree::GBTree;
+use crate::gbm::Tree;
+use crate::json;
 use crate::model_reader::ModelReader;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+/// Kind of feature importance, as xgboost's `importance_type`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ImportanceType {
+    /// number of splits on the feature, or the coefficient of a linear model
+    Weight,
+    /// average gain of the splits on the feature
+    Gain,
+    /// average cover (sum of hessians) of the splits on the feature
+    Cover,
+    /// total gain of the splits on the feature
+    TotalGain,
+    /// total cover of the splits on the feature
+    TotalCover,
+}
 
 /// Interface of gradient boosting model
-pub trait GradBooster<F: FVec> {
+///
+/// `iteration_range` selects the boosting rounds `begin..end` to predict with,
+/// `end == 0` means all rounds.
+pub trait GradBooster<F: FVec>: Send + Sync {
     /// Generates predictions for given feature vector
-    fn predict(&self, feat: &F, ntree_limit: usize) -> Vec<f32>;
+    fn predict(&self, feat: &F, root_index: usize, iteration_range: (usize, usize)) -> Vec<f32>;
     /// Generates a prediction for given feature vector
-    fn predict_single(&self, feat: &F, ntree_limit: usize) -> f32;
+    fn predict_single(&self, feat: &F, root_index: usize, iteration_range: (usize, usize)) -> f32;
     /// Predicts the leaf index of each tree. This is only valid in gbtree predictor
-    fn predict_leaf(&self, feat: &F, ntree_limit: usize) -> Vec<usize>;
+    fn predict_leaf(
+        &self,
+        feat: &F,
+        root_index: usize,
+        iteration_range: (usize, usize),
+    ) -> Result<Vec<usize>>;
+    /// Computes the contribution of each feature to the margin of each output group,
+    /// `num_feature + 1` values per group ending with the bias
+    fn predict_contributions(
+        &self,
+        feat: &F,
+        root_index: usize,
+        iteration_range: (usize, usize),
+    ) -> Result<Vec<f32>>;
+    /// Generates predictions after each boosting round, walking the trees once.
+    /// This is only valid in gbtree predictor
+    fn predict_staged(&self, feat: &F, root_index: usize) -> Result<Vec<Vec<f32>>>;
+    /// Number of roots of each tree, valid root indices are below it
+    fn num_roots(&self) -> usize;
+    /// Number of boosting rounds of the model
+    fn num_boosted_rounds(&self) -> usize;
+    /// Importance of each feature, features without splits are left out
+    fn feature_importance(&self, importance_type: ImportanceType) -> Result<HashMap<usize, f32>>;
+    /// Name of the booster: `gbtree`, `dart` or `gblinear`
+    fn name(&self) -> &'static str;
+    /// Number of values the model outputs per instance
+    fn num_output_group(&self) -> usize;
+    /// Number of trees, 0 for linear models
+    fn num_trees(&self) -> usize;
+    /// Returns the tree `index`
+    fn tree(&self, index: usize) -> Option<Tree<'_>>;
+    /// Trees of the model, empty for linear models
+    fn trees(&self) -> Vec<Tree<'_>>;
+    /// Boosting round `i` holds the trees `iteration_indptr[i]..iteration_indptr[i + 1]`
+    fn iteration_indptr(&self) -> &[usize];
+    /// Weights of a linear model, `None` for tree models
+    fn linear_weights(&self) -> Option<&[f32]>;
+    /// Dumps the model, one string per tree (a single one for linear models)
+    fn dump(&self, fmap: &FeatureMap, with_stats: bool, format: DumpFormat) -> Result<Vec<String>>;
+    /// Keeps the boosting rounds `begin, begin + step, ...` below `end`, `end == 0` means
+    /// all rounds
+    fn slice(&self, begin: usize, end: usize, step: usize) -> Result<Box<dyn GradBooster<F>>>;
+    /// Drops the trees `tree_indices`, rounds left without trees are dropped too
+    fn drop_trees(&self, tree_indices: &[usize]) -> Result<Box<dyn GradBooster<F>>>;
+    /// Keeps the outputs of `groups`, which become the groups `0..groups.len()`.
+    /// `groups` are valid distinct output groups.
+    fn keep_output_groups(&self, groups: &[usize]) -> Result<Box<dyn GradBooster<F>>>;
+    /// The tree booster, `None` for linear models
+    fn as_gbtree(&self) -> Option<&GBTree>;
+    /// Writes the model in the binary format, as read by `load_grad_booster`
+    fn write_to(&self, writer: &mut dyn Write) -> Result<()>;
+    /// Converts the model into the `gradient_booster` object of the JSON model format
+    fn to_json(&self) -> Result<Value>;
 }
 
-pub fn load_grad_booster<F: FVec, T: ModelReader>(
+pub fn load_grad_booster<F: FVec, T: ModelReader + BufRead>(
     reader: &mut T,
     name_gbm: Vec<u8>,
     with_pbuffer: bool,
+    major_version: u32,
 ) -> Result<Box<dyn GradBooster<F>>> {
     match name_gbm.as_slice() {
-        b"gbtree" => Ok(Box::new(GBTree::read_from(with_pbuffer, reader, false)?)),
+        b"gbtree" => Ok(Box::new(GBTree::read_from(
+            with_pbuffer,
+            reader,
+            false,
+            major_version,
+        )?)),
         b"gblinear" => Ok(Box::new(GBLinear::read_from(with_pbuffer, reader)?)),
-        b"dart" => Ok(Box::new(GBTree::read_from(with_pbuffer, reader, true)?)),
+        b"dart" => Ok(Box::new(GBTree::read_from(
+            with_pbuffer,
+            reader,
+            true,
+            major_version,
+        )?)),
         _ => Err(Error::from_kind(ErrorKind::UnsupportedModelType(
             String::from_utf8(name_gbm)?,
         ))),
     }
 }
+
+/// Loads the `gradient_booster` object of a model saved in the JSON format
+pub fn load_grad_booster_json<F: FVec>(
+    gradient_booster: &Value,
+    num_feature: usize,
+    num_output_group: usize,
+) -> Result<Box<dyn GradBooster<F>>> {
+    let name_gbm = json::as_str(json::field(gradient_booster, "name")?)?;
+    match name_gbm {
+        "gbtree" => Ok(Box::new(GBTree::read_from_json(
+            json::field(gradient_booster, "model")?,
+            num_feature,
+            num_output_group,
+            None,
+        )?)),
+        "gblinear" => Ok(Box::new(GBLinear::read_from_json(
+            json::field(gradient_booster, "model")?,
+            num_feature,
+            num_output_group,
+        )?)),
+        "dart" => {
+            let gbtree = json::field(gradient_booster, "gbtree")?;
+            let weight_drop = json::f32_vec(json::field(gradient_booster, "weight_drop")?)?;
+            Ok(Box::new(GBTree::read_from_json(
+                json::field(gbtree, "model")?,
+                num_feature,
+                num_output_group,
+                Some(weight_drop),
+            )?))
+        }
+        _ => Err(Error::from_kind(ErrorKind::UnsupportedModelType(
+            name_gbm.to_string(),
+        ))),
+    }
+}
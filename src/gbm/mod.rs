@@ -1,4 +1,7 @@
+pub mod dump;
 mod gblinear;
-mod gbtree;
+pub mod gbtree;
 pub mod grad_booster;
 mod regtree;
+
+pub use self::regtree::{Tree, TreeNode};
@@ -1,7 +1,18 @@
 use crate::errors::*;
 use crate::fvec::FVec;
-use crate::model_reader::ModelReader;
+use crate::gbm::dump::{
+    escape_dot, format_float, json_number, DumpCondition, DumpFormat, DumpSplit, FeatureMap,
+    FeatureType,
+};
+use crate::json;
+use crate::model_reader::{read_records, ModelReader};
+use crate::model_writer::ModelWriter;
+use byteorder::{ByteOrder, LE};
+use serde_json::{json, Value};
+use std::cmp;
 use std::f32;
+use std::io::BufRead;
+use std::sync::OnceLock;
 
 #[derive(Clone, Copy)]
 struct Param {
@@ -21,18 +32,12 @@ struct Param {
 
 impl Param {
     fn read_from<T: ModelReader>(reader: &mut T) -> Result<Param> {
+        // 6 fields followed by 31 reserved ones
+        let fields = reader.read_int_vec(37)?;
         let (num_roots, num_nodes, num_deleted, max_depth, num_feature, size_leaf_vector) = (
-            reader.read_i32_le()?,
-            reader.read_i32_le()?,
-            reader.read_i32_le()?,
-            reader.read_i32_le()?,
-            reader.read_i32_le()?,
-            reader.read_i32_le()?,
+            fields[0], fields[1], fields[2], fields[3], fields[4], fields[5],
         );
 
-        let mut reserved = [0i32; 31];
-        reader.read_to_i32_buffer(&mut reserved)?;
-
         return Ok(Param {
             num_roots,
             num_nodes,
@@ -42,11 +47,37 @@ impl Param {
             size_leaf_vector,
         });
     }
+
+    fn write_to<T: ModelWriter + ?Sized>(&self, writer: &mut T) -> Result<()> {
+        writer.write_int_vec(&[
+            self.num_roots,
+            self.num_nodes,
+            self.num_deleted,
+            self.max_depth,
+            self.num_feature,
+            self.size_leaf_vector,
+        ])?;
+        return writer.write_int_vec(&[0i32; 31]);
+    }
+
+    fn read_from_json(value: &Value) -> Result<Param> {
+        return Ok(Param {
+            num_roots: 1,
+            num_nodes: json::as_i32(json::field(value, "num_nodes")?)?,
+            num_deleted: json::opt_field(value, "num_deleted").map_or(Ok(0), json::as_i32)?,
+            max_depth: 0,
+            num_feature: json::opt_field(value, "num_feature").map_or(Ok(0), json::as_i32)?,
+            size_leaf_vector: json::opt_field(value, "size_leaf_vector")
+                .map_or(Ok(0), json::as_i32)?,
+        });
+    }
 }
 
 #[derive(Clone, Copy)]
 enum LeafOrSplit {
     LeafValue(f32),
+    /// leaf of a multi-target tree, offset of its values in `RegTree::leaf_vectors`
+    LeafVector(usize),
     Split {
         /// pointer to right
         cleft: i32,
@@ -57,12 +88,71 @@ enum LeafOrSplit {
         default_next: i32,
         split_index: i32,
     },
+    /// partition-based split on a categorical feature
+    CategoricalSplit {
+        cleft: i32,
+        cright: i32,
+        /// saved with the tree but unused, categories decide the child
+        split_cond: f32,
+        default_next: i32,
+        split_index: i32,
+        /// index of the categories going to the right child in `RegTree::categories`
+        categories: usize,
+    },
+}
+
+/// Bitset of the categories chosen by a categorical split
+#[derive(Clone)]
+struct CategorySet {
+    bits: Vec<u32>,
+}
+
+impl CategorySet {
+    /// categories are stored as float feature values, only integers below 2^24 are exact
+    const MAX_CATEGORY: f32 = (1 << 24) as f32;
+
+    fn from_categories(categories: &[i32]) -> Result<CategorySet> {
+        let mut bits = vec![];
+        for &cat in categories.iter() {
+            if cat < 0 || cat as f32 >= CategorySet::MAX_CATEGORY {
+                bail!("invalid category {} in categorical split", cat);
+            }
+            let (word, bit) = (cat as usize / 32, cat as usize % 32);
+            if bits.len() <= word {
+                bits.resize(word + 1, 0u32);
+            }
+            bits[word] |= 1 << bit;
+        }
+        return Ok(CategorySet { bits });
+    }
+
+    /// Chosen categories in increasing order
+    fn categories(&self) -> Vec<usize> {
+        return (0..self.bits.len() * 32)
+            .filter(|cat| (self.bits[cat / 32] >> (cat % 32)) & 1 != 0)
+            .collect();
+    }
+
+    fn contains(&self, fvalue: f32) -> bool {
+        // negative and too large values are invalid categories, never chosen
+        if !(0f32..CategorySet::MAX_CATEGORY).contains(&fvalue) {
+            return false;
+        }
+        let cat = fvalue as usize;
+        return match self.bits.get(cat / 32) {
+            None => false,
+            Some(word) => (word >> (cat % 32)) & 1 != 0,
+        };
+    }
 }
 
 #[derive(Clone, Copy)]
 struct Node {
     /// pointer to parent, highest bit is used to indicate whether it's a left child or not
     parent: i32,
+    /// split index as saved, the highest bit tells whether missing values go left.
+    /// Kept for leaves too, deleted nodes are marked with -1.
+    sindex: i32,
     leaf_or_split: LeafOrSplit,
 }
 
@@ -75,44 +165,133 @@ impl Node {
         return ((sindex as i64) & ((1i64 << 31) - 1i64)) as i32;
     }
 
-    fn read_from<T: ModelReader>(reader: &mut T) -> Result<Node> {
-        let (parent, cleft, cright, sindex) = (
-            reader.read_i32_le()?,
-            reader.read_i32_le()?,
-            reader.read_i32_le()?,
-            reader.read_i32_le()?,
+    /// Size of a node saved in the binary format
+    const SIZE: usize = 20;
+
+    fn from_bytes(bytes: &[u8]) -> Node {
+        return Node::new(
+            LE::read_i32(&bytes[0..4]),
+            LE::read_i32(&bytes[4..8]),
+            LE::read_i32(&bytes[8..12]),
+            LE::read_i32(&bytes[12..16]),
+            LE::read_f32(&bytes[16..20]),
         );
+    }
 
-        let split_index = Node::decode_split_index(sindex);
-        let default_next = if Node::is_default_left(sindex) {
-            cleft
-        } else {
-            cright
+    /// Left child, right child and value as saved, the inverse of `Node::new`
+    fn to_raw(self) -> (i32, i32, f32) {
+        return match self.leaf_or_split {
+            LeafOrSplit::LeafValue(leaf_value) => (-1, -1, leaf_value),
+            LeafOrSplit::LeafVector(_) => (-1, -1, 0f32),
+            LeafOrSplit::Split {
+                cleft,
+                cright,
+                split_cond,
+                ..
+            }
+            | LeafOrSplit::CategoricalSplit {
+                cleft,
+                cright,
+                split_cond,
+                ..
+            } => (cleft, cright, split_cond),
         };
+    }
 
-        let is_leaf = cleft == -1;
+    fn write_to<T: ModelWriter + ?Sized>(&self, writer: &mut T) -> Result<()> {
+        let (cleft, cright, value) = self.to_raw();
+        writer.write_int_vec(&[self.parent, cleft, cright, self.sindex])?;
+        return writer.write_f32_le(value);
+    }
 
-        let leaf_or_split = if is_leaf {
-            LeafOrSplit::LeafValue(reader.read_f32_le()?)
+    fn new(parent: i32, cleft: i32, cright: i32, sindex: i32, value: f32) -> Node {
+        let leaf_or_split = if cleft == -1 {
+            LeafOrSplit::LeafValue(value)
         } else {
             LeafOrSplit::Split {
                 cleft,
                 cright,
-                split_cond: reader.read_f32_le()?,
-                default_next,
-                split_index,
+                split_cond: value,
+                default_next: if Node::is_default_left(sindex) {
+                    cleft
+                } else {
+                    cright
+                },
+                split_index: Node::decode_split_index(sindex),
             }
         };
-
-        return Ok(Node {
+        return Node {
             parent,
+            sindex,
             leaf_or_split,
-        });
+        };
+    }
+
+    fn left_child(&self) -> Option<usize> {
+        return match self.leaf_or_split {
+            LeafOrSplit::LeafValue(_) | LeafOrSplit::LeafVector(_) => None,
+            LeafOrSplit::Split { cleft, .. } | LeafOrSplit::CategoricalSplit { cleft, .. } => {
+                Some(cleft as usize)
+            }
+        };
     }
 
-    fn next<F: FVec>(&self, feat: &F) -> Option<usize> {
+    fn right_child(&self) -> Option<usize> {
         return match self.leaf_or_split {
-            LeafOrSplit::LeafValue(_) => None,
+            LeafOrSplit::LeafValue(_) | LeafOrSplit::LeafVector(_) => None,
+            LeafOrSplit::Split { cright, .. } | LeafOrSplit::CategoricalSplit { cright, .. } => {
+                Some(cright as usize)
+            }
+        };
+    }
+
+    /// Turns a split into a categorical split on the same feature
+    fn into_categorical(self, categories: usize) -> Node {
+        let leaf_or_split = match self.leaf_or_split {
+            LeafOrSplit::Split {
+                cleft,
+                cright,
+                split_cond,
+                default_next,
+                split_index,
+            } => LeafOrSplit::CategoricalSplit {
+                cleft,
+                cright,
+                split_cond,
+                default_next,
+                split_index,
+                categories,
+            },
+            other => other,
+        };
+        return Node {
+            parent: self.parent,
+            sindex: self.sindex,
+            leaf_or_split,
+        };
+    }
+
+    fn next<F: FVec>(&self, feat: &F, categories: &[CategorySet]) -> Option<usize> {
+        return match self.leaf_or_split {
+            LeafOrSplit::LeafValue(_) | LeafOrSplit::LeafVector(_) => None,
+            LeafOrSplit::CategoricalSplit {
+                cleft,
+                cright,
+                default_next,
+                split_index,
+                categories: cat_index,
+                ..
+            } => match feat.fvalue(split_index as usize) {
+                None => Some(default_next as usize),
+                // chosen categories go right, like one-hot encoding
+                Some(fvalue) => {
+                    if categories[cat_index].contains(fvalue) {
+                        Some(cright as usize)
+                    } else {
+                        Some(cleft as usize)
+                    }
+                }
+            },
             LeafOrSplit::Split {
                 cleft,
                 cright,
@@ -146,44 +325,470 @@ struct RTreeNodeStat {
 }
 
 impl RTreeNodeStat {
-    fn read_from<T: ModelReader>(reader: &mut T) -> Result<RTreeNodeStat> {
-        return Ok(RTreeNodeStat {
-            loss_chg: reader.read_f32_le()?,
-            sum_hess: reader.read_f32_le()?,
-            base_weight: reader.read_f32_le()?,
-            leaf_child_cnt: reader.read_i32_le()?,
-        });
+    /// Size of the statistics of a node saved in the binary format
+    const SIZE: usize = 16;
+
+    fn from_bytes(bytes: &[u8]) -> RTreeNodeStat {
+        return RTreeNodeStat {
+            loss_chg: LE::read_f32(&bytes[0..4]),
+            sum_hess: LE::read_f32(&bytes[4..8]),
+            base_weight: LE::read_f32(&bytes[8..12]),
+            leaf_child_cnt: LE::read_i32(&bytes[12..16]),
+        };
+    }
+
+    fn write_to<T: ModelWriter + ?Sized>(&self, writer: &mut T) -> Result<()> {
+        writer.write_float_vec(&[self.loss_chg, self.sum_hess, self.base_weight])?;
+        return writer.write_i32_le(self.leaf_child_cnt);
+    }
+}
+
+/// Feature split on the path to a node, with the fractions of the paths through it with
+/// the feature missing (`zero_fraction`) and present (`one_fraction`)
+#[derive(Clone, Copy)]
+struct PathElement {
+    feature_index: i32,
+    zero_fraction: f32,
+    one_fraction: f32,
+    /// weight of the subsets of features of the same size as this element's index
+    pweight: f32,
+}
+
+fn extend_path(path: &mut Vec<PathElement>, element: PathElement) {
+    let unique_depth = path.len();
+    path.push(PathElement {
+        pweight: if unique_depth == 0 { 1f32 } else { 0f32 },
+        ..element
+    });
+    let depth = (unique_depth + 1) as f32;
+    for i in (0..unique_depth).rev() {
+        path[i + 1].pweight += element.one_fraction * path[i].pweight * (i + 1) as f32 / depth;
+        path[i].pweight =
+            element.zero_fraction * path[i].pweight * (unique_depth - i) as f32 / depth;
     }
 }
 
+/// Removes the element `path_index` from the path, the inverse of `extend_path`
+fn unwind_path(path: &mut Vec<PathElement>, path_index: usize) {
+    let unique_depth = path.len() - 1;
+    let depth = (unique_depth + 1) as f32;
+    let (one_fraction, zero_fraction) = (
+        path[path_index].one_fraction,
+        path[path_index].zero_fraction,
+    );
+    let mut next_one_portion = path[unique_depth].pweight;
+    for i in (0..unique_depth).rev() {
+        if one_fraction != 0f32 {
+            let tmp = path[i].pweight;
+            path[i].pweight = next_one_portion * depth / ((i + 1) as f32 * one_fraction);
+            next_one_portion =
+                tmp - path[i].pweight * zero_fraction * (unique_depth - i) as f32 / depth;
+        } else {
+            path[i].pweight = path[i].pweight * depth / (zero_fraction * (unique_depth - i) as f32);
+        }
+    }
+    for i in path_index..unique_depth {
+        path[i].feature_index = path[i + 1].feature_index;
+        path[i].zero_fraction = path[i + 1].zero_fraction;
+        path[i].one_fraction = path[i + 1].one_fraction;
+    }
+    path.pop();
+}
+
+/// Total weight of the path once the element `path_index` is removed
+fn unwound_path_sum(path: &[PathElement], path_index: usize) -> f32 {
+    let unique_depth = path.len() - 1;
+    let depth = (unique_depth + 1) as f32;
+    let (one_fraction, zero_fraction) = (
+        path[path_index].one_fraction,
+        path[path_index].zero_fraction,
+    );
+    let mut next_one_portion = path[unique_depth].pweight;
+    let mut total = 0f32;
+    for i in (0..unique_depth).rev() {
+        if one_fraction != 0f32 {
+            let tmp = next_one_portion * depth / ((i + 1) as f32 * one_fraction);
+            total += tmp;
+            next_one_portion =
+                path[i].pweight - tmp * zero_fraction * ((unique_depth - i) as f32 / depth);
+        } else if zero_fraction != 0f32 {
+            total += (path[i].pweight / zero_fraction) / ((unique_depth - i) as f32 / depth);
+        }
+    }
+    return total;
+}
+
 /// Regression tree
 pub struct RegTree {
     param: Param,
     nodes: Vec<Node>,
     stats: Vec<RTreeNodeStat>,
+    categories: Vec<CategorySet>,
+    /// leaf values of a multi-target tree, `size_leaf_vector` per node
+    leaf_vectors: Vec<f32>,
+    /// expected output of the subtree of each node, computed by the first contributions
+    mean_values: OnceLock<Vec<f32>>,
 }
 
 impl RegTree {
-    pub fn read_from<T: ModelReader>(reader: &mut T) -> Result<RegTree> {
+    pub fn read_from<T: ModelReader + BufRead>(reader: &mut T) -> Result<RegTree> {
         let param = Param::read_from(reader)?;
-        let nodes: Result<Vec<Node>> = (0..param.num_nodes)
-            .map(|_| Node::read_from(reader))
+        let num_nodes = cmp::max(param.num_nodes, 0) as usize;
+        let nodes = read_records(reader, num_nodes, Node::SIZE, Node::from_bytes)?;
+        let stats = read_records(
+            reader,
+            num_nodes,
+            RTreeNodeStat::SIZE,
+            RTreeNodeStat::from_bytes,
+        )?;
+        return Ok(RegTree {
+            param,
+            nodes,
+            stats,
+            categories: vec![],
+            leaf_vectors: vec![],
+            mean_values: OnceLock::new(),
+        });
+    }
+
+    /// Reads a tree saved in the JSON model format
+    pub fn read_from_json(value: &Value) -> Result<RegTree> {
+        let param = Param::read_from_json(json::field(value, "tree_param")?)?;
+        let num_nodes = param.num_nodes as usize;
+        let parents = json::i32_vec(json::field(value, "parents")?)?;
+        let left_children = json::i32_vec(json::field(value, "left_children")?)?;
+        let right_children = json::i32_vec(json::field(value, "right_children")?)?;
+        let split_indices = json::i32_vec(json::field(value, "split_indices")?)?;
+        let split_conditions = json::f32_vec(json::field(value, "split_conditions")?)?;
+        let default_left = json::i32_vec(json::field(value, "default_left")?)?;
+        let base_weights = json::f32_vec(json::field(value, "base_weights")?)?;
+        // multi-target trees hold a vector of weights per node and may omit the statistics
+        let size_leaf_vector = cmp::max(param.size_leaf_vector, 1) as usize;
+        let is_multi_target = size_leaf_vector > 1;
+        let (loss_changes, sum_hessian) = if is_multi_target {
+            (
+                RegTree::opt_f32_vec(value, "loss_changes", num_nodes)?,
+                RegTree::opt_f32_vec(value, "sum_hessian", num_nodes)?,
+            )
+        } else {
+            (
+                json::f32_vec(json::field(value, "loss_changes")?)?,
+                json::f32_vec(json::field(value, "sum_hessian")?)?,
+            )
+        };
+        if is_multi_target && base_weights.len() != num_nodes * size_leaf_vector {
+            bail!(
+                "multi-target tree has {} nodes with {} targets but {} weights",
+                num_nodes,
+                size_leaf_vector,
+                base_weights.len()
+            );
+        }
+
+        for len in [
+            parents.len(),
+            left_children.len(),
+            right_children.len(),
+            split_indices.len(),
+            split_conditions.len(),
+            default_left.len(),
+            loss_changes.len(),
+            sum_hessian.len(),
+        ]
+        .iter()
+        {
+            if *len != num_nodes {
+                bail!(
+                    "tree has {} nodes but a node array of size {}",
+                    num_nodes,
+                    len
+                );
+            }
+        }
+
+        let split_type = match json::opt_field(value, "split_type") {
+            Some(split_type) => json::i32_vec(split_type)?,
+            None => vec![0; num_nodes],
+        };
+        if split_type.len() != num_nodes {
+            bail!(
+                "tree has {} nodes but split_type of size {}",
+                num_nodes,
+                split_type.len()
+            );
+        }
+        let categories_nodes = RegTree::opt_i32_vec(value, "categories_nodes")?;
+        let categories_segments = RegTree::opt_i32_vec(value, "categories_segments")?;
+        let categories_sizes = RegTree::opt_i32_vec(value, "categories_sizes")?;
+        let all_categories = RegTree::opt_i32_vec(value, "categories")?;
+        if categories_segments.len() != categories_nodes.len()
+            || categories_sizes.len() != categories_nodes.len()
+        {
+            bail!("categorical split arrays of the tree have different sizes");
+        }
+
+        if let Some(parent) = parents
+            .iter()
+            .find(|p| **p != i32::MAX && (**p < 0 || **p as usize >= num_nodes))
+        {
+            bail!("tree has {} nodes but a parent {}", num_nodes, parent);
+        }
+
+        let mut nodes: Vec<Node> = (0..num_nodes)
+            .map(|i| {
+                let sindex = if default_left[i] != 0 {
+                    split_indices[i] | (1 << 31)
+                } else {
+                    split_indices[i]
+                };
+                // the root's parent is saved as i32::MAX instead of -1, and without the
+                // highest bit telling whether the node is a left child
+                let parent = if parents[i] == i32::MAX {
+                    -1
+                } else if parents[i] >= 0
+                    && left_children.get(parents[i] as usize) == Some(&(i as i32))
+                {
+                    parents[i] | (1 << 31)
+                } else {
+                    parents[i]
+                };
+                Node::new(
+                    parent,
+                    left_children[i],
+                    right_children[i],
+                    sindex,
+                    split_conditions[i],
+                )
+            })
             .collect();
-        let stats: Result<Vec<RTreeNodeStat>> = (0..param.num_nodes)
-            .map(|_| RTreeNodeStat::read_from(reader))
+
+        let mut categories = Vec::with_capacity(categories_nodes.len());
+        for (k, &nid) in categories_nodes.iter().enumerate() {
+            let (begin, size) = (categories_segments[k], categories_sizes[k]);
+            if nid < 0 || nid as usize >= num_nodes || split_type[nid as usize] != 1 {
+                bail!("node {} is not a categorical split", nid);
+            }
+            if begin < 0 || size < 0 || (begin + size) as usize > all_categories.len() {
+                bail!("categories of node {} are out of range", nid);
+            }
+            let cats = &all_categories[begin as usize..(begin + size) as usize];
+            categories.push(CategorySet::from_categories(cats)?);
+            nodes[nid as usize] = nodes[nid as usize].into_categorical(k);
+        }
+        for (i, node) in nodes.iter_mut().enumerate() {
+            // a categorical split without chosen categories sends everything left
+            if split_type[i] == 1 {
+                if let LeafOrSplit::Split { .. } = node.leaf_or_split {
+                    categories.push(CategorySet { bits: vec![] });
+                    *node = node.into_categorical(categories.len() - 1);
+                }
+            }
+        }
+
+        RegTree::check_children(&nodes, param.num_roots)?;
+
+        let leaf_vectors = if is_multi_target {
+            for (i, node) in nodes.iter_mut().enumerate() {
+                if let LeafOrSplit::LeafValue(_) = node.leaf_or_split {
+                    node.leaf_or_split = LeafOrSplit::LeafVector(i * size_leaf_vector);
+                }
+            }
+            base_weights.clone()
+        } else {
+            vec![]
+        };
+
+        let stats = (0..num_nodes)
+            .map(|i| RTreeNodeStat {
+                loss_chg: loss_changes[i],
+                sum_hess: sum_hessian[i],
+                base_weight: base_weights
+                    .get(i * size_leaf_vector)
+                    .cloned()
+                    .unwrap_or(0f32),
+                leaf_child_cnt: 0,
+            })
             .collect();
+
         return Ok(RegTree {
             param,
-            nodes: nodes?,
-            stats: stats?,
+            nodes,
+            stats,
+            categories,
+            leaf_vectors,
+            mean_values: OnceLock::new(),
         });
     }
 
+    /// Checks that walking the tree from its roots always ends on a leaf: children are
+    /// nodes of the tree and every node is reached at most once, as `CompactModel` does
+    fn check_children(nodes: &[Node], num_roots: i32) -> Result<()> {
+        let mut visited = vec![false; nodes.len()];
+        let mut stack: Vec<usize> =
+            (0..cmp::min(cmp::max(num_roots, 1) as usize, nodes.len())).collect();
+        while let Some(nid) = stack.pop() {
+            if visited[nid] {
+                bail!("node {} of the tree is reached twice", nid);
+            }
+            visited[nid] = true;
+            let (left, right) = match nodes[nid].leaf_or_split {
+                LeafOrSplit::Split { cleft, cright, .. }
+                | LeafOrSplit::CategoricalSplit { cleft, cright, .. } => (cleft, cright),
+                LeafOrSplit::LeafValue(_) | LeafOrSplit::LeafVector(_) => continue,
+            };
+            for child in [left, right].iter() {
+                if *child < 0 || *child as usize >= nodes.len() {
+                    bail!("node {} of the tree has invalid children", nid);
+                }
+                stack.push(*child as usize);
+            }
+        }
+        return Ok(());
+    }
+
+    /// Multiplies the output of the tree by `factor`
+    pub fn scale(&mut self, factor: f32) {
+        for node in self.nodes.iter_mut() {
+            if let LeafOrSplit::LeafValue(leaf_value) = &mut node.leaf_or_split {
+                *leaf_value *= factor;
+            }
+        }
+        for value in self.leaf_vectors.iter_mut() {
+            *value *= factor;
+        }
+        for stat in self.stats.iter_mut() {
+            stat.base_weight *= factor;
+        }
+        self.mean_values = OnceLock::new();
+    }
+
+    /// Writes the tree in the binary format, which has no categorical splits nor leaf vectors
+    pub fn write_to<T: ModelWriter + ?Sized>(&self, writer: &mut T) -> Result<()> {
+        if !self.categories.is_empty() {
+            bail!("trees with categorical splits can't be saved in the binary format");
+        }
+        if !self.leaf_vectors.is_empty() {
+            bail!("multi-target trees can't be saved in the binary format");
+        }
+        self.param.write_to(writer)?;
+        for node in self.nodes.iter() {
+            node.write_to(writer)?;
+        }
+        for stat in self.stats.iter() {
+            stat.write_to(writer)?;
+        }
+        return Ok(());
+    }
+
+    /// Converts the tree into the JSON model format, `id` is its index in the model
+    pub fn to_json(&self, id: usize) -> Result<Value> {
+        if self.num_roots() > 1 {
+            bail!("trees with several roots can't be saved in the JSON format");
+        }
+        let mut parents = vec![];
+        let mut left_children = vec![];
+        let mut right_children = vec![];
+        let mut split_indices = vec![];
+        let mut split_conditions = vec![];
+        let mut default_left = vec![];
+        let mut split_type = vec![];
+        for node in self.nodes.iter() {
+            let (cleft, cright, value) = node.to_raw();
+            parents.push(if node.parent == -1 {
+                i32::MAX
+            } else {
+                node.parent & i32::MAX
+            });
+            left_children.push(cleft);
+            right_children.push(cright);
+            split_indices.push(Node::decode_split_index(node.sindex));
+            split_conditions.push(value);
+            default_left.push(Node::is_default_left(node.sindex) as i32);
+            split_type.push(match node.leaf_or_split {
+                LeafOrSplit::CategoricalSplit { .. } => 1,
+                _ => 0,
+            });
+        }
+
+        let mut categories_nodes = vec![];
+        let mut categories_segments = vec![];
+        let mut categories_sizes = vec![];
+        let mut all_categories = vec![];
+        for (nid, node) in self.nodes.iter().enumerate() {
+            if let LeafOrSplit::CategoricalSplit { categories, .. } = node.leaf_or_split {
+                let cats = self.categories[categories].categories();
+                categories_nodes.push(nid);
+                categories_segments.push(all_categories.len());
+                categories_sizes.push(cats.len());
+                all_categories.extend(cats);
+            }
+        }
+
+        let base_weights: Vec<f32> = if self.leaf_vectors.is_empty() {
+            self.stats.iter().map(|stat| stat.base_weight).collect()
+        } else {
+            self.leaf_vectors.clone()
+        };
+        let loss_changes: Vec<f32> = self.stats.iter().map(|stat| stat.loss_chg).collect();
+        let sum_hessian: Vec<f32> = self.stats.iter().map(|stat| stat.sum_hess).collect();
+        return Ok(json!({
+            "base_weights": json::from_f32_vec(&base_weights)?,
+            "categories": all_categories,
+            "categories_nodes": categories_nodes,
+            "categories_segments": categories_segments,
+            "categories_sizes": categories_sizes,
+            "default_left": default_left,
+            "id": id,
+            "left_children": left_children,
+            "loss_changes": json::from_f32_vec(&loss_changes)?,
+            "parents": parents,
+            "right_children": right_children,
+            "split_conditions": json::from_f32_vec(&split_conditions)?,
+            "split_indices": split_indices,
+            "split_type": split_type,
+            "sum_hessian": json::from_f32_vec(&sum_hessian)?,
+            "tree_param": {
+                "num_deleted": json::param(self.param.num_deleted),
+                "num_feature": json::param(self.param.num_feature),
+                "num_nodes": json::param(self.nodes.len()),
+                "size_leaf_vector": json::param(cmp::max(self.param.size_leaf_vector, 1)),
+            },
+        }));
+    }
+
+    fn opt_f32_vec(value: &Value, name: &str, size: usize) -> Result<Vec<f32>> {
+        return match json::opt_field(value, name) {
+            Some(values) if !json::as_array(values)?.is_empty() => json::f32_vec(values),
+            _ => Ok(vec![0f32; size]),
+        };
+    }
+
+    /// Number of roots, nodes `0..num_roots` are the roots of the tree
+    pub fn num_roots(&self) -> usize {
+        return cmp::max(self.param.num_roots, 1) as usize;
+    }
+
+    /// Size of the leaf vectors of a multi-target tree, 1 for the usual scalar leaves
+    pub fn size_leaf_vector(&self) -> usize {
+        return if self.leaf_vectors.is_empty() {
+            1
+        } else {
+            self.param.size_leaf_vector as usize
+        };
+    }
+
+    fn opt_i32_vec(value: &Value, name: &str) -> Result<Vec<i32>> {
+        return match json::opt_field(value, name) {
+            Some(values) => json::i32_vec(values),
+            None => Ok(vec![]),
+        };
+    }
+
     pub fn get_leaf_index<F: FVec>(&self, feat: &F, root_id: usize) -> usize {
         let mut pid = root_id;
         let mut node = self.nodes[pid];
         loop {
-            match node.next(feat) {
+            match node.next(feat, &self.categories) {
                 None => return pid,
                 Some(new_pid) => {
                     pid = new_pid;
@@ -197,19 +802,618 @@ impl RegTree {
         let leaf_node = self.nodes[self.get_leaf_index(feat, root_id)];
         return match leaf_node.leaf_or_split {
             LeafOrSplit::LeafValue(leaf_value) => leaf_value,
-            LeafOrSplit::Split { .. } => {
+            LeafOrSplit::LeafVector(_) => {
+                panic!("Can't get a single leaf value of a multi-target tree")
+            }
+            LeafOrSplit::Split { .. } | LeafOrSplit::CategoricalSplit { .. } => {
+                panic!("Broken tree - is not leaf node")
+            }
+        };
+    }
+
+    /// Returns the leaf values reached by `feat`, one per target.
+    /// Scalar trees return their single leaf value.
+    pub fn get_leaf_vector<F: FVec>(&self, feat: &F, root_id: usize) -> &[f32] {
+        let leaf_index = self.get_leaf_index(feat, root_id);
+        return match &self.nodes[leaf_index].leaf_or_split {
+            LeafOrSplit::LeafValue(leaf_value) => std::slice::from_ref(leaf_value),
+            LeafOrSplit::LeafVector(offset) => {
+                &self.leaf_vectors[*offset..*offset + self.size_leaf_vector()]
+            }
+            LeafOrSplit::Split { .. } | LeafOrSplit::CategoricalSplit { .. } => {
                 panic!("Broken tree - is not leaf node")
             }
         };
     }
+
+    /// Adds the SHAP values of `feat` scaled by `weight` to `phi`, which holds one value
+    /// per feature followed by the bias. This is the TreeSHAP algorithm of xgboost's
+    /// `pred_contribs`, it panics on multi-target trees.
+    pub fn add_contributions<F: FVec>(
+        &self,
+        feat: &F,
+        root_id: usize,
+        weight: f32,
+        phi: &mut [f32],
+    ) {
+        let bias = phi.len() - 1;
+        phi[bias] += weight * self.mean_values()[root_id];
+        let root = PathElement {
+            feature_index: -1,
+            zero_fraction: 1f32,
+            one_fraction: 1f32,
+            pweight: 0f32,
+        };
+        self.tree_shap(feat, weight, phi, root_id, &[], root);
+    }
+
+    /// Expected output of the subtree of each node, computed once per tree
+    fn mean_values(&self) -> &[f32] {
+        return self.mean_values.get_or_init(|| {
+            let mut mean_values = vec![0f32; self.nodes.len()];
+            for root_id in 0..cmp::min(self.num_roots(), self.nodes.len()) {
+                self.fill_mean_values(root_id, &mut mean_values);
+            }
+            mean_values
+        });
+    }
+
+    /// Fills the expected output of the subtree of `nid` and of its nodes, the leaves
+    /// weighted by their cover
+    fn fill_mean_values(&self, nid: usize, mean_values: &mut [f32]) -> f32 {
+        let node = &self.nodes[nid];
+        let mean_value = match (node.left_child(), node.right_child()) {
+            (Some(left), Some(right)) => {
+                (self.fill_mean_values(left, mean_values) * self.stats[left].sum_hess
+                    + self.fill_mean_values(right, mean_values) * self.stats[right].sum_hess)
+                    / self.stats[nid].sum_hess
+            }
+            _ => match node.leaf_or_split {
+                LeafOrSplit::LeafValue(leaf_value) => leaf_value,
+                _ => panic!("Can't compute contributions of a multi-target tree"),
+            },
+        };
+        mean_values[nid] = mean_value;
+        return mean_value;
+    }
+
+    fn tree_shap<F: FVec>(
+        &self,
+        feat: &F,
+        weight: f32,
+        phi: &mut [f32],
+        nid: usize,
+        parent_path: &[PathElement],
+        incoming: PathElement,
+    ) {
+        let mut path = parent_path.to_vec();
+        extend_path(&mut path, incoming);
+        let node = &self.nodes[nid];
+        let hot = match node.next(feat, &self.categories) {
+            Some(hot) => hot,
+            None => {
+                let leaf_value = match node.leaf_or_split {
+                    LeafOrSplit::LeafValue(leaf_value) => leaf_value,
+                    _ => panic!("Can't compute contributions of a multi-target tree"),
+                };
+                for i in 1..path.len() {
+                    let w = unwound_path_sum(&path, i);
+                    let element = path[i];
+                    phi[element.feature_index as usize] +=
+                        w * (element.one_fraction - element.zero_fraction) * leaf_value * weight;
+                }
+                return;
+            }
+        };
+        let (left, right) = (node.left_child().unwrap(), node.right_child().unwrap());
+        let cold = if hot == left { right } else { left };
+        let cover = self.stats[nid].sum_hess;
+        let hot_zero_fraction = self.stats[hot].sum_hess / cover;
+        let cold_zero_fraction = self.stats[cold].sum_hess / cover;
+        let split_index = Node::decode_split_index(node.sindex);
+
+        // a feature already split on is undone, then split again below
+        let (mut incoming_zero_fraction, mut incoming_one_fraction) = (1f32, 1f32);
+        if let Some(path_index) = path.iter().position(|e| e.feature_index == split_index) {
+            incoming_zero_fraction = path[path_index].zero_fraction;
+            incoming_one_fraction = path[path_index].one_fraction;
+            unwind_path(&mut path, path_index);
+        }
+        let hot_element = PathElement {
+            feature_index: split_index,
+            zero_fraction: hot_zero_fraction * incoming_zero_fraction,
+            one_fraction: incoming_one_fraction,
+            pweight: 0f32,
+        };
+        self.tree_shap(feat, weight, phi, hot, &path, hot_element);
+        let cold_element = PathElement {
+            zero_fraction: cold_zero_fraction * incoming_zero_fraction,
+            one_fraction: 0f32,
+            ..hot_element
+        };
+        self.tree_shap(feat, weight, phi, cold, &path, cold_element);
+    }
+
+    /// Returns the split feature, gain and cover of the splits reachable from the roots
+    pub fn split_stats(&self) -> Vec<(usize, f32, f32)> {
+        let mut result = vec![];
+        let mut stack: Vec<usize> = (0..self.num_roots()).collect();
+        while let Some(nid) = stack.pop() {
+            let (cleft, cright, split_index) = match self.nodes[nid].leaf_or_split {
+                LeafOrSplit::LeafValue(_) | LeafOrSplit::LeafVector(_) => continue,
+                LeafOrSplit::Split {
+                    cleft,
+                    cright,
+                    split_index,
+                    ..
+                }
+                | LeafOrSplit::CategoricalSplit {
+                    cleft,
+                    cright,
+                    split_index,
+                    ..
+                } => (cleft, cright, split_index),
+            };
+            let stat = &self.stats[nid];
+            result.push((split_index as usize, stat.loss_chg, stat.sum_hess));
+            stack.push(cright as usize);
+            stack.push(cleft as usize);
+        }
+        return result;
+    }
+
+    /// Dumps the tree in the format of xgboost's `Booster.get_dump()`, starting from the
+    /// first root. `with_stats` adds the gain of splits and the cover of nodes.
+    pub fn dump(&self, fmap: &FeatureMap, with_stats: bool, format: DumpFormat) -> String {
+        let mut result = String::new();
+        match format {
+            DumpFormat::Text => self.dump_text_node(0, 0, fmap, with_stats, &mut result),
+            DumpFormat::Json => {
+                self.dump_json_node(0, 0, fmap, with_stats, &mut result);
+                result.push('\n');
+            }
+            DumpFormat::Dot => {
+                result.push_str("digraph {\n    graph [ rankdir=TB ]\n");
+                self.dump_dot_node(0, fmap, with_stats, &mut result);
+                result.push_str("}\n");
+            }
+        }
+        return result;
+    }
+
+    /// Describes the split of node `nid`, `None` for leaves
+    fn dump_split(&self, nid: usize, fmap: &FeatureMap) -> Option<DumpSplit> {
+        let (left, right, missing, split_index, condition) = match self.nodes[nid].leaf_or_split {
+            LeafOrSplit::LeafValue(_) | LeafOrSplit::LeafVector(_) => return None,
+            LeafOrSplit::Split {
+                cleft,
+                cright,
+                split_cond,
+                default_next,
+                split_index,
+            } => {
+                let condition = match fmap.feature_type(split_index as usize) {
+                    FeatureType::Indicator => DumpCondition::Indicator,
+                    FeatureType::Integer => {
+                        DumpCondition::Threshold((split_cond.ceil() as i32).to_string())
+                    }
+                    FeatureType::Quantitative | FeatureType::Categorical => {
+                        DumpCondition::Threshold(format_float(split_cond, 9))
+                    }
+                };
+                (cleft, cright, default_next, split_index, condition)
+            }
+            LeafOrSplit::CategoricalSplit {
+                cleft,
+                cright,
+                default_next,
+                split_index,
+                categories,
+                ..
+            } => (
+                cleft,
+                cright,
+                default_next,
+                split_index,
+                DumpCondition::Categories(self.categories[categories].categories()),
+            ),
+        };
+        return Some(DumpSplit {
+            fname: fmap.name(split_index as usize),
+            condition,
+            left,
+            right,
+            missing,
+        });
+    }
+
+    /// Value of the leaf `nid` as dumped in `format`, multi-target leaves in brackets
+    fn dump_leaf(&self, nid: usize, format: DumpFormat) -> String {
+        let format_value = |value: f32| match format {
+            DumpFormat::Json => json_number(format_float(value, 9)),
+            DumpFormat::Text | DumpFormat::Dot => format_float(value, 9),
+        };
+        return match self.nodes[nid].leaf_or_split {
+            LeafOrSplit::LeafVector(offset) => {
+                let values: Vec<String> = self.leaf_vectors
+                    [offset..offset + self.size_leaf_vector()]
+                    .iter()
+                    .map(|value| format_value(*value))
+                    .collect();
+                let separator = if format == DumpFormat::Json {
+                    ", "
+                } else {
+                    ","
+                };
+                format!("[{}]", values.join(separator))
+            }
+            LeafOrSplit::LeafValue(leaf_value) => format_value(leaf_value),
+            LeafOrSplit::Split { .. } | LeafOrSplit::CategoricalSplit { .. } => {
+                panic!("Broken tree - is not leaf node")
+            }
+        };
+    }
+
+    fn dump_text_node(
+        &self,
+        nid: usize,
+        depth: usize,
+        fmap: &FeatureMap,
+        with_stats: bool,
+        result: &mut String,
+    ) {
+        let stat = &self.stats[nid];
+        result.push_str(&"\t".repeat(depth));
+        match self.dump_split(nid, fmap) {
+            None => {
+                result.push_str(&format!(
+                    "{}:leaf={}",
+                    nid,
+                    self.dump_leaf(nid, DumpFormat::Text)
+                ));
+                if with_stats {
+                    result.push_str(&format!(",cover={}", format_float(stat.sum_hess, 9)));
+                }
+                result.push('\n');
+            }
+            Some(split) => {
+                result.push_str(&format!("{}:{}", nid, split.to_text()));
+                if with_stats {
+                    result.push_str(&format!(
+                        ",gain={},cover={}",
+                        format_float(stat.loss_chg, 9),
+                        format_float(stat.sum_hess, 9)
+                    ));
+                }
+                result.push('\n');
+                self.dump_text_node(split.left as usize, depth + 1, fmap, with_stats, result);
+                self.dump_text_node(split.right as usize, depth + 1, fmap, with_stats, result);
+            }
+        }
+    }
+
+    fn dump_json_node(
+        &self,
+        nid: usize,
+        depth: usize,
+        fmap: &FeatureMap,
+        with_stats: bool,
+        result: &mut String,
+    ) {
+        let stat = &self.stats[nid];
+        let indent = "  ".repeat(depth + 1);
+        result.push_str(&indent);
+        match self.dump_split(nid, fmap) {
+            None => {
+                result.push_str(&format!(
+                    "{{ \"nodeid\": {}, \"leaf\": {} ",
+                    nid,
+                    self.dump_leaf(nid, DumpFormat::Json)
+                ));
+                if with_stats {
+                    let cover = json_number(format_float(stat.sum_hess, 9));
+                    result.push_str(&format!(", \"cover\": {} ", cover));
+                }
+                result.push('}');
+            }
+            Some(split) => {
+                result.push_str(&format!(
+                    "{{ \"nodeid\": {}, \"depth\": {}, {} ",
+                    nid,
+                    depth,
+                    split.to_json()
+                ));
+                if with_stats {
+                    result.push_str(&format!(
+                        ", \"gain\": {}, \"cover\": {}",
+                        json_number(format_float(stat.loss_chg, 9)),
+                        json_number(format_float(stat.sum_hess, 9))
+                    ));
+                }
+                result.push_str(", \"children\": [\n");
+                self.dump_json_node(split.left as usize, depth + 1, fmap, with_stats, result);
+                result.push_str(", \n");
+                self.dump_json_node(split.right as usize, depth + 1, fmap, with_stats, result);
+                result.push_str(&format!("\n{}]}}", indent));
+            }
+        }
+    }
+
+    fn dump_dot_node(&self, nid: usize, fmap: &FeatureMap, with_stats: bool, result: &mut String) {
+        let stat = &self.stats[nid];
+        match self.dump_split(nid, fmap) {
+            None => {
+                let mut label = format!("leaf={}", self.dump_leaf(nid, DumpFormat::Dot));
+                if with_stats {
+                    label.push_str(&format!("\\ncover={}", format_float(stat.sum_hess, 9)));
+                }
+                result.push_str(&format!("    {} [ label=\"{}\" ]\n", nid, label));
+            }
+            Some(split) => {
+                let mut label = escape_dot(&split.to_label());
+                if with_stats {
+                    label.push_str(&format!(
+                        "\\ngain={}\\ncover={}",
+                        format_float(stat.loss_chg, 9),
+                        format_float(stat.sum_hess, 9)
+                    ));
+                }
+                result.push_str(&format!("    {} [ label=\"{}\" shape=box ]\n", nid, label));
+                let (yes, no) = split.yes_no();
+                for (child, branch, color) in [(yes, "yes", "#0000FF"), (no, "no", "#FF0000")] {
+                    let missing = match split.condition {
+                        // the no branch of indicators is taken by missing values only
+                        DumpCondition::Indicator => "",
+                        _ if child == split.missing => ", missing",
+                        _ => "",
+                    };
+                    result.push_str(&format!(
+                        "    {} -> {} [ label=\"{}{}\" color=\"{}\" ]\n",
+                        nid, child, branch, missing, color
+                    ));
+                }
+                self.dump_dot_node(split.left as usize, fmap, with_stats, result);
+                self.dump_dot_node(split.right as usize, fmap, with_stats, result);
+            }
+        }
+    }
+}
+
+/// Read-only view of a tree of a model
+#[derive(Clone, Copy)]
+pub struct Tree<'a> {
+    tree: &'a RegTree,
+    group: usize,
+    weight: f32,
+}
+
+impl<'a> Tree<'a> {
+    pub(crate) fn new(tree: &'a RegTree, group: usize, weight: f32) -> Tree<'a> {
+        return Tree {
+            tree,
+            group,
+            weight,
+        };
+    }
+
+    /// Output group the tree contributes to, 0 for multi-target trees
+    pub fn group(&self) -> usize {
+        return self.group;
+    }
+
+    /// Weight of the tree output, 1 except for DART models
+    pub fn weight(&self) -> f32 {
+        return self.weight;
+    }
+
+    /// Number of nodes, including deleted ones
+    pub fn num_nodes(&self) -> usize {
+        return self.tree.nodes.len();
+    }
+
+    /// Number of roots, nodes `0..num_roots` are the roots of the tree
+    pub fn num_roots(&self) -> usize {
+        return self.tree.num_roots();
+    }
+
+    pub(crate) fn dump(&self, fmap: &FeatureMap, with_stats: bool, format: DumpFormat) -> String {
+        return self.tree.dump(fmap, with_stats, format);
+    }
+
+    /// Number of leaf values, more than 1 for multi-target trees
+    pub fn size_leaf_vector(&self) -> usize {
+        return self.tree.size_leaf_vector();
+    }
+
+    /// Returns the node `nid`
+    pub fn node(&self, nid: usize) -> Option<TreeNode<'a>> {
+        if nid >= self.tree.nodes.len() {
+            return None;
+        }
+        return Some(TreeNode {
+            tree: self.tree,
+            nid,
+        });
+    }
+
+    /// Returns the nodes reachable from the roots, parents before their children
+    pub fn nodes(&self) -> Vec<TreeNode<'a>> {
+        let mut result = vec![];
+        let mut stack: Vec<usize> = (0..self.num_roots()).rev().collect();
+        while let Some(nid) = stack.pop() {
+            let node = TreeNode {
+                tree: self.tree,
+                nid,
+            };
+            if let (Some(left), Some(right)) = (node.left_child(), node.right_child()) {
+                stack.push(right);
+                stack.push(left);
+            }
+            result.push(node);
+        }
+        return result;
+    }
+
+    /// Returns the reachable leaves
+    pub fn leaves(&self) -> Vec<TreeNode<'a>> {
+        return self.nodes().into_iter().filter(|n| n.is_leaf()).collect();
+    }
+
+    /// Number of splits on the longest path from a root to a leaf
+    pub fn max_depth(&self) -> usize {
+        let mut max_depth = 0;
+        let mut stack: Vec<(usize, usize)> = (0..self.num_roots()).map(|nid| (nid, 0)).collect();
+        while let Some((nid, depth)) = stack.pop() {
+            max_depth = cmp::max(max_depth, depth);
+            if let (Some(left), Some(right)) = (
+                self.tree.nodes[nid].left_child(),
+                self.tree.nodes[nid].right_child(),
+            ) {
+                stack.push((left, depth + 1));
+                stack.push((right, depth + 1));
+            }
+        }
+        return max_depth;
+    }
+}
+
+/// Read-only view of a node of a tree
+#[derive(Clone, Copy)]
+pub struct TreeNode<'a> {
+    tree: &'a RegTree,
+    nid: usize,
+}
+
+impl<'a> TreeNode<'a> {
+    fn node(&self) -> &'a Node {
+        return &self.tree.nodes[self.nid];
+    }
+
+    fn stat(&self) -> &'a RTreeNodeStat {
+        return &self.tree.stats[self.nid];
+    }
+
+    /// Index of the node in its tree
+    pub fn id(&self) -> usize {
+        return self.nid;
+    }
+
+    /// Parent node, `None` for roots
+    pub fn parent(&self) -> Option<usize> {
+        let parent = self.node().parent;
+        // the highest bit tells whether the node is a left child
+        return if parent == -1 {
+            None
+        } else {
+            Some((parent & i32::MAX) as usize)
+        };
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        return self.node().left_child().is_none();
+    }
+
+    /// Whether the node is a partition-based split on a categorical feature
+    pub fn is_categorical(&self) -> bool {
+        return matches!(
+            self.node().leaf_or_split,
+            LeafOrSplit::CategoricalSplit { .. }
+        );
+    }
+
+    /// Feature the node splits on, `None` for leaves
+    pub fn split_index(&self) -> Option<usize> {
+        return match self.node().leaf_or_split {
+            LeafOrSplit::LeafValue(_) | LeafOrSplit::LeafVector(_) => None,
+            LeafOrSplit::Split { split_index, .. }
+            | LeafOrSplit::CategoricalSplit { split_index, .. } => Some(split_index as usize),
+        };
+    }
+
+    /// Threshold of a numerical split, values below it go left
+    pub fn split_condition(&self) -> Option<f32> {
+        return match self.node().leaf_or_split {
+            LeafOrSplit::Split { split_cond, .. } => Some(split_cond),
+            _ => None,
+        };
+    }
+
+    /// Categories of a categorical split going right, in increasing order
+    pub fn categories(&self) -> Option<Vec<usize>> {
+        return match self.node().leaf_or_split {
+            LeafOrSplit::CategoricalSplit { categories, .. } => {
+                Some(self.tree.categories[categories].categories())
+            }
+            _ => None,
+        };
+    }
+
+    pub fn left_child(&self) -> Option<usize> {
+        return self.node().left_child();
+    }
+
+    pub fn right_child(&self) -> Option<usize> {
+        return self.node().right_child();
+    }
+
+    /// Child taken by missing values
+    pub fn default_child(&self) -> Option<usize> {
+        return match self.node().leaf_or_split {
+            LeafOrSplit::LeafValue(_) | LeafOrSplit::LeafVector(_) => None,
+            LeafOrSplit::Split { default_next, .. }
+            | LeafOrSplit::CategoricalSplit { default_next, .. } => Some(default_next as usize),
+        };
+    }
+
+    /// Whether missing values go left, `false` for leaves
+    pub fn default_left(&self) -> bool {
+        return self.default_child().is_some() && self.default_child() == self.left_child();
+    }
+
+    /// Value of a leaf, `None` for splits and leaves of multi-target trees
+    pub fn leaf_value(&self) -> Option<f32> {
+        return match self.node().leaf_or_split {
+            LeafOrSplit::LeafValue(leaf_value) => Some(leaf_value),
+            _ => None,
+        };
+    }
+
+    /// Values of a leaf, one per target, `None` for splits
+    pub fn leaf_vector(&self) -> Option<&'a [f32]> {
+        return match &self.node().leaf_or_split {
+            LeafOrSplit::LeafValue(leaf_value) => Some(std::slice::from_ref(leaf_value)),
+            LeafOrSplit::LeafVector(offset) => {
+                Some(&self.tree.leaf_vectors[*offset..*offset + self.tree.size_leaf_vector()])
+            }
+            _ => None,
+        };
+    }
+
+    /// Loss change (gain) of the split
+    pub fn loss_change(&self) -> f32 {
+        return self.stat().loss_chg;
+    }
+
+    /// Sum of hessians (cover) of the training instances reaching the node
+    pub fn sum_hessian(&self) -> f32 {
+        return self.stat().sum_hess;
+    }
+
+    /// Weight of the node before it was split
+    pub fn base_weight(&self) -> f32 {
+        return self.stat().base_weight;
+    }
 }
 
 impl Clone for RegTree {
     fn clone(&self) -> RegTree {
         return RegTree {
-            param: self.param.clone(),
+            param: self.param,
             nodes: self.nodes.clone(),
             stats: self.stats.clone(),
+            categories: self.categories.clone(),
+            leaf_vectors: self.leaf_vectors.clone(),
+            mean_values: self.mean_values.clone(),
         };
     }
 }
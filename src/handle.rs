@@ -0,0 +1,196 @@
+//! Shared handle to a model which can be replaced while predictions are running.
+
+use crate::errors::*;
+use crate::fvec::FVec;
+use crate::predictor::Predictor;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::SystemTime;
+
+/// Rows with their expected predictions, checked against each new model before it is
+/// published
+pub struct Canary<F: FVec> {
+    rows: Vec<F>,
+    expected: Vec<Vec<f32>>,
+    tolerance: f32,
+}
+
+impl<F: FVec> Canary<F> {
+    /// `expected` holds the transformed predictions (`output_margin == false`) of each
+    /// row, a new model must predict them within `tolerance`
+    pub fn new(rows: Vec<F>, expected: Vec<Vec<f32>>, tolerance: f32) -> Result<Canary<F>> {
+        if rows.len() != expected.len() {
+            bail!(
+                "canary has {} rows but {} expected predictions",
+                rows.len(),
+                expected.len()
+            );
+        }
+        return Ok(Canary {
+            rows,
+            expected,
+            tolerance,
+        });
+    }
+
+    fn check(&self, predictor: &Predictor<F>) -> Result<()> {
+        for (i, (row, expected)) in self.rows.iter().zip(self.expected.iter()).enumerate() {
+            // a model panicking on a canary row is rejected like any other invalid model
+            let predicted = match panic::catch_unwind(AssertUnwindSafe(|| {
+                predictor.try_predict(row, false, 0)
+            })) {
+                Ok(predicted) => predicted?,
+                Err(_) => {
+                    return Err(Error::from_kind(ErrorKind::IncompatibleModel(format!(
+                        "panicked on canary row {}",
+                        i
+                    ))))
+                }
+            };
+            let matches = predicted.len() == expected.len()
+                && predicted
+                    .iter()
+                    .zip(expected.iter())
+                    .all(|(p, e)| (p - e).abs() <= self.tolerance);
+            if !matches {
+                return Err(Error::from_kind(ErrorKind::CanaryMismatch(
+                    i,
+                    expected.clone(),
+                    predicted,
+                )));
+            }
+        }
+        return Ok(());
+    }
+}
+
+/// Thread-safe handle to the current model of a service.
+///
+/// Predictions take the current model with `get` and keep using it until they are done,
+/// while `replace`, `reload` or `reload_if_modified` publish a new model. A new model
+/// must have the features, output groups and objective of the current one, and pass the
+/// canary if any, otherwise the current model is kept.
+pub struct PredictorHandle<F: FVec> {
+    current: RwLock<Arc<Predictor<F>>>,
+    /// file the model is reloaded from
+    path: Option<PathBuf>,
+    canary: Option<Canary<F>>,
+    /// modification time of the file of the current model, also serializes reloads
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl<F: FVec> PredictorHandle<F> {
+    pub fn new(predictor: Predictor<F>) -> PredictorHandle<F> {
+        return PredictorHandle {
+            current: RwLock::new(Arc::new(predictor)),
+            path: None,
+            canary: None,
+            modified: Mutex::new(None),
+        };
+    }
+
+    /// Loads the model saved at `path`, which `reload` reads again
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<PredictorHandle<F>> {
+        let path = path.as_ref().to_path_buf();
+        let modified = fs::metadata(&path)?.modified()?;
+        let predictor = Predictor::from_path(&path)?;
+        return Ok(PredictorHandle {
+            current: RwLock::new(Arc::new(predictor)),
+            path: Some(path),
+            canary: None,
+            modified: Mutex::new(Some(modified)),
+        });
+    }
+
+    /// Checks new models against `canary`. The current model must pass it.
+    pub fn with_canary(mut self, canary: Canary<F>) -> Result<PredictorHandle<F>> {
+        canary.check(&self.get())?;
+        self.canary = Some(canary);
+        return Ok(self);
+    }
+
+    /// Returns the current model
+    pub fn get(&self) -> Arc<Predictor<F>> {
+        return self
+            .current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+    }
+
+    /// Checks that `predictor` can replace the current model
+    pub fn validate(&self, predictor: &Predictor<F>) -> Result<()> {
+        let current = self.get();
+        if predictor.num_feature() != current.num_feature() {
+            return Err(Error::from_kind(ErrorKind::IncompatibleModel(format!(
+                "{} features instead of {}",
+                predictor.num_feature(),
+                current.num_feature()
+            ))));
+        }
+        if predictor.num_output_group() != current.num_output_group() {
+            return Err(Error::from_kind(ErrorKind::IncompatibleModel(format!(
+                "{} output groups instead of {}",
+                predictor.num_output_group(),
+                current.num_output_group()
+            ))));
+        }
+        if predictor.objective() != current.objective() {
+            return Err(Error::from_kind(ErrorKind::IncompatibleModel(format!(
+                "objective '{}' instead of '{}'",
+                predictor.objective(),
+                current.objective()
+            ))));
+        }
+        if let Some(canary) = &self.canary {
+            canary.check(predictor)?;
+        }
+        return Ok(());
+    }
+
+    /// Validates `predictor` and publishes it, returning the previous model
+    pub fn replace(&self, predictor: Predictor<F>) -> Result<Arc<Predictor<F>>> {
+        let _modified = self.modified.lock().unwrap_or_else(PoisonError::into_inner);
+        return self.publish(predictor);
+    }
+
+    fn publish(&self, predictor: Predictor<F>) -> Result<Arc<Predictor<F>>> {
+        self.validate(&predictor)?;
+        let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+        return Ok(std::mem::replace(&mut *current, Arc::new(predictor)));
+    }
+
+    /// Reads the model file again and publishes it
+    pub fn reload(&self) -> Result<()> {
+        let mut modified = self.modified.lock().unwrap_or_else(PoisonError::into_inner);
+        let path = self.path()?;
+        let new_modified = fs::metadata(path)?.modified()?;
+        self.publish(Predictor::from_path(path)?)?;
+        *modified = Some(new_modified);
+        return Ok(());
+    }
+
+    /// Reloads the model if its file was modified since it was last loaded, returning
+    /// whether it was. Meant to be polled, a model failing validation is tried again
+    /// on the next call.
+    pub fn reload_if_modified(&self) -> Result<bool> {
+        let mut modified = self.modified.lock().unwrap_or_else(PoisonError::into_inner);
+        let path = self.path()?;
+        let new_modified = fs::metadata(path)?.modified()?;
+        if *modified == Some(new_modified) {
+            return Ok(false);
+        }
+        self.publish(Predictor::from_path(path)?)?;
+        *modified = Some(new_modified);
+        return Ok(true);
+    }
+
+    fn path(&self) -> Result<&Path> {
+        return match &self.path {
+            Some(path) => Ok(path),
+            None => bail!("model was not loaded from a file"),
+        };
+    }
+}
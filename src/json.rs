@@ -0,0 +1,135 @@
+use crate::errors::*;
+use serde_json::Value;
+
+/// Returns the member `name` of a JSON object
+pub fn field<'a>(value: &'a Value, name: &str) -> Result<&'a Value> {
+    return value
+        .get(name)
+        .ok_or_else(|| format!("missing field '{}' in model", name).into());
+}
+
+/// Returns the member `name` of a JSON object, if present and not null
+pub fn opt_field<'a>(value: &'a Value, name: &str) -> Option<&'a Value> {
+    return value.get(name).filter(|v| !v.is_null());
+}
+
+/// Reads a number, xgboost stores most parameters as strings (`"5E-1"`)
+pub fn as_f32(value: &Value) -> Result<f32> {
+    return match value {
+        Value::Number(n) => n
+            .as_f64()
+            .map(|x| x as f32)
+            .ok_or_else(|| format!("invalid number '{}' in model", n).into()),
+        Value::String(s) => s
+            .trim()
+            .parse::<f32>()
+            .chain_err(|| format!("invalid number '{}' in model", s)),
+        Value::Bool(b) => Ok(*b as i32 as f32),
+        _ => bail!("expected number in model, got '{}'", value),
+    };
+}
+
+pub fn as_i64(value: &Value) -> Result<i64> {
+    return match value {
+        Value::Number(n) => match n.as_i64() {
+            Some(x) => Ok(x),
+            None => Ok(as_f32(value)? as i64),
+        },
+        Value::String(s) => s
+            .trim()
+            .parse::<i64>()
+            .chain_err(|| format!("invalid integer '{}' in model", s)),
+        Value::Bool(b) => Ok(*b as i64),
+        _ => bail!("expected integer in model, got '{}'", value),
+    };
+}
+
+pub fn as_i32(value: &Value) -> Result<i32> {
+    return Ok(as_i64(value)? as i32);
+}
+
+pub fn as_usize(value: &Value) -> Result<usize> {
+    let x = as_i64(value)?;
+    if x < 0 {
+        bail!("expected non-negative integer in model, got '{}'", x);
+    }
+    return Ok(x as usize);
+}
+
+pub fn as_str(value: &Value) -> Result<&str> {
+    return value
+        .as_str()
+        .ok_or_else(|| format!("expected string in model, got '{}'", value).into());
+}
+
+pub fn as_array(value: &Value) -> Result<&Vec<Value>> {
+    return value
+        .as_array()
+        .ok_or_else(|| format!("expected array in model, got '{}'", value).into());
+}
+
+pub fn f32_vec(value: &Value) -> Result<Vec<f32>> {
+    return as_array(value)?.iter().map(as_f32).collect();
+}
+
+pub fn i32_vec(value: &Value) -> Result<Vec<i32>> {
+    return as_array(value)?.iter().map(as_i32).collect();
+}
+
+pub fn string_vec(value: &Value) -> Result<Vec<String>> {
+    return as_array(value)?
+        .iter()
+        .map(|x| Ok(as_str(x)?.to_string()))
+        .collect();
+}
+
+/// Reads a list of numbers written either as an array or as a string such as `"[0.1, 0.5]"`
+pub fn f32_list(value: &Value) -> Result<Vec<f32>> {
+    return match value {
+        Value::Array(_) => f32_vec(value),
+        Value::String(s) => s
+            .trim()
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(|x| {
+                x.parse::<f32>()
+                    .chain_err(|| format!("invalid number '{}' in model", x))
+            })
+            .collect(),
+        _ => Ok(vec![as_f32(value)?]),
+    };
+}
+
+/// Writes a number with the shortest representation reading back as the same `f32`.
+/// JSON has no NaN nor infinity, they fail.
+pub fn from_f32(value: f32) -> Result<Value> {
+    if !value.is_finite() {
+        bail!("{} can't be written to JSON", value);
+    }
+    // the `f64` read from the shortest representation of the `f32` is written as short
+    let number: f64 = value
+        .to_string()
+        .parse()
+        .chain_err(|| format!("failed to convert {} to f64", value))?;
+    return match serde_json::Number::from_f64(number) {
+        Some(n) => Ok(Value::Number(n)),
+        None => bail!("{} can't be written to JSON", value),
+    };
+}
+
+pub fn from_f32_vec(values: &[f32]) -> Result<Value> {
+    return Ok(Value::Array(
+        values
+            .iter()
+            .map(|x| from_f32(*x))
+            .collect::<Result<Vec<Value>>>()?,
+    ));
+}
+
+/// Writes a parameter, xgboost saves them as strings
+pub fn param<T: ToString>(value: T) -> Value {
+    return Value::String(value.to_string());
+}
@@ -1,4 +1,5 @@
 #![recursion_limit = "1024"]
+#![allow(clippy::needless_return)]
 
 #[macro_use]
 extern crate error_chain;
@@ -8,6 +9,7 @@ pub mod errors {
         foreign_links {
             Io(::std::io::Error);
             Utf8Error(::std::string::FromUtf8Error);
+            Json(::serde_json::Error);
         }
         // Define additional `ErrorKind` variants.  Define custom responses with the
         // `description` and `display` calls.
@@ -20,12 +22,46 @@ pub mod errors {
                 description("Unsupported object function type")
                 display("Unsupported object function type: '{}'", t)
             }
+            InvalidRootIndex(root_index: usize, num_roots: usize) {
+                description("Invalid root index")
+                display("Root index {} is out of range for model with {} roots", root_index, num_roots)
+            }
+            InvalidIterationRange(begin: usize, end: usize) {
+                description("Invalid iteration range")
+                display("Invalid iteration range: begin {} is past end {}", begin, end)
+            }
+            InvalidBaseMargin(size: usize, num_outputs: usize) {
+                description("Invalid base margin")
+                display("Base margin of size {} does not match {} outputs", size, num_outputs)
+            }
+            UnknownFeatureName(name: String) {
+                description("Unknown feature name")
+                display("Unknown feature name: '{}'", name)
+            }
+            FeatureCountMismatch(size: usize, num_feature: usize) {
+                description("Feature count mismatch")
+                display("Input has {} features but model has {} features", size, num_feature)
+            }
+            IncompatibleModel(reason: String) {
+                description("Incompatible model")
+                display("Model can't replace the current one: {}", reason)
+            }
+            CanaryMismatch(row: usize, expected: Vec<f32>, predicted: Vec<f32>) {
+                description("Canary mismatch")
+                display("Canary row {} is predicted {:?} instead of {:?}", row, predicted, expected)
+            }
         }
     }
 }
 
-mod functions;
+pub mod compact;
+pub mod functions;
 pub mod fvec;
 mod gbm;
+pub mod handle;
+mod json;
+pub mod model_format;
 pub mod model_reader;
+pub mod model_writer;
 pub mod predictor;
+mod ubjson;
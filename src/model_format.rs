@@ -0,0 +1,121 @@
+use crate::errors::*;
+use std::borrow::Cow;
+use std::fmt;
+
+/// Serialization format of a model
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModelFormat {
+    /// binary format of xgboost before 1.0, including `binf` and xgboost4j-spark models
+    Binary,
+    Json,
+    /// Universal Binary JSON
+    Ubjson,
+}
+
+impl ModelFormat {
+    /// Detects the format of the uncompressed model `bytes`. JSON starts with `{` and a
+    /// key, anything else which is not UBJSON is read as binary.
+    pub fn detect(bytes: &[u8]) -> ModelFormat {
+        let skip_whitespace = |bytes: &[u8]| -> usize {
+            return bytes
+                .iter()
+                .position(|b| !b.is_ascii_whitespace())
+                .unwrap_or(bytes.len());
+        };
+        let start = skip_whitespace(bytes);
+        return match (&bytes[start..], start) {
+            // UBJSON keys start with the type of their length, or an optimized container
+            ([b'{', b'i', ..], 0)
+            | ([b'{', b'U', ..], 0)
+            | ([b'{', b'I', ..], 0)
+            | ([b'{', b'l', ..], 0)
+            | ([b'{', b'L', ..], 0)
+            | ([b'{', b'$', ..], 0)
+            | ([b'{', b'#', ..], 0) => ModelFormat::Ubjson,
+            ([b'{', rest @ ..], _) if rest.get(skip_whitespace(rest)) == Some(&b'"') => {
+                ModelFormat::Json
+            }
+            _ => ModelFormat::Binary,
+        };
+    }
+}
+
+/// Compression of a model file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Detects the compression of `bytes` from its magic number
+    pub fn detect(bytes: &[u8]) -> Compression {
+        return if bytes.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        };
+    }
+}
+
+/// Decompresses gzip or zstd `bytes`, other bytes are returned as is.
+/// Each compression requires its cargo feature, `gzip` or `zstd`.
+pub fn decompress(bytes: &[u8]) -> Result<Cow<'_, [u8]>> {
+    return match Compression::detect(bytes) {
+        Compression::None => Ok(Cow::Borrowed(bytes)),
+        Compression::Gzip => Ok(Cow::Owned(gunzip(bytes)?)),
+        Compression::Zstd => Ok(Cow::Owned(unzstd(bytes)?)),
+    };
+}
+
+#[cfg(feature = "gzip")]
+fn gunzip(bytes: &[u8]) -> Result<Vec<u8>> {
+    use std::io::Read;
+    let mut decompressed = Vec::new();
+    flate2::read::MultiGzDecoder::new(bytes)
+        .read_to_end(&mut decompressed)
+        .chain_err(|| "failed to decompress gzip model")?;
+    return Ok(decompressed);
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gunzip(_bytes: &[u8]) -> Result<Vec<u8>> {
+    bail!("model is gzip-compressed, enable the `gzip` feature to read it");
+}
+
+#[cfg(feature = "zstd")]
+fn unzstd(bytes: &[u8]) -> Result<Vec<u8>> {
+    return zstd::stream::decode_all(bytes).chain_err(|| "failed to decompress zstd model");
+}
+
+#[cfg(not(feature = "zstd"))]
+fn unzstd(_bytes: &[u8]) -> Result<Vec<u8>> {
+    bail!("model is zstd-compressed, enable the `zstd` feature to read it");
+}
+
+/// Version of xgboost a model was saved with, as far as its format tells
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModelVersion {
+    /// binary format of xgboost before 1.0
+    Legacy,
+    /// binary format of xgboost before 1.0, with the `binf` header of model files
+    Binf,
+    /// binary format of xgboost 1.x, which saves its major and minor version
+    Binary(u32, u32),
+    /// JSON or UBJSON format, with the version saved in the model
+    Json(u32, u32, u32),
+}
+
+impl fmt::Display for ModelVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            ModelVersion::Legacy => write!(f, "legacy"),
+            ModelVersion::Binf => write!(f, "binf"),
+            ModelVersion::Binary(major, minor) => write!(f, "{}.{}", major, minor),
+            ModelVersion::Json(major, minor, patch) => write!(f, "{}.{}.{}", major, minor, patch),
+        };
+    }
+}
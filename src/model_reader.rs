@@ -1,6 +1,7 @@
 use crate::errors::*;
-use byteorder::{ReadBytesExt, BE, LE};
-use std::io::Read;
+use byteorder::{ByteOrder, ReadBytesExt, BE, LE};
+use std::cmp;
+use std::io::{self, BufRead, Read};
 
 pub trait ModelReader: ReadBytesExt {
     #[inline]
@@ -30,50 +31,75 @@ pub trait ModelReader: ReadBytesExt {
         return Ok(byte as i32);
     }
 
+    #[inline]
+    fn read_i32_be(&mut self) -> Result<i32> {
+        return self
+            .read_i32::<BE>()
+            .chain_err(|| "failed to read i32 from model");
+    }
+
+    /// Reads a string written by Java's `DataOutput.writeUTF`, prefixed with its
+    /// big-endian 16-bit length
+    fn read_utf(&mut self) -> Result<String> {
+        let len = self.read_u16::<BE>()? as usize;
+        return Ok(String::from_utf8(self.read_u8_vec(len)?)?);
+    }
+
     fn read_to_i32_buffer(&mut self, buffer: &mut [i32]) -> Result<()> {
-        for b in buffer.iter_mut() {
-            match self.read_i32_le() {
-                Ok(val) => *b = val,
-                Err(e) => return Err(e),
-            }
-        }
-        return Ok(());
+        return self
+            .read_i32_into::<LE>(buffer)
+            .chain_err(|| "failed to read i32 slice from model");
     }
 
     fn read_to_f32_buffer(&mut self, buffer: &mut [f32]) -> Result<()> {
-        for b in buffer.iter_mut() {
-            match self.read_f32_le() {
-                Ok(val) => *b = val,
-                Err(e) => return Err(e),
-            }
-        }
-        return Ok(());
+        return self
+            .read_f32_into::<LE>(buffer)
+            .chain_err(|| "failed to read f32 slice from model");
     }
+
     fn read_to_f64_buffer_be(&mut self, buffer: &mut [f64]) -> Result<()> {
-        for b in buffer.iter_mut() {
-            *b = self.read_f64::<BE>()?;
-        }
-        return Ok(());
+        return self
+            .read_f64_into::<BE>(buffer)
+            .chain_err(|| "failed to read f64 slice from model");
+    }
+
+    fn read_f64_vec_be(&mut self, num_values: usize) -> Result<Vec<f64>> {
+        let bytes = self.read_u8_vec(num_values.saturating_mul(8))?;
+        let mut values = vec![0f64; num_values];
+        BE::read_f64_into(&bytes, &mut values);
+        return Ok(values);
     }
 
     fn read_int_vec(&mut self, num_values: usize) -> Result<Vec<i32>> {
-        return (0..num_values).map(|_| self.read_i32_le()).collect();
+        let bytes = self.read_u8_vec(num_values.saturating_mul(4))?;
+        let mut values = vec![0i32; num_values];
+        LE::read_i32_into(&bytes, &mut values);
+        return Ok(values);
     }
 
     fn read_float_vec(&mut self, num_values: usize) -> Result<Vec<f32>> {
-        return (0..num_values).map(|_| self.read_f32_le()).collect();
+        let bytes = self.read_u8_vec(num_values.saturating_mul(4))?;
+        let mut values = vec![0f32; num_values];
+        LE::read_f32_into(&bytes, &mut values);
+        return Ok(values);
     }
 
     fn skip(&mut self, num_bytes: usize) -> Result<()> {
-        let mut vec: Vec<u8> = vec![0u8; num_bytes];
-        return self
-            .read_exact(vec.as_mut_slice())
-            .chain_err(|| "failed to read u8 slice from model");
+        let skipped = io::copy(&mut self.take(num_bytes as u64), &mut io::sink())?;
+        if skipped != num_bytes as u64 {
+            bail!("failed to read u8 slice from model");
+        }
+        return Ok(());
     }
 
+    /// Reads `size` bytes. The buffer grows with the bytes actually read, so a corrupted
+    /// size fails at the end of the input rather than allocating it upfront.
     fn read_u8_vec(&mut self, size: usize) -> Result<Vec<u8>> {
-        let mut vec: Vec<u8> = vec![0u8; size];
-        self.read_exact(vec.as_mut_slice())?;
+        let mut vec: Vec<u8> = Vec::new();
+        self.take(size as u64).read_to_end(&mut vec)?;
+        if vec.len() != size {
+            bail!("failed to read u8 slice from model");
+        }
         return Ok(vec);
     }
 
@@ -84,3 +110,82 @@ pub trait ModelReader: ReadBytesExt {
 }
 
 impl<T: Read> ModelReader for T {}
+
+/// Reads `count` records of `size` bytes, decoding them straight from the buffer of
+/// `reader` while it holds whole records. Once it holds less than a record, such as
+/// with a record split between two fills or a reader which does not read ahead, the
+/// remaining records are read in one go.
+pub(crate) fn read_records<T, R, D>(
+    reader: &mut T,
+    count: usize,
+    size: usize,
+    decode: D,
+) -> Result<Vec<R>>
+where
+    T: BufRead,
+    D: Fn(&[u8]) -> R,
+{
+    let mut records = Vec::new();
+    while records.len() < count {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            bail!("failed to read {} records from model", count);
+        }
+        let num_records = cmp::min(buffer.len() / size, count - records.len());
+        if num_records == 0 {
+            let bytes = reader
+                .read_u8_vec((count - records.len()).saturating_mul(size))
+                .chain_err(|| format!("failed to read {} records from model", count))?;
+            records.extend(bytes.chunks_exact(size).map(&decode));
+            break;
+        }
+        records.extend(buffer[..num_records * size].chunks_exact(size).map(&decode));
+        reader.consume(num_records * size);
+    }
+    return Ok(records);
+}
+
+/// `BufRead` over a reader which never reads ahead of what is consumed: `fill_buf`
+/// buffers a single byte and other reads go straight to the reader, which is left right
+/// after the model.
+pub(crate) struct ExactReader<'a, T: Read + ?Sized> {
+    reader: &'a mut T,
+    buffer: [u8; 1],
+    buffered: bool,
+}
+
+impl<'a, T: Read + ?Sized> ExactReader<'a, T> {
+    pub(crate) fn new(reader: &'a mut T) -> ExactReader<'a, T> {
+        return ExactReader {
+            reader,
+            buffer: [0u8],
+            buffered: false,
+        };
+    }
+}
+
+impl<T: Read + ?Sized> Read for ExactReader<'_, T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.buffered && !buf.is_empty() {
+            buf[0] = self.buffer[0];
+            self.buffered = false;
+            return Ok(1);
+        }
+        return self.reader.read(buf);
+    }
+}
+
+impl<T: Read + ?Sized> BufRead for ExactReader<'_, T> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if !self.buffered {
+            self.buffered = self.reader.read(&mut self.buffer)? == 1;
+        }
+        return Ok(&self.buffer[..self.buffered as usize]);
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if amt > 0 {
+            self.buffered = false;
+        }
+    }
+}
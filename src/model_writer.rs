@@ -0,0 +1,49 @@
+use crate::errors::*;
+use byteorder::{WriteBytesExt, LE};
+use std::io::Write;
+
+pub trait ModelWriter: WriteBytesExt {
+    #[inline]
+    fn write_i32_le(&mut self, value: i32) -> Result<()> {
+        return self
+            .write_i32::<LE>(value)
+            .chain_err(|| "failed to write i32 to model");
+    }
+
+    #[inline]
+    fn write_i64_le(&mut self, value: i64) -> Result<()> {
+        return self
+            .write_i64::<LE>(value)
+            .chain_err(|| "failed to write i64 to model");
+    }
+
+    #[inline]
+    fn write_f32_le(&mut self, value: f32) -> Result<()> {
+        return self
+            .write_f32::<LE>(value)
+            .chain_err(|| "failed to write f32 to model");
+    }
+
+    fn write_int_vec(&mut self, values: &[i32]) -> Result<()> {
+        for value in values.iter() {
+            self.write_i32_le(*value)?;
+        }
+        return Ok(());
+    }
+
+    fn write_float_vec(&mut self, values: &[f32]) -> Result<()> {
+        for value in values.iter() {
+            self.write_f32_le(*value)?;
+        }
+        return Ok(());
+    }
+
+    fn write_u8_vec_len(&mut self, values: &[u8]) -> Result<()> {
+        self.write_i64_le(values.len() as i64)?;
+        return self
+            .write_all(values)
+            .chain_err(|| "failed to write u8 slice to model");
+    }
+}
+
+impl<T: Write + ?Sized> ModelWriter for T {}
@@ -1,10 +1,28 @@
+use crate::compact;
 use crate::errors::*;
-use crate::functions::{get_classify_func_type, get_classify_function, ObjFunction};
-use crate::fvec::FVec;
+use crate::functions::{get_obj_function, ObjFunction};
+use crate::fvec::{FVec, FVecNamed, ToFloat};
+pub use crate::gbm::dump::DumpFormat;
+use crate::gbm::dump::FeatureMap;
+use crate::gbm::gbtree::GBTree;
 use crate::gbm::grad_booster::GradBooster;
-use crate::model_reader::ModelReader;
+pub use crate::gbm::grad_booster::ImportanceType;
+pub use crate::gbm::{Tree, TreeNode};
+use crate::json;
+use crate::model_format::{self, ModelFormat, ModelVersion};
+use crate::model_reader::{ExactReader, ModelReader};
+use crate::model_writer::ModelWriter;
 use byteorder::{ByteOrder, LE};
+use serde_json::{json, Value};
+use std::cmp;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, Read, Write};
+use std::path::Path;
+use std::slice;
+use std::sync::Arc;
 
+#[derive(Clone)]
 struct ModelParam {
     /// global bias
     base_score: f32,
@@ -12,8 +30,14 @@ struct ModelParam {
     num_feature: usize,
     /// number of class, if it is multi-class classification
     num_class: i32,
-    /// whether the model itself is saved with pbuffer
+    /// whether the model itself is saved with pbuffer (before 0.6),
+    /// or with extra attributes (0.6 and later)
     saved_with_pbuffer: i32,
+    /// whether evaluation metrics follow the model (0.6 and later)
+    contain_eval_metrics: bool,
+    /// version saved by xgboost 1.x, 0 before
+    major_version: u32,
+    minor_version: u32,
 }
 
 impl ModelParam {
@@ -30,97 +54,1426 @@ impl ModelParam {
             num_feature,
             num_class,
             saved_with_pbuffer,
+            contain_eval_metrics: reserved[0] != 0,
+            major_version: reserved[1] as u32,
+            minor_version: reserved[2] as u32,
+        });
+    }
+
+    /// Writes the parameters with the version of xgboost `(major, minor)`, `(0, 0)` for
+    /// the format before 1.0
+    fn write_to<T: ModelWriter>(
+        &self,
+        writer: &mut T,
+        contain_extra_attrs: bool,
+        version: (u32, u32),
+    ) -> Result<()> {
+        writer.write_f32_le(self.base_score)?;
+        writer.write_int_vec(&[
+            self.num_feature as i32,
+            self.num_class,
+            contain_extra_attrs as i32,
+        ])?;
+        // evaluation metrics are not kept
+        let mut reserved = [0i32; 30];
+        reserved[1] = version.0 as i32;
+        reserved[2] = version.1 as i32;
+        return writer.write_int_vec(&reserved);
+    }
+
+    fn read_from_json(
+        learner_model_param: &Value,
+        obj_func: &dyn ObjFunction,
+    ) -> Result<ModelParam> {
+        let base_score =
+            json::opt_field(learner_model_param, "base_score").map_or(Ok(0.5f32), json::as_f32)?;
+        return Ok(ModelParam {
+            base_score: obj_func.prob_to_margin(base_score),
+            num_feature: json::as_usize(json::field(learner_model_param, "num_feature")?)?,
+            num_class: json::opt_field(learner_model_param, "num_class")
+                .map_or(Ok(0), json::as_i32)?,
+            saved_with_pbuffer: 0,
+            contain_eval_metrics: false,
+            major_version: 0,
+            minor_version: 0,
+        });
+    }
+}
+
+/// Parameters of a model saved by xgboost4j-spark, which precede the binary model
+#[derive(Clone, Debug, PartialEq)]
+pub struct SparkModelParam {
+    /// `_cls_` for classification models, `_reg_` for regression models
+    pub model_type: String,
+    pub features_col: String,
+    pub label_col: String,
+    pub prediction_col: String,
+    /// column of the margins, classification models only
+    pub raw_prediction_col: Option<String>,
+    /// thresholds of each class, classification models only
+    pub thresholds: Option<Vec<f64>>,
+}
+
+impl SparkModelParam {
+    const MODEL_TYPE_CLS: &'static str = "_cls_";
+    const MODEL_TYPE_REG: &'static str = "_reg_";
+
+    fn read_from<T: ModelReader>(
+        model_type: &str,
+        features_col: String,
+        reader: &mut T,
+    ) -> Result<SparkModelParam> {
+        let label_col = reader.read_utf()?;
+        let prediction_col = reader.read_utf()?;
+        let (raw_prediction_col, thresholds) = if model_type == SparkModelParam::MODEL_TYPE_CLS {
+            let raw_prediction_col = reader.read_utf()?;
+            let num_thresholds = reader.read_i32_be()?;
+            if num_thresholds < 0 {
+                bail!("invalid number of thresholds {}", num_thresholds);
+            }
+            let thresholds = reader.read_f64_vec_be(num_thresholds as usize)?;
+            (Some(raw_prediction_col), Some(thresholds))
+        } else {
+            (None, None)
+        };
+        return Ok(SparkModelParam {
+            model_type: model_type.to_string(),
+            features_col,
+            label_col,
+            prediction_col,
+            raw_prediction_col,
+            thresholds,
         });
     }
 }
 
+/// Overview of a model
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModelSummary {
+    /// `gbtree`, `dart` or `gblinear`
+    pub booster: String,
+    /// name of the objective the model was trained with
+    pub objective: String,
+    /// global bias, in margin space
+    pub base_score: f32,
+    pub num_feature: usize,
+    pub num_output_group: usize,
+    pub num_trees: usize,
+    /// number of trees of each output group
+    pub num_trees_per_group: Vec<usize>,
+    pub num_boosted_rounds: usize,
+    /// largest depth of the trees
+    pub max_depth: usize,
+    /// number of trees of each depth, indexed by depth
+    pub depth_histogram: Vec<usize>,
+    /// number of reachable nodes of all trees
+    pub num_nodes: usize,
+    /// number of reachable leaves of all trees
+    pub num_leaves: usize,
+    /// features some split uses, in increasing order
+    pub used_features: Vec<usize>,
+}
+
+/// Options of `Predictor::predict_with`, the default predicts as `predict(feat, false, 0)`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PredictOptions {
+    /// outputs margins instead of transformed predictions
+    pub output_margin: bool,
+    /// boosting rounds `begin..end` to predict with, `end == 0` means up to the last round.
+    /// `(0, n)` is the same as `ntree_limit` n.
+    pub iteration_range: (usize, usize),
+    /// root of each tree to start from, models trained with `root_index` have several
+    pub root_index: usize,
+    /// margins replacing the global `base_score` (e.g. the log of exposure for Poisson
+    /// regression), laid out row by row as xgboost's `base_margin`: a single value per row,
+    /// or one value per row and output group
+    pub base_margin: Option<Vec<f32>>,
+}
+
+/// 64-bit FNV-1a hash, unlike `DefaultHasher` it is the same on every platform and release
+struct Fingerprint(u64);
+
+impl Fingerprint {
+    fn new() -> Fingerprint {
+        return Fingerprint(0xcbf2_9ce4_8422_2325);
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes.iter() {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100_0000_01b3);
+        }
+    }
+
+    fn write_u8(&mut self, value: u8) {
+        self.write(&[value]);
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write(&(value as u64).to_le_bytes());
+    }
+
+    fn write_f32(&mut self, value: f32) {
+        // adding zero turns -0 into 0, they are the same value
+        self.write(&(value + 0f32).to_bits().to_le_bytes());
+    }
+
+    fn write_str(&mut self, value: &str) {
+        self.write_usize(value.len());
+        self.write(value.as_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        return self.0;
+    }
+}
+
 /// Predicts using the Xgboost model
 pub struct Predictor<F: FVec> {
     mparam: ModelParam,
-    //SparkModelParam sparkModelParam;
-    obj_func: ObjFunction,
+    /// parameters preceding models saved by xgboost4j-spark
+    spark_model_param: Option<SparkModelParam>,
+    /// version of xgboost the model was saved with
+    version: ModelVersion,
+    /// name of the objective the model was saved with
+    objective: String,
+    obj_func: Arc<dyn ObjFunction>,
     gbm: Box<dyn GradBooster<F>>,
+    /// target quantiles of a `reg:quantileerror` model, one per output group
+    quantile_alphas: Option<Vec<f32>>,
+    /// learner attributes such as `best_iteration`
+    attributes: HashMap<String, String>,
+    /// whether predicting with all rounds stops at `best_iteration`
+    use_best_iteration: bool,
+    /// feature names, empty if the model has none
+    feature_names: Vec<String>,
+    /// feature types (`q`, `i`, `int`, `float` or `c`), empty if the model has none
+    feature_types: Vec<String>,
+    /// index of each feature name
+    feature_index: HashMap<String, usize>,
+    /// whether inputs must match the number of features of the model
+    strict_feature_count: bool,
 }
 
 impl<F: FVec> Predictor<F> {
-    fn read_model_params<T: ModelReader>(reader: &mut T) -> Result<ModelParam> {
+    fn read_model_params<T: ModelReader>(
+        reader: &mut T,
+    ) -> Result<(ModelParam, Option<SparkModelParam>, ModelVersion)> {
         let mut first4bytes = [0u8; 4];
         let mut next4bytes = [0u8; 4];
         reader.read_exact(&mut first4bytes)?;
         reader.read_exact(&mut next4bytes)?;
 
-        let (base_score, num_feature) = if &first4bytes == b"binf" {
-            (LE::read_f32(&next4bytes), reader.read_i32_le()? as usize)
-        } else if &first4bytes[..3] == [0x00u8, 0x05, 0x5f] {
-            // Model generated by xgboost4j-spark?
-            unimplemented!("not implemented for xgboost4j-spark models")
+        // models generated by xgboost4j-spark start with the UTF string `_cls_` or `_reg_`
+        let spark_model_type = if first4bytes[..3] == [0x00u8, 0x05, 0x5f] {
+            match (first4bytes[3], &next4bytes[..3]) {
+                (b'c', b"ls_") => Some(SparkModelParam::MODEL_TYPE_CLS),
+                (b'r', b"eg_") => Some(SparkModelParam::MODEL_TYPE_REG),
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        let (base_score, num_feature, spark_model_param) = if &first4bytes == b"binf" {
+            (
+                LE::read_f32(&next4bytes),
+                reader.read_i32_le()? as usize,
+                None,
+            )
+        } else if let Some(model_type) = spark_model_type {
+            // the length of the features column starts in the last byte read
+            let len = ((next4bytes[3] as usize) << 8) + reader.read_byte_as_i32()? as usize;
+            let features_col = String::from_utf8(reader.read_u8_vec(len)?)?;
+            let spark_model_param = SparkModelParam::read_from(model_type, features_col, reader)?;
+            (
+                reader.read_f32_le()?,
+                reader.read_i32_le()? as usize,
+                Some(spark_model_param),
+            )
         } else {
             (
                 LE::read_f32(&first4bytes),
                 LE::read_i32(&next4bytes) as usize,
+                None,
             )
         };
 
-        return ModelParam::read_from(base_score, num_feature, reader);
+        let mparam = ModelParam::read_from(base_score, num_feature, reader)?;
+        let version = if mparam.major_version != 0 {
+            ModelVersion::Binary(mparam.major_version, mparam.minor_version)
+        } else if &first4bytes == b"binf" {
+            ModelVersion::Binf
+        } else {
+            ModelVersion::Legacy
+        };
+        return Ok((mparam, spark_model_param, version));
+    }
+
+    /// Reads the learner attributes following the booster of 0.6+ binary models
+    fn read_attributes<T: ModelReader>(reader: &mut T) -> Result<HashMap<String, String>> {
+        let mut attributes = HashMap::new();
+        // models saved with pbuffer by older versions end right after the booster
+        let mut first_byte = [0u8; 1];
+        if reader.read(&mut first_byte)? == 0 {
+            return Ok(attributes);
+        }
+        let mut next_bytes = [0u8; 7];
+        reader.read_exact(&mut next_bytes)?;
+        let mut len_bytes = [0u8; 8];
+        len_bytes[0] = first_byte[0];
+        len_bytes[1..].copy_from_slice(&next_bytes);
+        let num_attributes = LE::read_u64(&len_bytes) as usize;
+        for _ in 0..num_attributes {
+            let name = String::from_utf8(reader.read_u8_vec_len()?)?;
+            let value = String::from_utf8(reader.read_u8_vec_len()?)?;
+            attributes.insert(name, value);
+        }
+        return Ok(attributes);
+    }
+
+    /// Reads the names of the evaluation metrics which follow the attributes, they are
+    /// not kept
+    fn skip_eval_metrics<T: ModelReader>(reader: &mut T) -> Result<()> {
+        let num_metrics = reader.read_i64_le()?;
+        if num_metrics < 0 {
+            bail!("invalid number of evaluation metrics {}", num_metrics);
+        }
+        for _ in 0..num_metrics {
+            reader.read_u8_vec_len()?;
+        }
+        return Ok(());
     }
 
-    /// Instantiates with the Xgboost model
+    /// Instantiates with the Xgboost model. `reader` is left right after the model, see
+    /// `read_from_buffered` to load faster from a `BufReader` or a `&[u8]`.
     pub fn read_from<T: ModelReader>(reader: &mut T) -> Result<Predictor<F>> {
-        let mparam = Predictor::<F>::read_model_params(reader)?;
+        return Predictor::read_from_with_obj_function(reader, None);
+    }
+
+    /// Instantiates with the Xgboost model, transforming outputs with `obj_func`
+    /// instead of the objective function the model was saved with
+    pub fn read_from_with_obj_function<T: ModelReader>(
+        reader: &mut T,
+        obj_func: Option<Arc<dyn ObjFunction>>,
+    ) -> Result<Predictor<F>> {
+        return Predictor::read_from_buffered_with_obj_function(
+            &mut ExactReader::new(reader),
+            obj_func,
+        );
+    }
+
+    /// Instantiates with the Xgboost model read from a buffered input, such as a
+    /// `BufReader` or a `&[u8]` whose trees are decoded in place. Reading stops at the end
+    /// of the model.
+    pub fn read_from_buffered<T: BufRead>(reader: &mut T) -> Result<Predictor<F>> {
+        return Predictor::read_from_buffered_with_obj_function(reader, None);
+    }
+
+    /// Instantiates with the Xgboost model read from a buffered input, transforming
+    /// outputs with `obj_func` instead of the objective function the model was saved with
+    pub fn read_from_buffered_with_obj_function<T: BufRead>(
+        reader: &mut T,
+        obj_func: Option<Arc<dyn ObjFunction>>,
+    ) -> Result<Predictor<F>> {
+        let (mparam, spark_model_param, version) = Predictor::<F>::read_model_params(reader)?;
 
         let name_obj = reader.read_u8_vec_len()?;
         let name_gbm = reader.read_u8_vec_len()?;
 
-        let obj_func_type = get_classify_func_type(name_obj)?;
-        let obj_func = get_classify_function(obj_func_type);
+        let objective = String::from_utf8(name_obj.clone())?;
+        let obj_func = match obj_func {
+            Some(obj_func) => obj_func,
+            None => get_obj_function(name_obj)?,
+        };
         let gbm = crate::gbm::grad_booster::load_grad_booster(
             reader,
             name_gbm,
             mparam.saved_with_pbuffer != 0,
+            mparam.major_version,
         )?;
+        // before 0.6 the flag tells that a prediction buffer follows the trees, and nothing
+        // follows it; 0.6 and later no longer save prediction buffers
+        let saved_pred_buffer = gbm
+            .as_gbtree()
+            .is_some_and(|gbtree| gbtree.saved_pred_buffer());
+        let attributes = if mparam.saved_with_pbuffer != 0 && !saved_pred_buffer {
+            Predictor::<F>::read_attributes(reader)?
+        } else {
+            HashMap::new()
+        };
+        if mparam.contain_eval_metrics && !saved_pred_buffer {
+            Predictor::<F>::skip_eval_metrics(reader)?;
+        }
 
         return Ok(Predictor {
             mparam,
+            spark_model_param,
+            version,
+            objective,
             obj_func,
             gbm,
+            quantile_alphas: None,
+            attributes,
+            use_best_iteration: false,
+            feature_names: Vec::new(),
+            feature_types: Vec::new(),
+            feature_index: HashMap::new(),
+            strict_feature_count: false,
         });
     }
 
-    fn predict_raw(&self, feat: &F, ntree_limit: usize) -> Vec<f32> {
-        let mut preds = self.gbm.predict(feat, ntree_limit);
-        for i in 0..preds.len() {
-            preds[i] += self.mparam.base_score as f32;
+    /// Instantiates with the Xgboost model held by `bytes`, in any format read by this
+    /// crate. Gzip and zstd models are decompressed with the `gzip` and `zstd` features.
+    /// Binary models which happen to start like JSON or UBJSON are read as binary when
+    /// they fail to parse as such.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Predictor<F>> {
+        let bytes = model_format::decompress(bytes)?;
+        let read = match ModelFormat::detect(&bytes) {
+            ModelFormat::Binary => return Predictor::read_from_buffered(&mut &bytes[..]),
+            ModelFormat::Json => Predictor::read_from_json(&mut &bytes[..]),
+            ModelFormat::Ubjson => Predictor::read_from_ubjson(&mut &bytes[..]),
+        };
+        return read.or_else(|e| Predictor::read_from_buffered(&mut &bytes[..]).map_err(|_| e));
+    }
+
+    /// Instantiates with the Xgboost model saved at `path`, see `from_bytes`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Predictor<F>> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).chain_err(|| format!("failed to read {}", path.display()))?;
+        return Predictor::from_bytes(&bytes);
+    }
+
+    /// Instantiates with the Xgboost model saved in the JSON format
+    pub fn read_from_json<T: Read>(reader: &mut T) -> Result<Predictor<F>> {
+        return Predictor::read_from_json_with_obj_function(reader, None);
+    }
+
+    /// Instantiates with the Xgboost model saved in the JSON format, transforming outputs
+    /// with `obj_func` instead of the objective function the model was saved with
+    pub fn read_from_json_with_obj_function<T: Read>(
+        reader: &mut T,
+        obj_func: Option<Arc<dyn ObjFunction>>,
+    ) -> Result<Predictor<F>> {
+        let model: Value = serde_json::from_reader(reader)?;
+        return Predictor::read_from_json_value(&model, obj_func);
+    }
+
+    /// Instantiates with the Xgboost model saved in the Universal Binary JSON format
+    pub fn read_from_ubjson<T: Read>(reader: &mut T) -> Result<Predictor<F>> {
+        return Predictor::read_from_ubjson_with_obj_function(reader, None);
+    }
+
+    /// Instantiates with the Xgboost model saved in the Universal Binary JSON format,
+    /// transforming outputs with `obj_func` instead of the objective function the model
+    /// was saved with
+    pub fn read_from_ubjson_with_obj_function<T: Read>(
+        reader: &mut T,
+        obj_func: Option<Arc<dyn ObjFunction>>,
+    ) -> Result<Predictor<F>> {
+        let model = crate::ubjson::from_reader(reader)?;
+        return Predictor::read_from_json_value(&model, obj_func);
+    }
+
+    fn read_from_json_value(
+        model: &Value,
+        obj_func: Option<Arc<dyn ObjFunction>>,
+    ) -> Result<Predictor<F>> {
+        let version = match json::i32_vec(json::field(model, "version")?)?.as_slice() {
+            [major, minor, patch] => {
+                ModelVersion::Json(*major as u32, *minor as u32, *patch as u32)
+            }
+            version => bail!("invalid model version {:?}", version),
+        };
+        let learner = json::field(model, "learner")?;
+        let objective = json::field(learner, "objective")?;
+        let name_obj = json::as_str(json::field(objective, "name")?)?;
+        let obj_func = match obj_func {
+            Some(obj_func) => obj_func,
+            None => get_obj_function(name_obj.as_bytes().to_vec())?,
+        };
+
+        let learner_model_param = json::field(learner, "learner_model_param")?;
+        let mparam = ModelParam::read_from_json(learner_model_param, obj_func.as_ref())?;
+        let num_target =
+            json::opt_field(learner_model_param, "num_target").map_or(Ok(1), json::as_usize)?;
+        let num_output_group = cmp::max(cmp::max(mparam.num_class as usize, num_target), 1);
+
+        let quantile_alphas = match json::opt_field(objective, "quantile_loss_param") {
+            None => None,
+            Some(param) => {
+                let alphas = json::f32_list(json::field(param, "quantile_alpha")?)?;
+                if alphas.len() != num_output_group {
+                    bail!(
+                        "model has {} quantiles but {} output groups",
+                        alphas.len(),
+                        num_output_group
+                    );
+                }
+                Some(alphas)
+            }
+        };
+
+        let gbm = crate::gbm::grad_booster::load_grad_booster_json(
+            json::field(learner, "gradient_booster")?,
+            mparam.num_feature,
+            num_output_group,
+        )?;
+
+        let attributes = match json::opt_field(learner, "attributes") {
+            None => HashMap::new(),
+            Some(attributes) => attributes
+                .as_object()
+                .ok_or("learner attributes must be an object")?
+                .iter()
+                .map(|(name, value)| {
+                    let value = match value {
+                        Value::String(value) => value.clone(),
+                        _ => value.to_string(),
+                    };
+                    (name.clone(), value)
+                })
+                .collect(),
+        };
+
+        let mut predictor = Predictor {
+            mparam,
+            spark_model_param: None,
+            version,
+            objective: name_obj.to_string(),
+            obj_func,
+            gbm,
+            quantile_alphas,
+            attributes,
+            use_best_iteration: false,
+            feature_names: Vec::new(),
+            feature_types: Vec::new(),
+            feature_index: HashMap::new(),
+            strict_feature_count: false,
+        };
+        if let Some(names) = json::opt_field(learner, "feature_names") {
+            let names = json::string_vec(names)?;
+            if !names.is_empty() {
+                predictor.set_feature_names(names)?;
+            }
+        }
+        if let Some(types) = json::opt_field(learner, "feature_types") {
+            let types = json::string_vec(types)?;
+            if !types.is_empty() {
+                predictor.set_feature_types(types)?;
+            }
+        }
+        return Ok(predictor);
+    }
+
+    /// Parameters of a model saved by xgboost4j-spark
+    pub fn spark_model_param(&self) -> Option<&SparkModelParam> {
+        return self.spark_model_param.as_ref();
+    }
+
+    /// Version of xgboost the model was saved with, as far as its format tells
+    pub fn version(&self) -> ModelVersion {
+        return self.version;
+    }
+
+    /// Fingerprint of what the model predicts: its booster, objective, base score, trees
+    /// and weights. It does not depend on the format the model was read from, nor on the
+    /// statistics, feature names or attributes saved with it.
+    /// The whole model is hashed, keep the fingerprint rather than computing it again.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = Fingerprint::new();
+        hasher.write_str(self.booster());
+        hasher.write_str(&self.objective);
+        hasher.write_f32(self.mparam.base_score);
+        hasher.write_usize(self.mparam.num_feature);
+        hasher.write_usize(self.num_output_group());
+        let iteration_indptr = self.iteration_indptr();
+        hasher.write_usize(iteration_indptr.len());
+        for end in iteration_indptr.iter() {
+            hasher.write_usize(*end);
+        }
+        for tree in self.trees().iter() {
+            hasher.write_usize(tree.group());
+            hasher.write_f32(tree.weight());
+            hasher.write_usize(tree.num_roots());
+            // nodes in preorder, whatever their ids
+            for node in tree.nodes().iter() {
+                if node.is_leaf() {
+                    hasher.write_u8(0);
+                    match node.leaf_vector() {
+                        Some(values) => values.iter().for_each(|v| hasher.write_f32(*v)),
+                        None => hasher.write_f32(node.leaf_value().unwrap_or(0f32)),
+                    }
+                    continue;
+                }
+                hasher.write_u8(1);
+                hasher.write_usize(node.split_index().unwrap());
+                hasher.write_u8(node.default_left() as u8);
+                match node.categories() {
+                    Some(categories) => {
+                        hasher.write_usize(categories.len());
+                        categories.iter().for_each(|c| hasher.write_usize(*c));
+                    }
+                    None => hasher.write_f32(node.split_condition().unwrap()),
+                }
+            }
+        }
+        if let Some(weights) = self.linear_weights() {
+            weights.iter().for_each(|w| hasher.write_f32(*w));
+        }
+        return hasher.finish();
+    }
+
+    /// Name of the booster: `gbtree`, `dart` or `gblinear`
+    pub fn booster(&self) -> &str {
+        return self.gbm.name();
+    }
+
+    /// Name of the objective the model was saved with, such as `binary:logistic`
+    pub fn objective(&self) -> &str {
+        return &self.objective;
+    }
+
+    /// Global bias added to all predictions, in margin space
+    pub fn base_score(&self) -> f32 {
+        return self.mparam.base_score;
+    }
+
+    /// Number of values predicted per instance
+    pub fn num_output_group(&self) -> usize {
+        return self.gbm.num_output_group();
+    }
+
+    /// Number of trees, 0 for linear models
+    pub fn num_trees(&self) -> usize {
+        return self.gbm.num_trees();
+    }
+
+    /// Trees of the model in training order, empty for linear models
+    pub fn trees(&self) -> Vec<Tree<'_>> {
+        return self.gbm.trees();
+    }
+
+    /// Returns the tree `index`
+    pub fn tree(&self, index: usize) -> Option<Tree<'_>> {
+        return self.gbm.tree(index);
+    }
+
+    /// Boosting round `i` holds the trees `iteration_indptr[i]..iteration_indptr[i + 1]`
+    pub(crate) fn iteration_indptr(&self) -> &[usize] {
+        return self.gbm.iteration_indptr();
+    }
+
+    /// Weights of a linear model, `None` for tree models.
+    /// Holds `num_output_group` weights per feature followed by the biases of each group.
+    pub fn linear_weights(&self) -> Option<&[f32]> {
+        return self.gbm.linear_weights();
+    }
+
+    /// Summarizes the model
+    pub fn summary(&self) -> ModelSummary {
+        let trees = self.trees();
+        let mut used_features: Vec<usize> = trees
+            .iter()
+            .flat_map(|tree| tree.nodes())
+            .filter_map(|node| node.split_index())
+            .collect();
+        used_features.sort_unstable();
+        used_features.dedup();
+        let mut num_trees_per_group = vec![0; self.num_output_group()];
+        let mut depth_histogram = vec![];
+        for tree in trees.iter() {
+            // vector-leaf trees predict all groups and belong to group 0
+            num_trees_per_group[tree.group()] += 1;
+            let depth = tree.max_depth();
+            if depth >= depth_histogram.len() {
+                depth_histogram.resize(depth + 1, 0);
+            }
+            depth_histogram[depth] += 1;
+        }
+        return ModelSummary {
+            booster: self.booster().to_string(),
+            objective: self.objective.clone(),
+            base_score: self.base_score(),
+            num_feature: self.num_feature(),
+            num_output_group: self.num_output_group(),
+            num_trees: trees.len(),
+            num_trees_per_group,
+            num_boosted_rounds: self.num_boosted_rounds(),
+            max_depth: depth_histogram.len().saturating_sub(1),
+            depth_histogram,
+            num_nodes: trees.iter().map(|tree| tree.nodes().len()).sum(),
+            num_leaves: trees.iter().map(|tree| tree.leaves().len()).sum(),
+            used_features,
+        };
+    }
+
+    /// Copies the model with another booster
+    fn with_gbm(&self, gbm: Box<dyn GradBooster<F>>) -> Predictor<F> {
+        return Predictor {
+            mparam: self.mparam.clone(),
+            spark_model_param: self.spark_model_param.clone(),
+            version: self.version,
+            objective: self.objective.clone(),
+            obj_func: self.obj_func.clone(),
+            gbm,
+            quantile_alphas: self.quantile_alphas.clone(),
+            attributes: self.attributes.clone(),
+            use_best_iteration: self.use_best_iteration,
+            feature_names: self.feature_names.clone(),
+            feature_types: self.feature_types.clone(),
+            feature_index: self.feature_index.clone(),
+            strict_feature_count: self.strict_feature_count,
+        };
+    }
+
+    /// Copies the model with another booster whose rounds differ, the best iteration
+    /// of this model does not apply to it
+    fn with_rounds_of(&self, gbm: Box<dyn GradBooster<F>>) -> Predictor<F> {
+        let mut predictor = self.with_gbm(gbm);
+        predictor.attributes.remove("best_iteration");
+        predictor.attributes.remove("best_ntree_limit");
+        return predictor;
+    }
+
+    /// Returns a model of the boosting rounds `begin, begin + step, ...` below `end`,
+    /// as xgboost's `Booster[begin:end:step]`. `end == 0` means all rounds.
+    /// The best iteration of this model is not kept.
+    pub fn slice(&self, begin: usize, end: usize, step: usize) -> Result<Predictor<F>> {
+        return Ok(self.with_rounds_of(self.gbm.slice(begin, end, step)?));
+    }
+
+    /// Returns a model of the first `num_rounds` boosting rounds, predicting as this model
+    /// does with `ntree_limit == num_rounds`
+    pub fn truncate(&self, num_rounds: usize) -> Result<Predictor<F>> {
+        if num_rounds == 0 {
+            bail!("a truncated model keeps at least one round");
+        }
+        return self.slice(0, num_rounds, 1);
+    }
+
+    /// Returns a model without the trees `tree_indices`, indices into `trees()`.
+    /// Boosting rounds left without trees are dropped.
+    pub fn drop_trees(&self, tree_indices: &[usize]) -> Result<Predictor<F>> {
+        return Ok(self.with_rounds_of(self.gbm.drop_trees(tree_indices)?));
+    }
+
+    /// Returns a model predicting the output groups `groups` only, in this order.
+    /// Objectives such as `multi:softprob` transform the margins of the kept groups.
+    pub fn keep_output_groups(&self, groups: &[usize]) -> Result<Predictor<F>> {
+        let num_output_group = self.num_output_group();
+        if groups.is_empty() {
+            bail!("at least one output group must be kept");
+        }
+        for (i, gid) in groups.iter().enumerate() {
+            if *gid >= num_output_group {
+                bail!(
+                    "output group {} is out of range for {} output groups",
+                    gid,
+                    num_output_group
+                );
+            }
+            if groups[..i].contains(gid) {
+                bail!("output group {} is kept twice", gid);
+            }
+        }
+        let mut predictor = self.with_gbm(self.gbm.keep_output_groups(groups)?);
+        if predictor.mparam.num_class > 0 {
+            predictor.mparam.num_class = groups.len() as i32;
+        }
+        if let Some(alphas) = &self.quantile_alphas {
+            predictor.quantile_alphas = Some(groups.iter().map(|gid| alphas[*gid]).collect());
+        }
+        return Ok(predictor);
+    }
+
+    /// Combines tree models into a single one, whose margin is the sum of the margins of
+    /// `models` weighted by `weights`, global biases included. Models must have the same
+    /// number of features, objective and output groups. The combined model has the
+    /// feature names of the first model and no attributes.
+    pub fn merge(models: &[&Predictor<F>], weights: &[f32]) -> Result<Predictor<F>> {
+        let first = match models.first() {
+            Some(first) => *first,
+            None => bail!("no models to merge"),
+        };
+        if weights.len() != models.len() {
+            bail!(
+                "{} models to merge but {} weights",
+                models.len(),
+                weights.len()
+            );
+        }
+        let mut gbtrees = Vec::with_capacity(models.len());
+        for (model, weight) in models.iter().zip(weights.iter()) {
+            if model.num_feature() != first.num_feature() {
+                bail!(
+                    "can't merge models with {} and {} features",
+                    first.num_feature(),
+                    model.num_feature()
+                );
+            }
+            if model.objective != first.objective || model.quantile_alphas != first.quantile_alphas
+            {
+                bail!(
+                    "can't merge models of objectives '{}' and '{}'",
+                    first.objective,
+                    model.objective
+                );
+            }
+            match model.gbm.as_gbtree() {
+                Some(gbtree) => gbtrees.push((gbtree, *weight)),
+                None => bail!("only tree models can be merged"),
+            }
+        }
+
+        let mut merged = first.with_gbm(Box::new(GBTree::merge(&gbtrees)?));
+        merged.attributes.clear();
+        merged.mparam.base_score = models
+            .iter()
+            .zip(weights.iter())
+            .map(|(model, weight)| model.mparam.base_score * weight)
+            .sum();
+        return Ok(merged);
+    }
+
+    /// Averages tree models, see `merge`
+    pub fn average(models: &[&Predictor<F>]) -> Result<Predictor<F>> {
+        let weights = vec![1f32 / models.len() as f32; models.len()];
+        return Predictor::merge(models, &weights);
+    }
+
+    /// Replaces the global bias added to all predictions, in margin space
+    pub fn set_base_score(&mut self, base_score: f32) {
+        self.mparam.base_score = base_score;
+    }
+
+    /// Writes the model in the binary format of xgboost before 1.0, read by `read_from`.
+    /// Models of random forests (`num_parallel_tree > 1`) and binary models of xgboost 1.x
+    /// are written in the binary format of 1.x instead.
+    /// Attributes are kept, feature names and types are not part of this format.
+    pub fn write_to<T: ModelWriter>(&self, writer: &mut T) -> Result<()> {
+        // models loaded with a prediction buffer are saved without it, attributes
+        // follow the booster whenever the flag is set
+        let contain_extra_attrs =
+            !self.attributes.is_empty() || self.mparam.saved_with_pbuffer != 0;
+        // before 1.0 the slot of `num_parallel_tree` holds the number of roots
+        let num_parallel_tree = self
+            .gbm
+            .as_gbtree()
+            .map_or(1, |gbtree| gbtree.num_parallel_tree());
+        let version = if self.mparam.major_version == 0 && num_parallel_tree > 1 {
+            (1, 0)
+        } else {
+            (self.mparam.major_version, self.mparam.minor_version)
+        };
+        self.mparam.write_to(writer, contain_extra_attrs, version)?;
+        writer.write_u8_vec_len(self.objective.as_bytes())?;
+        writer.write_u8_vec_len(self.gbm.name().as_bytes())?;
+        self.gbm.write_to(writer)?;
+        if contain_extra_attrs {
+            let mut attributes: Vec<(&String, &String)> = self.attributes.iter().collect();
+            attributes.sort();
+            writer.write_i64_le(attributes.len() as i64)?;
+            for (name, value) in attributes {
+                writer.write_u8_vec_len(name.as_bytes())?;
+                writer.write_u8_vec_len(value.as_bytes())?;
+            }
+        }
+        return Ok(());
+    }
+
+    /// Writes the model in the compact format evaluated by `compact::CompactModel`.
+    /// Only tree models with scalar leaves, numerical splits and a single root are supported.
+    pub fn write_compact<T: ModelWriter>(&self, writer: &mut T) -> Result<()> {
+        return compact::write_compact(self, writer);
+    }
+
+    /// Writes the model in the JSON format of xgboost 2.0, read by `read_from_json`
+    pub fn write_json<T: Write>(&self, writer: &mut T) -> Result<()> {
+        serde_json::to_writer(writer, &self.to_json()?)?;
+        return Ok(());
+    }
+
+    fn to_json(&self) -> Result<Value> {
+        let num_output_group = self.num_output_group();
+        let num_target = if self.mparam.num_class > 0 {
+            1
+        } else {
+            num_output_group
+        };
+        let base_score = self.obj_func.margin_to_prob(self.mparam.base_score);
+        return Ok(json!({
+            "learner": {
+                "attributes": self.attributes,
+                "feature_names": self.feature_names,
+                "feature_types": self.feature_types,
+                "gradient_booster": self.gbm.to_json()?,
+                "learner_model_param": {
+                    "base_score": json::param(format!("{:E}", base_score)),
+                    "boost_from_average": "1",
+                    "num_class": json::param(self.mparam.num_class),
+                    "num_feature": json::param(self.mparam.num_feature),
+                    "num_target": json::param(num_target),
+                },
+                "objective": self.objective_to_json(),
+            },
+            "version": [2, 0, 0],
+        }));
+    }
+
+    /// Objective with the parameters xgboost expects, their training-only values are not
+    /// kept and written with their defaults
+    fn objective_to_json(&self) -> Value {
+        let mut objective = json!({ "name": self.objective });
+        let name = self.objective.as_str();
+        if let Some(alphas) = &self.quantile_alphas {
+            let alphas: Vec<String> = alphas.iter().map(|alpha| alpha.to_string()).collect();
+            objective["quantile_loss_param"] =
+                json!({ "quantile_alpha": format!("[{}]", alphas.join(", ")) });
+        } else if name.starts_with("multi:") {
+            objective["softmax_multiclass_param"] =
+                json!({ "num_class": json::param(self.mparam.num_class) });
+        } else if name.starts_with("reg:") || name.starts_with("binary:") {
+            objective["reg_loss_param"] = json!({ "scale_pos_weight": "1" });
+        }
+        return objective;
+    }
+
+    /// Number of features the model was trained with
+    pub fn num_feature(&self) -> usize {
+        return self.mparam.num_feature;
+    }
+
+    /// Returns the feature names, empty if the model has none
+    pub fn feature_names(&self) -> &[String] {
+        return &self.feature_names;
+    }
+
+    /// Returns the feature types, empty if the model has none
+    pub fn feature_types(&self) -> &[String] {
+        return &self.feature_types;
+    }
+
+    /// Returns the index of each feature name, to build `FVecNamed` inputs
+    pub fn feature_index(&self) -> &HashMap<String, usize> {
+        return &self.feature_index;
+    }
+
+    /// Sets the feature names, e.g. for binary models which don't save them.
+    /// There must be one unique name per feature, or none.
+    pub fn set_feature_names(&mut self, names: Vec<String>) -> Result<()> {
+        if !names.is_empty() && names.len() != self.mparam.num_feature {
+            bail!(
+                "got {} feature names for {} features",
+                names.len(),
+                self.mparam.num_feature
+            );
+        }
+        let mut feature_index = HashMap::with_capacity(names.len());
+        for (index, name) in names.iter().enumerate() {
+            if feature_index.insert(name.clone(), index).is_some() {
+                bail!("duplicate feature name '{}'", name);
+            }
+        }
+        self.feature_names = names;
+        self.feature_index = feature_index;
+        return Ok(());
+    }
+
+    /// Sets the feature types, one of `q` (quantitative), `i` (indicator), `int`, `float`
+    /// or `c` (categorical) per feature, or none
+    pub fn set_feature_types(&mut self, types: Vec<String>) -> Result<()> {
+        if !types.is_empty() && types.len() != self.mparam.num_feature {
+            bail!(
+                "got {} feature types for {} features",
+                types.len(),
+                self.mparam.num_feature
+            );
+        }
+        if let Some(tp) = types
+            .iter()
+            .find(|tp| !["q", "i", "int", "float", "c"].contains(&tp.as_str()))
+        {
+            bail!("unknown feature type '{}'", tp);
+        }
+        self.feature_types = types;
+        return Ok(());
+    }
+
+    /// Computes the importance of each feature as xgboost's `get_score`. Tree models count
+    /// the splits reachable from the roots, features without splits are left out.
+    /// Linear models support `ImportanceType::Weight` only, the coefficient of each feature,
+    /// and must have a single output group.
+    pub fn feature_importance(
+        &self,
+        importance_type: ImportanceType,
+    ) -> Result<HashMap<usize, f32>> {
+        return self.gbm.feature_importance(importance_type);
+    }
+
+    /// Computes the importance of each feature keyed by feature name, `f{index}` for models
+    /// without feature names
+    pub fn feature_importance_by_name(
+        &self,
+        importance_type: ImportanceType,
+    ) -> Result<HashMap<String, f32>> {
+        let fmap = FeatureMap::new(&self.feature_names, &[], self.mparam.num_feature);
+        return Ok(self
+            .feature_importance(importance_type)?
+            .into_iter()
+            .map(|(fid, score)| (fmap.name(fid), score))
+            .collect());
+    }
+
+    /// Dumps the model as text in the format of xgboost's `Booster.get_dump()`, one string
+    /// per tree, or a single string with the weights of a linear model.
+    /// `with_stats` adds the gain of splits and the cover of nodes, `with_feature_names`
+    /// prints splits with the feature names and types of the model instead of `f{index}`.
+    pub fn dump_model(&self, with_stats: bool, with_feature_names: bool) -> Vec<String> {
+        return self
+            .dump_model_with_format(DumpFormat::Text, with_stats, with_feature_names)
+            .expect("all models can be dumped as text");
+    }
+
+    /// Dumps the model in `format`, one string per tree. Linear models are dumped as a single
+    /// string and can't be dumped as DOT graphs.
+    pub fn dump_model_with_format(
+        &self,
+        format: DumpFormat,
+        with_stats: bool,
+        with_feature_names: bool,
+    ) -> Result<Vec<String>> {
+        return self
+            .gbm
+            .dump(&self.feature_map(with_feature_names), with_stats, format);
+    }
+
+    /// Feature names and types of dumps, `f{index}` if `with_feature_names` is false
+    fn feature_map(&self, with_feature_names: bool) -> FeatureMap {
+        return if with_feature_names {
+            FeatureMap::new(
+                &self.feature_names,
+                &self.feature_types,
+                self.mparam.num_feature,
+            )
+        } else {
+            FeatureMap::default()
+        };
+    }
+
+    /// Renders the tree `tree_index` as a Graphviz DOT graph, as xgboost's `to_graphviz`
+    pub fn to_graphviz(
+        &self,
+        tree_index: usize,
+        with_stats: bool,
+        with_feature_names: bool,
+    ) -> Result<String> {
+        let tree = match self.tree(tree_index) {
+            Some(tree) => tree,
+            None => bail!(
+                "tree index {} is out of range for {} trees",
+                tree_index,
+                self.num_trees()
+            ),
+        };
+        let fmap = self.feature_map(with_feature_names);
+        return Ok(tree.dump(&fmap, with_stats, DumpFormat::Dot));
+    }
+
+    /// Builds a feature vector from `(name, value)` pairs using the model's feature names
+    pub fn named_fvec<S, T, I>(&self, values: I) -> Result<FVecNamed>
+    where
+        S: AsRef<str>,
+        T: ToFloat,
+        I: IntoIterator<Item = (S, T)>,
+    {
+        return FVecNamed::new(&self.feature_index, values);
+    }
+
+    /// Returns the learner attributes saved with the model, e.g. `best_iteration`
+    pub fn attributes(&self) -> &HashMap<String, String> {
+        return &self.attributes;
+    }
+
+    /// Returns the learner attribute `name`
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        return self.attributes.get(name).map(String::as_str);
+    }
+
+    /// Returns the best boosting round (0-based) recorded by early stopping
+    pub fn best_iteration(&self) -> Option<usize> {
+        return self.attribute("best_iteration")?.trim().parse().ok();
+    }
+
+    /// Returns the number of trees per output group recorded by early stopping,
+    /// `(best_iteration + 1) * num_parallel_tree` as xgboost computes it
+    pub fn best_ntree_limit(&self) -> Option<usize> {
+        return self.attribute("best_ntree_limit")?.trim().parse().ok();
+    }
+
+    /// When enabled, predictions asking for all boosting rounds (`ntree_limit == 0`, or
+    /// iteration range ending at 0) use the rounds up to `best_iteration` only, as xgboost
+    /// does. Models without `best_iteration` fall back to `best_ntree_limit`, then all rounds.
+    pub fn set_use_best_iteration(&mut self, enabled: bool) {
+        self.use_best_iteration = enabled;
+    }
+
+    /// Number of boosting rounds used when predicting with all rounds
+    fn default_iteration_end(&self) -> usize {
+        if !self.use_best_iteration {
+            return 0;
+        }
+        return match (self.best_iteration(), self.best_ntree_limit()) {
+            (Some(best_iteration), _) => best_iteration + 1,
+            (None, Some(best_ntree_limit)) => {
+                // a round of random forests holds `num_parallel_tree` trees per group
+                let num_parallel_tree = self
+                    .gbm
+                    .as_gbtree()
+                    .map_or(1, |gbtree| gbtree.num_parallel_tree());
+                best_ntree_limit / num_parallel_tree
+            }
+            (None, None) => 0,
+        };
+    }
+
+    fn iteration_range(&self, begin: usize, end: usize) -> (usize, usize) {
+        return if end == 0 {
+            (begin, self.default_iteration_end())
+        } else {
+            (begin, end)
+        };
+    }
+
+    /// Returns the quantiles of a `reg:quantileerror` model.
+    /// `predict` outputs one value per quantile in the same order.
+    pub fn quantile_alphas(&self) -> Option<&[f32]> {
+        return self.quantile_alphas.as_deref();
+    }
+
+    fn predict_raw(
+        &self,
+        feat: &F,
+        root_index: usize,
+        iteration_range: (usize, usize),
+    ) -> Vec<f32> {
+        let iteration_range = self.iteration_range(iteration_range.0, iteration_range.1);
+        let mut preds = self.gbm.predict(feat, root_index, iteration_range);
+        for pred in preds.iter_mut() {
+            *pred += self.mparam.base_score;
         }
         preds
     }
 
-    fn predict_single_raw(&self, feat: &F, ntree_limit: usize) -> f32 {
-        self.gbm.predict_single(feat, ntree_limit) + self.mparam.base_score as f32
+    fn predict_single_raw(
+        &self,
+        feat: &F,
+        root_index: usize,
+        iteration_range: (usize, usize),
+    ) -> f32 {
+        let iteration_range = self.iteration_range(iteration_range.0, iteration_range.1);
+        self.gbm.predict_single(feat, root_index, iteration_range) + self.mparam.base_score
+    }
+
+    /// Number of roots of each tree, models trained with `root_index` have more than one
+    pub fn num_roots(&self) -> usize {
+        return self.gbm.num_roots();
+    }
+
+    fn check_root_index(&self, root_index: usize) -> Result<()> {
+        let num_roots = self.num_roots();
+        if root_index >= num_roots {
+            return Err(Error::from_kind(ErrorKind::InvalidRootIndex(
+                root_index, num_roots,
+            )));
+        }
+        return Ok(());
+    }
+
+    /// Number of boosting rounds of a tree model.
+    /// A round holds one tree per output group (times `num_parallel_tree`),
+    /// or a single tree with vector leaves. Linear models have no rounds.
+    pub fn num_boosted_rounds(&self) -> usize {
+        return self.gbm.num_boosted_rounds();
+    }
+
+    fn check_iteration_range(&self, begin: usize, end: usize) -> Result<()> {
+        if end != 0 && begin > end {
+            return Err(Error::from_kind(ErrorKind::InvalidIterationRange(
+                begin, end,
+            )));
+        }
+        return Ok(());
+    }
+
+    /// When enabled, inputs are checked against the number of features of the model:
+    /// dense vectors must have exactly `num_feature` values, sparse vectors no index
+    /// past it. Methods returning `Result`, such as `try_predict` and `predict_with`, fail
+    /// with `FeatureCountMismatch` otherwise, the others panic.
+    pub fn set_strict_feature_count(&mut self, enabled: bool) {
+        self.strict_feature_count = enabled;
+    }
+
+    /// Checks `feat` against the number of features of the model, see
+    /// `set_strict_feature_count`
+    pub fn check_feature_count(&self, feat: &F) -> Result<()> {
+        let num_feature = self.mparam.num_feature;
+        let size = match (feat.dense_len(), feat.max_index()) {
+            (Some(len), _) if len != num_feature => len,
+            (None, Some(max_index)) if max_index >= num_feature => max_index + 1,
+            _ => return Ok(()),
+        };
+        return Err(Error::from_kind(ErrorKind::FeatureCountMismatch(
+            size,
+            num_feature,
+        )));
+    }
+
+    fn check_strict(&self, feat: &F) -> Result<()> {
+        if self.strict_feature_count {
+            return self.check_feature_count(feat);
+        }
+        return Ok(());
+    }
+
+    fn expect<T>(result: Result<T>) -> T {
+        return match result {
+            Ok(value) => value,
+            Err(e) => panic!("{}", e),
+        };
     }
 
-    /// Generates predictions for given feature vector
+    /// Generates predictions for given feature vector.
+    /// `ntree_limit` limits prediction to the first boosting rounds, 0 means all rounds.
+    /// Panics when the strict feature count check fails, see `try_predict`.
     pub fn predict(&self, feat: &F, output_margin: bool, ntree_limit: usize) -> Vec<f32> {
-        let preds = self.predict_raw(feat, ntree_limit);
+        return Predictor::<F>::expect(self.try_predict(feat, output_margin, ntree_limit));
+    }
+
+    /// Generates predictions for given feature vector as `predict` does, failing when
+    /// the strict feature count check fails
+    pub fn try_predict(
+        &self,
+        feat: &F,
+        output_margin: bool,
+        ntree_limit: usize,
+    ) -> Result<Vec<f32>> {
+        self.check_strict(feat)?;
+        let preds = self.predict_raw(feat, 0, (0, ntree_limit));
+
+        return Ok(if !output_margin {
+            self.obj_func.vector(&preds)
+        } else {
+            preds
+        });
+    }
+
+    /// Generates predictions for given feature vector with `options`, see `PredictOptions`
+    pub fn predict_with(&self, feat: &F, options: &PredictOptions) -> Result<Vec<f32>> {
+        let base_margin = match &options.base_margin {
+            Some(base_margin) => base_margin.as_slice(),
+            None => slice::from_ref(&self.mparam.base_score),
+        };
+        return self.predict_from(feat, options, base_margin);
+    }
+
+    /// Predicts with `options`, starting from `base_margin` instead of `options.base_margin`.
+    /// `base_margin` holds a single value shared by all output groups, or one per group.
+    fn predict_from(
+        &self,
+        feat: &F,
+        options: &PredictOptions,
+        base_margin: &[f32],
+    ) -> Result<Vec<f32>> {
+        let (begin, end) = options.iteration_range;
+        self.check_root_index(options.root_index)?;
+        self.check_iteration_range(begin, end)?;
+        self.check_strict(feat)?;
+        let iteration_range = self.iteration_range(begin, end);
+        let mut preds = self.gbm.predict(feat, options.root_index, iteration_range);
+        if base_margin.len() != 1 && base_margin.len() != preds.len() {
+            return Err(Error::from_kind(ErrorKind::InvalidBaseMargin(
+                base_margin.len(),
+                preds.len(),
+            )));
+        }
+        for (i, pred) in preds.iter_mut().enumerate() {
+            *pred += base_margin[i % base_margin.len()];
+        }
 
-        return if !output_margin {
-            (self.obj_func.vector)(&preds)
+        return Ok(if !options.output_margin {
+            self.obj_func.vector(&preds)
         } else {
             preds
+        });
+    }
+
+    /// Generates predictions for each feature vector of `feats`.
+    /// Panics when the strict feature count check fails, see `try_predict_batch`.
+    pub fn predict_batch(
+        &self,
+        feats: &[F],
+        output_margin: bool,
+        ntree_limit: usize,
+    ) -> Vec<Vec<f32>> {
+        return Predictor::<F>::expect(self.try_predict_batch(feats, output_margin, ntree_limit));
+    }
+
+    /// Generates predictions for each feature vector of `feats` as `predict_batch` does,
+    /// failing when the strict feature count check fails
+    pub fn try_predict_batch(
+        &self,
+        feats: &[F],
+        output_margin: bool,
+        ntree_limit: usize,
+    ) -> Result<Vec<Vec<f32>>> {
+        return feats
+            .iter()
+            .map(|feat| self.try_predict(feat, output_margin, ntree_limit))
+            .collect();
+    }
+
+    /// Generates predictions for each feature vector of `feats` with `options`,
+    /// `options.base_margin` holding the margins of all rows
+    pub fn predict_batch_with(
+        &self,
+        feats: &[F],
+        options: &PredictOptions,
+    ) -> Result<Vec<Vec<f32>>> {
+        let base_margins = match &options.base_margin {
+            Some(base_margins) => base_margins,
+            None => {
+                return feats
+                    .iter()
+                    .map(|feat| self.predict_with(feat, options))
+                    .collect();
+            }
         };
+        if feats.is_empty() {
+            return Ok(Vec::new());
+        }
+        if base_margins.is_empty() || base_margins.len() % feats.len() != 0 {
+            bail!(
+                "base margin of size {} does not match {} rows",
+                base_margins.len(),
+                feats.len()
+            );
+        }
+        let row_size = base_margins.len() / feats.len();
+        return feats
+            .iter()
+            .zip(base_margins.chunks(row_size))
+            .map(|(feat, base_margin)| self.predict_from(feat, options, base_margin))
+            .collect();
     }
 
-    /// Generates a prediction for given feature vector
+    /// Generates a prediction for given feature vector.
+    /// `ntree_limit` limits prediction to the first boosting rounds, 0 means all rounds.
+    /// Panics on models with several outputs and when the strict feature count check
+    /// fails, see `try_predict_single`.
     pub fn predict_single(&self, feat: &F, output_margin: bool, ntree_limit: usize) -> f32 {
-        let pred = self.predict_single_raw(feat, ntree_limit);
-        return if !output_margin {
-            (self.obj_func.scalar)(pred)
+        return Predictor::<F>::expect(self.try_predict_single(feat, output_margin, ntree_limit));
+    }
+
+    /// Generates a prediction for given feature vector as `predict_single` does, failing
+    /// on models with several outputs and when the strict feature count check fails
+    pub fn try_predict_single(
+        &self,
+        feat: &F,
+        output_margin: bool,
+        ntree_limit: usize,
+    ) -> Result<f32> {
+        if self.num_output_group() != 1 {
+            bail!(
+                "predict_single needs a single output, the model has {}",
+                self.num_output_group()
+            );
+        }
+        self.check_strict(feat)?;
+        let pred = self.predict_single_raw(feat, 0, (0, ntree_limit));
+        return Ok(if !output_margin {
+            self.obj_func.scalar(pred)
         } else {
             pred
+        });
+    }
+
+    /// Generates predictions after each boosting round, the `i`-th element equals
+    /// `predict(feat, output_margin, i + 1)`. Trees are walked once, so this is much cheaper
+    /// than predicting with every `ntree_limit`.
+    /// With `set_use_best_iteration`, rounds after `best_iteration` are left out.
+    /// Linear models have no boosting rounds and fail.
+    pub fn predict_staged(&self, feat: &F, output_margin: bool) -> Result<Vec<Vec<f32>>> {
+        self.check_strict(feat)?;
+        let num_rounds = match self.default_iteration_end() {
+            0 => usize::MAX,
+            end => end,
         };
+        return Ok(self
+            .gbm
+            .predict_staged(feat, 0)?
+            .into_iter()
+            .take(num_rounds)
+            .map(|mut preds| {
+                for pred in preds.iter_mut() {
+                    *pred += self.mparam.base_score;
+                }
+                if !output_margin {
+                    self.obj_func.vector(&preds)
+                } else {
+                    preds
+                }
+            })
+            .collect());
+    }
+
+    /// Computes the SHAP value of each feature of `feat`, as xgboost's `pred_contribs`.
+    /// Returns `num_feature + 1` values per output group, the last one being the bias, and
+    /// the values of a group sum up to its margin.
+    /// `ntree_limit` limits prediction to the first boosting rounds, 0 means all rounds.
+    /// Multi-target trees are not supported.
+    pub fn predict_contributions(&self, feat: &F, ntree_limit: usize) -> Result<Vec<f32>> {
+        self.check_strict(feat)?;
+        let mut contribs =
+            self.gbm
+                .predict_contributions(feat, 0, self.iteration_range(0, ntree_limit))?;
+        let num_columns = contribs.len() / self.num_output_group();
+        for bias in contribs
+            .iter_mut()
+            .skip(num_columns - 1)
+            .step_by(num_columns)
+        {
+            *bias += self.mparam.base_score;
+        }
+        return Ok(contribs);
     }
 
     /// Predicts leaf index of each tree.
+    /// `ntree_limit` limits prediction to the first boosting rounds, 0 means all rounds.
+    /// Trees with vector leaves (`multi_output_tree`) output one leaf for all groups.
+    /// Panics on gblinear models, which have no trees, and when the strict feature count
+    /// check fails, see `try_predict_leaf`.
     pub fn predict_leaf(&self, feat: &F, ntree_limit: usize) -> Vec<usize> {
-        self.gbm.predict_leaf(feat, ntree_limit)
+        return Predictor::<F>::expect(self.try_predict_leaf(feat, ntree_limit));
+    }
+
+    /// Predicts leaf index of each tree as `predict_leaf` does, failing on gblinear models
+    /// and when the strict feature count check fails
+    pub fn try_predict_leaf(&self, feat: &F, ntree_limit: usize) -> Result<Vec<usize>> {
+        self.check_strict(feat)?;
+        return self
+            .gbm
+            .predict_leaf(feat, 0, self.iteration_range(0, ntree_limit));
+    }
+
+    /// Predicts leaf index of each tree with `options.iteration_range` and
+    /// `options.root_index`, the other options don't apply to leaves
+    pub fn predict_leaf_with(&self, feat: &F, options: &PredictOptions) -> Result<Vec<usize>> {
+        let (begin, end) = options.iteration_range;
+        self.check_root_index(options.root_index)?;
+        self.check_iteration_range(begin, end)?;
+        self.check_strict(feat)?;
+        return self
+            .gbm
+            .predict_leaf(feat, options.root_index, self.iteration_range(begin, end));
     }
 }
@@ -0,0 +1,134 @@
+use crate::errors::*;
+use crate::model_reader::ModelReader;
+use byteorder::{ReadBytesExt, BE};
+use serde_json::{Map, Number, Value};
+use std::io::Read;
+
+/// Values reserved upfront for an array, larger arrays grow as they are read so that a
+/// corrupted count fails at the end of the input rather than allocating it
+const MAX_RESERVED_VALUES: usize = 1 << 16;
+
+/// Decodes a Universal Binary JSON document, the format xgboost uses for `.ubj` models.
+/// Numbers are big-endian, containers may be optimized with `$` (type) and `#` (count) markers.
+pub fn from_reader<R: Read>(reader: &mut R) -> Result<Value> {
+    let marker = reader.read_u8()?;
+    return read_value(reader, marker);
+}
+
+fn number(x: f64) -> Value {
+    return Number::from_f64(x).map_or(Value::Null, Value::Number);
+}
+
+fn read_length<R: Read>(reader: &mut R, marker: u8) -> Result<usize> {
+    let len = match read_value(reader, marker)? {
+        Value::Number(n) => n.as_i64(),
+        _ => None,
+    };
+    return match len {
+        Some(len) if len >= 0 => Ok(len as usize),
+        _ => bail!("invalid UBJSON length with marker '{}'", marker as char),
+    };
+}
+
+fn read_string<R: Read>(reader: &mut R, marker: u8) -> Result<String> {
+    let len = read_length(reader, marker)?;
+    return Ok(String::from_utf8(reader.read_u8_vec(len)?)?);
+}
+
+/// Reads the optional `$` type and `#` count of a container
+fn read_container_header<R: Read>(reader: &mut R) -> Result<(Option<u8>, Option<usize>, u8)> {
+    let mut marker = reader.read_u8()?;
+    let mut tp = None;
+    let mut count = None;
+    if marker == b'$' {
+        tp = Some(reader.read_u8()?);
+        marker = reader.read_u8()?;
+        if marker != b'#' {
+            bail!("UBJSON container type must be followed by a count");
+        }
+    }
+    if marker == b'#' {
+        let len_marker = reader.read_u8()?;
+        count = Some(read_length(reader, len_marker)?);
+        marker = 0;
+    }
+    return Ok((tp, count, marker));
+}
+
+fn read_array<R: Read>(reader: &mut R) -> Result<Value> {
+    let (tp, count, mut marker) = read_container_header(reader)?;
+    let mut values = Vec::new();
+    match count {
+        Some(count) => {
+            values.reserve(count.min(MAX_RESERVED_VALUES));
+            for _ in 0..count {
+                let marker = match tp {
+                    Some(tp) => tp,
+                    None => reader.read_u8()?,
+                };
+                values.push(read_value(reader, marker)?);
+            }
+        }
+        None => {
+            while marker != b']' {
+                values.push(read_value(reader, marker)?);
+                marker = reader.read_u8()?;
+            }
+        }
+    }
+    return Ok(Value::Array(values));
+}
+
+fn read_object<R: Read>(reader: &mut R) -> Result<Value> {
+    let (tp, count, mut marker) = read_container_header(reader)?;
+    let mut map = Map::new();
+    match count {
+        Some(count) => {
+            for _ in 0..count {
+                let len_marker = reader.read_u8()?;
+                let key = read_string(reader, len_marker)?;
+                let marker = match tp {
+                    Some(tp) => tp,
+                    None => reader.read_u8()?,
+                };
+                map.insert(key, read_value(reader, marker)?);
+            }
+        }
+        None => {
+            while marker != b'}' {
+                let key = read_string(reader, marker)?;
+                let value_marker = reader.read_u8()?;
+                map.insert(key, read_value(reader, value_marker)?);
+                marker = reader.read_u8()?;
+            }
+        }
+    }
+    return Ok(Value::Object(map));
+}
+
+fn read_value<R: Read>(reader: &mut R, marker: u8) -> Result<Value> {
+    return Ok(match marker {
+        b'Z' => Value::Null,
+        b'N' => {
+            let next = reader.read_u8()?;
+            return read_value(reader, next);
+        }
+        b'T' => Value::Bool(true),
+        b'F' => Value::Bool(false),
+        b'i' => Value::from(reader.read_i8()?),
+        b'U' => Value::from(reader.read_u8()?),
+        b'I' => Value::from(reader.read_i16::<BE>()?),
+        b'l' => Value::from(reader.read_i32::<BE>()?),
+        b'L' => Value::from(reader.read_i64::<BE>()?),
+        b'd' => number(reader.read_f32::<BE>()? as f64),
+        b'D' => number(reader.read_f64::<BE>()?),
+        b'C' => Value::from((reader.read_u8()? as char).to_string()),
+        b'S' | b'H' => {
+            let len_marker = reader.read_u8()?;
+            Value::String(read_string(reader, len_marker)?)
+        }
+        b'[' => read_array(reader)?,
+        b'{' => read_object(reader)?,
+        _ => bail!("unexpected UBJSON marker '{}'", marker as char),
+    });
+}
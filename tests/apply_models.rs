@@ -1,11 +1,20 @@
 mod common;
 
 use assert_approx_eq::assert_approx_eq;
-use std::collections::LinkedList;
+use std::collections::{HashMap, LinkedList};
 use std::fs::File;
-use xgboost_predictor::predictor::Predictor;
+use std::io::{BufReader, Read};
+use std::sync::Arc;
+use std::time::Instant;
+use xgboost_predictor::compact::CompactModel;
+use xgboost_predictor::errors::ErrorKind;
+use xgboost_predictor::functions::{register_obj_function, unregister_obj_function, ObjFunction};
+use xgboost_predictor::fvec::{FVecArray, FVecNamed};
+use xgboost_predictor::handle::{Canary, PredictorHandle};
+use xgboost_predictor::model_format::{Compression, ModelFormat, ModelVersion};
+use xgboost_predictor::predictor::{DumpFormat, ImportanceType, PredictOptions, Predictor, Tree};
 
-use crate::common::loaders::{load_data, load_expectation, open_resource_file};
+use crate::common::loaders::{get_resource, load_data, load_expectation, open_resource_file};
 use crate::common::tasks;
 use crate::common::types::*;
 
@@ -13,7 +22,7 @@ fn verify(
     predictor: &TestPredictor,
     data: &LinkedList<DataItem>,
     expectation: LinkedList<Vec<f32>>,
-    perform_predict_func: &Box<dyn Fn(&TestPredictor, &TestMap) -> Vec<f32>>,
+    perform_predict_func: &PredictFn,
 ) {
     assert_eq!(data.len(), expectation.len());
     for ((_, map), expected) in data.iter().zip(expectation.iter()) {
@@ -25,22 +34,43 @@ fn verify(
     }
 }
 
+fn load_model(model_type: &str, model_name: &str, extension: &str) -> TestPredictor {
+    let mut model_file = open_resource_file(&format!(
+        "model/{}/{}.{}",
+        model_type, model_name, extension
+    ))
+    .unwrap();
+    match extension {
+        "json" => Predictor::read_from_json(&mut model_file).unwrap(),
+        "ubj" => Predictor::read_from_ubjson(&mut model_file).unwrap(),
+        _ => Predictor::read_from::<File>(&mut model_file).unwrap(),
+    }
+}
+
 fn run(
     model_type: String,
     model_names: Vec<String>,
     data_file: String,
     tasks: Vec<tasks::PredictionTask>,
 ) {
-    let data = load_data(&*format!("data/{}", data_file));
+    run_format(model_type, model_names, data_file, tasks, "model")
+}
+
+fn run_format(
+    model_type: String,
+    model_names: Vec<String>,
+    data_file: String,
+    tasks: Vec<tasks::PredictionTask>,
+    extension: &str,
+) {
+    let data = load_data(&format!("data/{}", data_file));
     for task in tasks.iter() {
         for model_name in model_names.iter() {
-            let expectation = load_expectation(&*format!(
+            let expectation = load_expectation(&format!(
                 "expectation/{}/{}.{}",
                 model_type, model_name, task.expectation_suffix
             ));
-            let mut model_file =
-                open_resource_file(&*format!("model/{}/{}.model", model_type, model_name)).unwrap();
-            let predictor: TestPredictor = Predictor::read_from::<File>(&mut model_file).unwrap();
+            let predictor = load_model(&model_type, model_name, extension);
             verify(&predictor, &data, expectation, &task.predict);
         }
     }
@@ -120,6 +150,41 @@ fn test_gbtree() {
     );
 }
 
+#[test]
+fn test_random_forest() {
+    // binary model of xgboost 1.0 with two parallel trees per round,
+    // which together predict as a tree of v47/binary-logistic
+    let predictor = load_model("gbtree", "v100/random-forest", "model");
+    assert_eq!(predictor.version(), ModelVersion::Binary(1, 0));
+    assert_eq!(predictor.num_roots(), 1);
+    assert_eq!(predictor.num_boosted_rounds(), 3);
+    let data = load_data("data/agaricus.txt.0.test");
+    for task in [
+        tasks::predict(),
+        tasks::predict_margin(),
+        tasks::predict_with_ntree_limit(1),
+    ]
+    .iter()
+    {
+        let expectation = load_expectation(&format!(
+            "expectation/gbtree/v47/binary-logistic.{}",
+            task.expectation_suffix
+        ));
+        verify(&predictor, &data, expectation, &task.predict);
+    }
+
+    // both trees of a round reach the leaf of the tree they come from
+    let source = load_model("gbtree", "v47/binary-logistic", "model");
+    for (_, map) in data.iter() {
+        let leaves: Vec<usize> = source
+            .predict_leaf(map, 2)
+            .into_iter()
+            .flat_map(|leaf| vec![leaf, leaf])
+            .collect();
+        assert_eq!(predictor.predict_leaf(map, 2), leaves);
+    }
+}
+
 #[test]
 fn test_dart() {
     run(
@@ -132,3 +197,2646 @@ fn test_dart() {
         ],
     )
 }
+
+#[test]
+fn test_json() {
+    for extension in ["json", "ubj"].iter() {
+        run_format(
+            String::from("gbtree"),
+            vec![
+                String::from("v47/binary-logistic"),
+                String::from("v47/multi-softprob"),
+            ],
+            String::from("agaricus.txt.0.test"),
+            vec![
+                tasks::predict(),
+                tasks::predict_margin(),
+                tasks::predict_with_ntree_limit(1),
+                tasks::predict_leaf(),
+            ],
+            extension,
+        );
+    }
+    run_format(
+        String::from("gblinear"),
+        vec![String::from("v47/multi-softprob")],
+        String::from("agaricus.txt.0.test"),
+        vec![tasks::predict(), tasks::predict_margin()],
+        "json",
+    );
+    run_format(
+        String::from("dart"),
+        vec![String::from("rank-pairwise")],
+        String::from("mq2008.test"),
+        vec![tasks::predict()],
+        "json",
+    );
+}
+
+#[test]
+fn test_quantile() {
+    for extension in ["json", "ubj"].iter() {
+        let predictor = load_model("gbtree", "v200/reg-quantileerror", extension);
+        assert_eq!(predictor.quantile_alphas(), Some(&[0.1f32, 0.5, 0.9][..]));
+        run_format(
+            String::from("gbtree"),
+            vec![String::from("v200/reg-quantileerror")],
+            String::from("agaricus.txt.0.test"),
+            vec![tasks::predict()],
+            extension,
+        );
+    }
+
+    // the quantiles keep the order they were saved in, one per output group
+    let mut model = String::new();
+    open_resource_file("model/gbtree/v200/reg-quantileerror.json")
+        .unwrap()
+        .read_to_string(&mut model)
+        .unwrap();
+    let expected = load_model("gbtree", "v200/reg-quantileerror", "json");
+    let model = model.replace("[0.1, 0.5, 0.9]", "[0.9, 0.1, 0.5]");
+    let predictor = TestPredictor::read_from_json(&mut model.as_bytes()).unwrap();
+    assert_eq!(predictor.quantile_alphas(), Some(&[0.9f32, 0.1, 0.5][..]));
+    let data = load_data("data/agaricus.txt.0.test");
+    assert_same_model(&expected, &predictor, &data);
+}
+
+#[test]
+fn test_ubjson_huge_lengths() {
+    let huge = i64::MAX.to_be_bytes();
+    // an array of floats and a key of i64::MAX elements, with nothing after them
+    let array = [&b"[$d#L"[..], &huge[..], &[0u8; 8][..]].concat();
+    let key = [&b"{L"[..], &huge[..], &b"learner"[..]].concat();
+    for bytes in [array, key].iter() {
+        assert!(TestPredictor::read_from_ubjson(&mut bytes.as_slice()).is_err());
+    }
+}
+
+#[test]
+fn test_malformed_json_trees() {
+    let mut model = String::new();
+    open_resource_file("model/gbtree/v47/binary-logistic.json")
+        .unwrap()
+        .read_to_string(&mut model)
+        .unwrap();
+    let model: serde_json::Value = serde_json::from_str(&model).unwrap();
+    let read = |field: &str, node: usize, value: i64| {
+        let mut model = model.clone();
+        model["learner"]["gradient_booster"]["model"]["trees"][0][field][node] = value.into();
+        TestPredictor::read_from_json(&mut model.to_string().as_bytes())
+    };
+    assert!(read("left_children", 0, 1).is_ok());
+    // out of range children and parents
+    assert!(read("left_children", 0, 1000).is_err());
+    assert!(read("right_children", 0, -2).is_err());
+    assert!(read("parents", 1, 1000).is_err());
+    // a node reached twice, looping back to the root
+    assert!(read("right_children", 0, 1).is_err());
+    assert!(read("left_children", 1, 0).is_err());
+}
+
+struct CustomSigmoid;
+
+impl ObjFunction for CustomSigmoid {
+    fn vector(&self, preds: &[f32]) -> Vec<f32> {
+        preds.iter().map(|x| self.scalar(*x)).collect()
+    }
+
+    fn scalar(&self, pred: f32) -> f32 {
+        1f32 / (1f32 + (-pred).exp())
+    }
+
+    fn prob_to_margin(&self, base_score: f32) -> f32 {
+        -(1f32 / base_score - 1f32).ln()
+    }
+}
+
+/// Objective function panicking on every transformed prediction
+struct PanickingObjFunction;
+
+impl ObjFunction for PanickingObjFunction {
+    fn vector(&self, _preds: &[f32]) -> Vec<f32> {
+        panic!("transform of a broken model");
+    }
+
+    fn scalar(&self, _pred: f32) -> f32 {
+        panic!("transform of a broken model");
+    }
+
+    fn prob_to_margin(&self, base_score: f32) -> f32 {
+        CustomSigmoid.prob_to_margin(base_score)
+    }
+}
+
+#[test]
+fn test_custom_obj_function() {
+    let data = load_data("data/agaricus.txt.0.test");
+    let mut model = String::new();
+    open_resource_file("model/gbtree/v47/binary-logistic.json")
+        .unwrap()
+        .read_to_string(&mut model)
+        .unwrap();
+    let model = model.replace("\"binary:logistic\"", "\"custom:sigmoid\"");
+
+    match TestPredictor::read_from_json(&mut model.as_bytes()) {
+        Err(e) => match e.kind() {
+            ErrorKind::UnsupportedObjFunctionType(name) => assert_eq!(name, "custom:sigmoid"),
+            _ => panic!("unexpected error: {}", e),
+        },
+        Ok(_) => panic!("placeholder objective must be rejected"),
+    }
+
+    let predictor = TestPredictor::read_from_json_with_obj_function(
+        &mut model.as_bytes(),
+        Some(Arc::new(CustomSigmoid)),
+    )
+    .unwrap();
+    verify(
+        &predictor,
+        &data,
+        load_expectation("expectation/gbtree/v47/binary-logistic.predict"),
+        &tasks::predict().predict,
+    );
+
+    register_obj_function("custom:sigmoid", Arc::new(CustomSigmoid));
+    let predictor = TestPredictor::read_from_json(&mut model.as_bytes());
+    // unregister before checking, the registry is shared by all tests of the process
+    assert!(unregister_obj_function("custom:sigmoid").is_some());
+    verify(
+        &predictor.unwrap(),
+        &data,
+        load_expectation("expectation/gbtree/v47/binary-logistic.predict"),
+        &tasks::predict().predict,
+    );
+    assert!(TestPredictor::read_from_json(&mut model.as_bytes()).is_err());
+}
+
+#[test]
+fn test_categorical() {
+    for extension in ["json", "ubj"].iter() {
+        run_format(
+            String::from("gbtree"),
+            vec![String::from("v200/categorical")],
+            String::from("categorical.test"),
+            vec![tasks::predict(), tasks::predict_leaf()],
+            extension,
+        );
+    }
+}
+
+/// Routes categorical features as xgboost's `common::Decision` does: values whose
+/// truncated category is chosen go right, other categories, negative and too large
+/// values go left, and missing values follow the default child
+#[test]
+fn test_categorical_decision() {
+    let mut model = String::new();
+    open_resource_file("model/gbtree/v200/categorical.json")
+        .unwrap()
+        .read_to_string(&mut model)
+        .unwrap();
+    let mut model: serde_json::Value = serde_json::from_str(&model).unwrap();
+    // the first tree alone: f0 in {1, 3, 7} goes right to the leaf 0.75, else left to
+    // f1 < 0.3, whose left leaf is -0.5
+    let gbtree = &mut model["learner"]["gradient_booster"]["model"];
+    gbtree["trees"].as_array_mut().unwrap().truncate(1);
+    gbtree["gbtree_model_param"]["num_trees"] = "1".into();
+    gbtree["iteration_indptr"] = serde_json::json!([0, 1]);
+    gbtree["tree_info"] = serde_json::json!([0]);
+    let predictor = TestPredictor::read_from_json(&mut model.to_string().as_bytes()).unwrap();
+
+    for (f0, leaf, value) in [
+        (Some(1f32), 2, 0.75f32),
+        (Some(7f32), 2, 0.75),
+        (Some(3.9f32), 2, 0.75),
+        (Some(2f32), 3, -0.5),
+        (Some(0f32), 3, -0.5),
+        (Some(-1f32), 3, -0.5),
+        (Some(64f32), 3, -0.5),
+        (Some(1e9f32), 3, -0.5),
+        (None, 3, -0.5),
+    ]
+    .iter()
+    {
+        let mut row = TestMap::new();
+        row.insert(1, 0.1f32);
+        if let Some(f0) = f0 {
+            row.insert(0, *f0);
+        }
+        assert_eq!(predictor.predict_leaf(&row, 0), vec![*leaf], "{:?}", f0);
+        assert_eq!(
+            predictor.predict(&row, true, 0),
+            vec![0.5 + value],
+            "{:?}",
+            f0
+        );
+    }
+}
+
+#[test]
+fn test_multi_target() {
+    for extension in ["json", "ubj"].iter() {
+        run_format(
+            String::from("gbtree"),
+            vec![String::from("v200/multi-target")],
+            String::from("agaricus.txt.0.test"),
+            vec![
+                tasks::predict(),
+                tasks::predict_with_ntree_limit(1),
+                tasks::predict_leaf(),
+            ],
+            extension,
+        );
+    }
+}
+
+#[test]
+fn test_multi_root() {
+    run(
+        String::from("gbtree"),
+        vec![String::from("v40/multi-root")],
+        String::from("agaricus.txt.0.test"),
+        vec![
+            tasks::predict(),
+            tasks::predict_leaf(),
+            tasks::predict_with_root_index(1),
+            tasks::predict_leaf_with_root_index(1),
+        ],
+    );
+
+    let predictor = load_model("gbtree", "v40/multi-root", "model");
+    assert_eq!(predictor.num_roots(), 2);
+    match predictor.predict_with(
+        &TestMap::new(),
+        &PredictOptions {
+            root_index: 2,
+            ..Default::default()
+        },
+    ) {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidRootIndex(2, 2) => {}
+            _ => panic!("unexpected error: {}", e),
+        },
+        Ok(_) => panic!("root index must be validated"),
+    }
+    assert!(load_model("gbtree", "v47/binary-logistic", "model")
+        .predict_leaf_with(
+            &TestMap::new(),
+            &PredictOptions {
+                root_index: 1,
+                ..Default::default()
+            }
+        )
+        .is_err());
+}
+
+#[test]
+fn test_iteration_range() {
+    run(
+        String::from("gbtree"),
+        vec![
+            String::from("v47/binary-logistic"),
+            String::from("v47/multi-softprob"),
+        ],
+        String::from("agaricus.txt.0.test"),
+        vec![
+            tasks::predict_with_iteration_range(0, 1, "predict_ntree"),
+            tasks::predict_with_iteration_range(0, 0, "predict"),
+            tasks::predict_leaf_with_iteration_range(0, 2, "leaf_ntree"),
+        ],
+    );
+
+    let data = load_data("data/agaricus.txt.0.test");
+    for model_name in ["v47/multi-softprob", "v200/multi-target"].iter() {
+        let extension = if model_name.starts_with("v200") {
+            "json"
+        } else {
+            "model"
+        };
+        let predictor = load_model("gbtree", model_name, extension);
+        let rounds = predictor.num_boosted_rounds();
+        for (_, map) in data.iter() {
+            let margin = predictor.predict(map, true, 0);
+            let base = predictor
+                .predict_with(
+                    map,
+                    &PredictOptions {
+                        output_margin: true,
+                        iteration_range: (1, 1),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            let head = predictor
+                .predict_with(
+                    map,
+                    &PredictOptions {
+                        output_margin: true,
+                        iteration_range: (0, 1),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            let tail = predictor
+                .predict_with(
+                    map,
+                    &PredictOptions {
+                        output_margin: true,
+                        iteration_range: (1, rounds),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            for i in 0..margin.len() {
+                assert_approx_eq!(head[i] + tail[i] - base[i], margin[i], 1e-5f32);
+            }
+
+            let leaf = predictor.predict_leaf(map, 0);
+            let tail_leaf = predictor
+                .predict_leaf_with(
+                    map,
+                    &PredictOptions {
+                        iteration_range: (1, 0),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            assert_eq!(leaf.len() % rounds, 0);
+            assert_eq!(tail_leaf[..], leaf[leaf.len() / rounds..]);
+        }
+    }
+    assert_eq!(
+        load_model("gbtree", "v47/multi-softprob", "model").num_boosted_rounds(),
+        3
+    );
+    assert_eq!(
+        load_model("gbtree", "v200/multi-target", "json").num_boosted_rounds(),
+        2
+    );
+
+    let predictor = load_model("gbtree", "v47/binary-logistic", "model");
+    match predictor.predict_with(
+        &TestMap::new(),
+        &PredictOptions {
+            iteration_range: (2, 1),
+            ..Default::default()
+        },
+    ) {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidIterationRange(2, 1) => {}
+            _ => panic!("unexpected error: {}", e),
+        },
+        Ok(_) => panic!("iteration range must be validated"),
+    }
+}
+
+#[test]
+fn test_staged() {
+    let cases = [
+        (
+            "gbtree",
+            "v47/multi-softprob",
+            "model",
+            "agaricus.txt.0.test",
+        ),
+        (
+            "gbtree",
+            "v47/binary-logistic",
+            "model",
+            "agaricus.txt.0.test",
+        ),
+        ("gbtree", "v200/multi-target", "json", "agaricus.txt.0.test"),
+        ("dart", "rank-pairwise", "model", "mq2008.test"),
+    ];
+    for (model_type, model_name, extension, data_file) in cases.iter() {
+        let predictor = load_model(model_type, model_name, extension);
+        let data = load_data(&format!("data/{}", data_file));
+        for (_, map) in data.iter() {
+            for output_margin in [false, true].iter() {
+                let staged = predictor.predict_staged(map, *output_margin).unwrap();
+                assert_eq!(staged.len(), predictor.num_boosted_rounds());
+                for (round, preds) in staged.iter().enumerate() {
+                    let expected = predictor.predict(map, *output_margin, round + 1);
+                    assert_eq!(preds.len(), expected.len());
+                    for i in 0..preds.len() {
+                        assert_approx_eq!(preds[i], expected[i], 1e-5f32);
+                    }
+                }
+            }
+        }
+    }
+
+    // linear models have no boosting rounds
+    assert!(load_model("gblinear", "v47/binary-logistic", "model")
+        .predict_staged(&TestMap::new(), false)
+        .is_err());
+}
+
+fn append_string(bytes: &mut Vec<u8>, s: &str) {
+    bytes.extend_from_slice(&(s.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(s.as_bytes());
+}
+
+#[test]
+fn test_best_iteration() {
+    // binary models of 0.6+ save learner attributes after the booster
+    let mut bytes = Vec::new();
+    open_resource_file("model/gbtree/v47/binary-logistic.model")
+        .unwrap()
+        .read_to_end(&mut bytes)
+        .unwrap();
+    bytes[12..16].copy_from_slice(&1i32.to_le_bytes());
+    bytes.extend_from_slice(&2u64.to_le_bytes());
+    append_string(&mut bytes, "best_iteration");
+    append_string(&mut bytes, "1");
+    append_string(&mut bytes, "best_score");
+    append_string(&mut bytes, "0.04");
+    let binary: TestPredictor = Predictor::read_from(&mut bytes.as_slice()).unwrap();
+    assert_eq!(binary.attributes().len(), 2);
+    assert_eq!(binary.attribute("best_score"), Some("0.04"));
+    assert_eq!(binary.best_iteration(), Some(1));
+    assert_eq!(binary.best_ntree_limit(), None);
+
+    let mut json_text = String::new();
+    open_resource_file("model/gbtree/v47/multi-softprob.json")
+        .unwrap()
+        .read_to_string(&mut json_text)
+        .unwrap();
+    let json_text = json_text.replacen(
+        "\"attributes\":{}",
+        "\"attributes\":{\"best_ntree_limit\":\"1\"}",
+        1,
+    );
+    let json: TestPredictor = Predictor::read_from_json(&mut json_text.as_bytes()).unwrap();
+    assert_eq!(json.best_iteration(), None);
+    assert_eq!(json.best_ntree_limit(), Some(1));
+
+    let data = load_data("data/agaricus.txt.0.test");
+    for (mut predictor, best_rounds) in [(binary, 2), (json, 1)] {
+        let all_rounds = predictor.num_boosted_rounds();
+        assert!(best_rounds < all_rounds);
+        let expected: Vec<(Vec<f32>, Vec<usize>)> = data
+            .iter()
+            .map(|(_, map)| {
+                (
+                    predictor.predict(map, true, best_rounds),
+                    predictor.predict_leaf(map, best_rounds),
+                )
+            })
+            .collect();
+        predictor.set_use_best_iteration(true);
+        for ((_, map), (preds, leaves)) in data.iter().zip(expected.iter()) {
+            assert_eq!(&predictor.predict(map, true, 0), preds);
+            assert_eq!(
+                &predictor
+                    .predict_with(
+                        map,
+                        &PredictOptions {
+                            output_margin: true,
+                            iteration_range: (0, 0),
+                            ..Default::default()
+                        }
+                    )
+                    .unwrap(),
+                preds
+            );
+            assert_eq!(&predictor.predict_leaf(map, 0), leaves);
+            assert_eq!(
+                predictor.predict_staged(map, true).unwrap().len(),
+                best_rounds
+            );
+            // explicit limits are kept
+            assert_eq!(
+                predictor.predict_leaf(map, all_rounds).len(),
+                all_rounds * leaves.len() / best_rounds
+            );
+        }
+    }
+
+    // best_ntree_limit counts the parallel trees of a round
+    let mut bytes = Vec::new();
+    open_resource_file("model/gbtree/v100/random-forest.model")
+        .unwrap()
+        .read_to_end(&mut bytes)
+        .unwrap();
+    bytes[12..16].copy_from_slice(&1i32.to_le_bytes());
+    bytes.extend_from_slice(&1u64.to_le_bytes());
+    append_string(&mut bytes, "best_ntree_limit");
+    append_string(&mut bytes, "4");
+    let mut forest: TestPredictor = Predictor::read_from(&mut bytes.as_slice()).unwrap();
+    assert_eq!(forest.best_ntree_limit(), Some(4));
+    forest.set_use_best_iteration(true);
+    for (_, map) in data.iter() {
+        assert_eq!(forest.predict(map, true, 0), forest.predict(map, true, 2));
+        assert_eq!(forest.predict_leaf(map, 0).len(), 4);
+    }
+
+    // before 0.6 the flag announces a prediction buffer, which nothing follows
+    let mut bytes = Vec::new();
+    open_resource_file("model/gbtree/v47/binary-logistic.model")
+        .unwrap()
+        .read_to_end(&mut bytes)
+        .unwrap();
+    bytes[12..16].copy_from_slice(&1i32.to_le_bytes());
+    // num_pbuffer of the booster, following the names of the objective and the booster
+    let num_pbuffer_offset = 136 + 8 + "binary:logistic".len() + 8 + "gbtree".len() + 16;
+    bytes[num_pbuffer_offset..num_pbuffer_offset + 8].copy_from_slice(&2i64.to_le_bytes());
+    bytes.extend_from_slice(&[0u8; 2 * 8]);
+    bytes.extend_from_slice(&[0xffu8; 8]);
+    let pbuffer: TestPredictor = Predictor::read_from_buffered(&mut bytes.as_slice()).unwrap();
+    assert!(pbuffer.attributes().is_empty());
+}
+
+#[test]
+fn test_base_margin() {
+    let cases = [
+        ("gblinear", "v47/binary-logistic"),
+        ("gblinear", "v47/multi-softprob"),
+        ("gbtree", "v47/binary-logistic"),
+        ("gbtree", "v47/multi-softprob"),
+    ];
+    let data = load_data("data/agaricus.txt.0.test");
+    let feats: Vec<TestMap> = data.iter().map(|(_, map)| map.clone()).collect();
+    for (model_type, model_name) in cases.iter() {
+        let predictor = load_model(model_type, model_name, "model");
+        for (row, feat) in feats.iter().enumerate() {
+            let margin = predictor.predict(feat, true, 0);
+            let tree_sum = predictor
+                .predict_with(
+                    feat,
+                    &PredictOptions {
+                        output_margin: true,
+                        base_margin: Some(vec![0f32]),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            // a base margin equal to base_score gives the usual predictions
+            let base_score = margin[0] - tree_sum[0];
+            let preds = predictor
+                .predict_with(
+                    feat,
+                    &PredictOptions {
+                        base_margin: Some(vec![base_score]),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            let expected = predictor.predict(feat, false, 0);
+            for i in 0..preds.len() {
+                assert_approx_eq!(preds[i], expected[i], 1e-5f32);
+            }
+
+            let base_margin: Vec<f32> = (0..margin.len())
+                .map(|group| (row + group) as f32 * 0.01)
+                .collect();
+            let preds = predictor
+                .predict_with(
+                    feat,
+                    &PredictOptions {
+                        output_margin: true,
+                        base_margin: Some(base_margin.clone()),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            for i in 0..preds.len() {
+                assert_approx_eq!(preds[i], tree_sum[i] + base_margin[i], 1e-5f32);
+            }
+        }
+
+        let base_margins: Vec<f32> = (0..feats.len()).map(|row| row as f32 * 0.01).collect();
+        let batch = predictor
+            .predict_batch_with(
+                &feats,
+                &PredictOptions {
+                    base_margin: Some(base_margins.clone()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(batch.len(), feats.len());
+        for (row, preds) in batch.iter().enumerate() {
+            let expected = predictor
+                .predict_with(
+                    &feats[row],
+                    &PredictOptions {
+                        base_margin: Some(base_margins[row..row + 1].to_vec()),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            assert_eq!(preds, &expected);
+        }
+        assert_eq!(
+            predictor.predict_batch(&feats, false, 0)[0],
+            predictor.predict(&feats[0], false, 0)
+        );
+
+        let too_long = vec![0f32; predictor.predict(&feats[0], true, 0).len() + 1];
+        match predictor
+            .predict_with(
+                &feats[0],
+                &PredictOptions {
+                    output_margin: true,
+                    base_margin: Some(too_long.clone()),
+                    ..Default::default()
+                },
+            )
+            .map_err(|e| e.0)
+        {
+            Err(ErrorKind::InvalidBaseMargin(size, _)) => assert_eq!(size, too_long.len()),
+            _ => panic!("base margin of size {} accepted", too_long.len()),
+        }
+        assert!(predictor
+            .predict_batch_with(
+                &feats,
+                &PredictOptions {
+                    base_margin: Some(base_margins[1..].to_vec()),
+                    ..Default::default()
+                }
+            )
+            .is_err());
+    }
+}
+
+#[test]
+fn test_poisson_exposure() {
+    let mut model = String::new();
+    open_resource_file("model/gbtree/v47/binary-logistic.json")
+        .unwrap()
+        .read_to_string(&mut model)
+        .unwrap();
+    let model = model.replace("\"binary:logistic\"", "\"count:poisson\"");
+    let predictor = TestPredictor::read_from_json(&mut model.as_bytes()).unwrap();
+    assert_eq!(predictor.objective(), "count:poisson");
+
+    for (_, feat) in load_data("data/agaricus.txt.0.test").iter().take(50) {
+        let margin = predictor.predict(feat, true, 0)[0];
+        assert_approx_eq!(predictor.predict(feat, false, 0)[0], margin.exp(), 1e-5f32);
+        // base_score 0.5 is saved as a mean, its margin is ln(0.5)
+        let tree_sum = margin - 0.5f32.ln();
+        // the log of the exposure as base margin scales the predicted counts
+        for exposure in [0.25f32, 1f32, 3f32].iter() {
+            let preds = predictor
+                .predict_with(
+                    feat,
+                    &PredictOptions {
+                        base_margin: Some(vec![exposure.ln()]),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            assert_approx_eq!(preds[0], exposure * tree_sum.exp(), 1e-4f32);
+        }
+    }
+}
+
+#[test]
+fn test_feature_names() {
+    let names: Vec<String> = (0..126).map(|i| format!("f{}", i)).collect();
+    let quoted: Vec<String> = names.iter().map(|n| format!("\"{}\"", n)).collect();
+    let mut json_text = String::new();
+    open_resource_file("model/gbtree/v47/binary-logistic.json")
+        .unwrap()
+        .read_to_string(&mut json_text)
+        .unwrap();
+    let json_text = json_text
+        .replacen(
+            "\"feature_names\":[]",
+            &format!("\"feature_names\":[{}]", quoted.join(",")),
+            1,
+        )
+        .replacen(
+            "\"feature_types\":[]",
+            &format!("\"feature_types\":[{}]", vec!["\"i\""; 126].join(",")),
+            1,
+        );
+    let named: Predictor<FVecNamed> = Predictor::read_from_json(&mut json_text.as_bytes()).unwrap();
+    assert_eq!(named.feature_names(), &names[..]);
+    assert_eq!(named.feature_types(), &vec![String::from("i"); 126][..]);
+
+    // legacy binary models get their names from the caller
+    let mut binary = load_model("gbtree", "v47/binary-logistic", "model");
+    assert!(binary.feature_names().is_empty());
+    assert!(binary.set_feature_names(names[1..].to_vec()).is_err());
+    let mut duplicated = names.clone();
+    duplicated[1] = String::from("f0");
+    assert!(binary.set_feature_names(duplicated).is_err());
+    assert!(binary
+        .set_feature_types(vec![String::from("x"); 126])
+        .is_err());
+    binary.set_feature_names(names.clone()).unwrap();
+    assert_eq!(binary.feature_index(), named.feature_index());
+
+    let data = load_data("data/agaricus.txt.0.test");
+    for (_, map) in data.iter() {
+        let feat = named
+            .named_fvec(map.iter().map(|(index, value)| (&names[*index], *value)))
+            .unwrap();
+        assert_eq!(
+            named.predict(&feat, false, 0),
+            binary.predict(map, false, 0)
+        );
+    }
+
+    match named.named_fvec(vec![("f0", 1f32), ("unknown", 1f32)]) {
+        Err(e) => match e.0 {
+            ErrorKind::UnknownFeatureName(name) => assert_eq!(name, "unknown"),
+            _ => panic!("unexpected error {}", e),
+        },
+        Ok(_) => panic!("unknown feature name accepted"),
+    }
+}
+
+#[test]
+fn test_strict_feature_count() {
+    let mut model_file = open_resource_file("model/gbtree/v47/binary-logistic.model").unwrap();
+    let mut dense: Predictor<FVecArray<f32>> = Predictor::read_from(&mut model_file).unwrap();
+    let mut sparse = load_model("gbtree", "v47/binary-logistic", "model");
+    assert_eq!(dense.num_feature(), 126);
+    dense.set_strict_feature_count(true);
+    sparse.set_strict_feature_count(true);
+
+    let data = load_data("data/agaricus.txt.0.test");
+    for (_, map) in data.iter().take(20) {
+        let mut values = vec![0f32; 126];
+        for (index, value) in map.iter() {
+            values[*index] = *value;
+        }
+        let expected = sparse.predict(map, false, 0);
+        assert_eq!(
+            dense.predict(&FVecArray::new(values.clone(), true), false, 0),
+            expected
+        );
+
+        // a dense vector which lost a column
+        values.pop();
+        let truncated = FVecArray::new(values, true);
+        match dense
+            .predict_with(
+                &truncated,
+                &PredictOptions {
+                    iteration_range: (0, 0),
+                    ..Default::default()
+                },
+            )
+            .map_err(|e| e.0)
+        {
+            Err(ErrorKind::FeatureCountMismatch(size, num_feature)) => {
+                assert_eq!((size, num_feature), (125, 126))
+            }
+            _ => panic!("truncated input accepted"),
+        }
+
+        let mut extended = map.clone();
+        extended.insert(126, 1f32);
+        assert!(sparse.check_feature_count(&extended).is_err());
+        assert!(sparse
+            .predict_with(
+                &extended,
+                &PredictOptions {
+                    base_margin: Some(vec![0f32]),
+                    ..Default::default()
+                }
+            )
+            .is_err());
+    }
+
+    let mut extended = data.front().unwrap().1.clone();
+    extended.insert(200, 1f32);
+    sparse.set_strict_feature_count(false);
+    assert_eq!(sparse.predict(&extended, false, 0).len(), 1);
+}
+
+#[test]
+fn test_strict_feature_count_errors() {
+    fn assert_mismatch<T>(result: xgboost_predictor::errors::Result<T>) {
+        match result.map_err(|e| e.0) {
+            Err(ErrorKind::FeatureCountMismatch(127, 126)) => {}
+            _ => panic!("input with 127 features accepted"),
+        }
+    }
+
+    let mut predictor = load_model("gbtree", "v47/binary-logistic", "model");
+    predictor.set_strict_feature_count(true);
+    let mut extended = TestMap::new();
+    extended.insert(126, 1f32);
+    assert_mismatch(predictor.try_predict(&extended, false, 0));
+    assert_mismatch(predictor.try_predict_single(&extended, false, 0));
+    assert_mismatch(predictor.try_predict_leaf(&extended, 0));
+    assert_mismatch(predictor.predict_staged(&extended, false));
+    assert_mismatch(predictor.try_predict_batch(&[TestMap::new(), extended], false, 0));
+}
+
+#[test]
+#[should_panic(expected = "Input has 127 features but model has 126 features")]
+fn test_strict_feature_count_panics() {
+    let mut predictor = load_model("gbtree", "v47/binary-logistic", "model");
+    predictor.set_strict_feature_count(true);
+    let mut extended = TestMap::new();
+    extended.insert(126, 1f32);
+    predictor.predict(&extended, false, 0);
+}
+
+#[test]
+fn test_try_predict_single_errors() {
+    let data = load_data("data/agaricus.txt.0.test");
+    let (_, map) = data.front().unwrap();
+    for (model_type, model_name, extension) in [
+        ("gbtree", "v47/multi-softmax", "model"),
+        ("gbtree", "v47/multi-softprob", "model"),
+        ("gblinear", "v47/multi-softprob", "model"),
+        ("gbtree", "v200/reg-quantileerror", "json"),
+        ("gbtree", "v200/multi-target", "json"),
+    ]
+    .iter()
+    {
+        let predictor = load_model(model_type, model_name, extension);
+        assert!(predictor.try_predict_single(map, false, 0).is_err());
+        assert!(predictor.try_predict_single(map, true, 0).is_err());
+    }
+    let predictor = load_model("gbtree", "v47/binary-logistic", "model");
+    assert_eq!(
+        predictor.try_predict_single(map, false, 0).unwrap(),
+        predictor.predict_single(map, false, 0)
+    );
+}
+
+/// Reads a dump saved by xgboost's `Booster.dump_model()`, one `booster[i]:` section per tree
+fn load_dump(rel_path: &str) -> Vec<String> {
+    let mut text = String::new();
+    open_resource_file(rel_path)
+        .unwrap()
+        .read_to_string(&mut text)
+        .unwrap();
+    let mut trees: Vec<String> = Vec::new();
+    for line in text.lines() {
+        if line.starts_with("booster[") {
+            trees.push(String::new());
+        } else {
+            let tree = trees.last_mut().unwrap();
+            tree.push_str(line);
+            tree.push('\n');
+        }
+    }
+    trees
+}
+
+#[test]
+fn test_dump_model() {
+    let cases = [
+        ("gbtree", "v47/binary-logistic", "model", false),
+        ("gbtree", "v47/binary-logistic", "model", true),
+        ("gbtree", "v47/multi-softprob", "model", false),
+        ("gbtree", "v47/multi-softprob", "json", true),
+        ("gbtree", "v200/categorical", "json", true),
+    ];
+    for (model_type, model_name, extension, with_stats) in cases.iter() {
+        let predictor = load_model(model_type, model_name, extension);
+        let suffix = if *with_stats { "dump_stats" } else { "dump" };
+        let expected = load_dump(&format!(
+            "expectation/{}/{}.{}",
+            model_type, model_name, suffix
+        ));
+        assert_eq!(predictor.dump_model(*with_stats, false), expected);
+    }
+
+    // without names and types, feature names are `f{index}`
+    let mut predictor = load_model("gbtree", "v47/binary-logistic", "model");
+    assert_eq!(
+        predictor.dump_model(false, true),
+        predictor.dump_model(false, false)
+    );
+    let names: Vec<String> = (0..126).map(|i| format!("feature{}", i)).collect();
+    let types: Vec<String> = (0..126)
+        .map(|i| String::from(["i", "q", "int"][i % 3]))
+        .collect();
+    predictor.set_feature_names(names).unwrap();
+    predictor.set_feature_types(types).unwrap();
+    assert_eq!(
+        predictor.dump_model(false, true),
+        load_dump("expectation/gbtree/v47/binary-logistic.dump_fmap")
+    );
+
+    let linear = load_model("gblinear", "v47/multi-softprob", "model");
+    assert_eq!(
+        linear.dump_model(false, false),
+        load_dump("expectation/gblinear/v47/multi-softprob.dump")
+    );
+}
+
+/// Loads v47/binary-logistic with a NaN first leaf
+fn load_model_with_nan_leaf() -> TestPredictor {
+    let mut bytes = Vec::new();
+    open_resource_file("model/gbtree/v47/binary-logistic.model")
+        .unwrap()
+        .read_to_end(&mut bytes)
+        .unwrap();
+    // the nodes of the first tree follow the parameters of the learner, the booster and the tree
+    let nodes = 136 + 8 + "binary:logistic".len() + 8 + "gbtree".len() + 160 + 148;
+    let leaf = (0..)
+        .map(|nid| nodes + nid * 20)
+        .find(|node| bytes[node + 4..node + 8] == (-1i32).to_le_bytes())
+        .unwrap();
+    bytes[leaf + 16..leaf + 20].copy_from_slice(&f32::NAN.to_le_bytes());
+    Predictor::read_from(&mut bytes.as_slice()).unwrap()
+}
+
+#[test]
+fn test_dump_model_json_and_dot() {
+    let cases = [
+        (
+            "v47/binary-logistic",
+            "model",
+            DumpFormat::Json,
+            false,
+            "dump_json",
+        ),
+        (
+            "v47/binary-logistic",
+            "json",
+            DumpFormat::Json,
+            true,
+            "dump_json_stats",
+        ),
+        (
+            "v47/binary-logistic",
+            "model",
+            DumpFormat::Dot,
+            false,
+            "dump_dot",
+        ),
+        (
+            "v200/categorical",
+            "json",
+            DumpFormat::Json,
+            true,
+            "dump_json_stats",
+        ),
+        (
+            "v200/categorical",
+            "ubj",
+            DumpFormat::Dot,
+            true,
+            "dump_dot_stats",
+        ),
+    ];
+    for (model_name, extension, format, with_stats, suffix) in cases.iter() {
+        let predictor = load_model("gbtree", model_name, extension);
+        let expected = load_dump(&format!("expectation/gbtree/{}.{}", model_name, suffix));
+        let dump = predictor
+            .dump_model_with_format(*format, *with_stats, false)
+            .unwrap();
+        assert_eq!(dump, expected);
+        if *format == DumpFormat::Json {
+            for tree in dump.iter() {
+                serde_json::from_str::<serde_json::Value>(tree).unwrap();
+            }
+        } else {
+            assert_eq!(
+                predictor.to_graphviz(1, *with_stats, false).unwrap(),
+                expected[1]
+            );
+            assert!(predictor
+                .to_graphviz(expected.len(), *with_stats, false)
+                .is_err());
+        }
+    }
+
+    let mut predictor = load_model("gbtree", "v47/binary-logistic", "model");
+    let names: Vec<String> = (0..126).map(|i| format!("feature{}", i)).collect();
+    let types: Vec<String> = (0..126)
+        .map(|i| String::from(["i", "q", "int"][i % 3]))
+        .collect();
+    predictor.set_feature_names(names).unwrap();
+    predictor.set_feature_types(types).unwrap();
+    assert_eq!(
+        predictor
+            .dump_model_with_format(DumpFormat::Json, false, true)
+            .unwrap(),
+        load_dump("expectation/gbtree/v47/binary-logistic.dump_json_fmap")
+    );
+
+    let linear = load_model("gblinear", "v47/multi-softprob", "model");
+    let dump = linear
+        .dump_model_with_format(DumpFormat::Json, false, false)
+        .unwrap();
+    let weights: serde_json::Value = serde_json::from_str(&dump[0]).unwrap();
+    assert_eq!(weights["bias"].as_array().unwrap().len(), 2);
+    assert_eq!(weights["weight"].as_array().unwrap().len(), 2 * 126);
+    assert!(linear
+        .dump_model_with_format(DumpFormat::Dot, false, false)
+        .is_err());
+
+    // JSON has no NaN, NaN leaves are dumped as null
+    let predictor = load_model_with_nan_leaf();
+    let dump = predictor
+        .dump_model_with_format(DumpFormat::Json, true, false)
+        .unwrap();
+    assert!(dump[0].contains("\"leaf\": null"));
+    serde_json::from_str::<serde_json::Value>(&dump[0]).unwrap();
+}
+
+#[test]
+fn test_feature_importance() {
+    let importance_types = [
+        ImportanceType::Weight,
+        ImportanceType::Gain,
+        ImportanceType::Cover,
+        ImportanceType::TotalGain,
+        ImportanceType::TotalCover,
+    ];
+    let cases = [
+        ("gbtree", "v47/binary-logistic", "model"),
+        ("gbtree", "v47/multi-softprob", "json"),
+        ("dart", "rank-pairwise", "model"),
+    ];
+    for (model_type, model_name, extension) in cases.iter() {
+        let predictor = load_model(model_type, model_name, extension);
+        // each line holds the feature index and its score of each importance type
+        let expected = load_expectation(&format!(
+            "expectation/{}/{}.importance",
+            model_type, model_name
+        ));
+        for (k, importance_type) in importance_types.iter().enumerate() {
+            let scores = predictor.feature_importance(*importance_type).unwrap();
+            assert_eq!(scores.len(), expected.len());
+            for values in expected.iter() {
+                let score = scores[&(values[0] as usize)];
+                assert_approx_eq!(score, values[k + 1], values[k + 1].abs() * 1e-5);
+            }
+        }
+    }
+
+    let mut predictor = load_model("gbtree", "v47/binary-logistic", "model");
+    let by_index = predictor
+        .feature_importance(ImportanceType::TotalGain)
+        .unwrap();
+    let names: Vec<String> = (0..126).map(|i| format!("feature{}", i)).collect();
+    for (by_name, prefix) in [(false, "f"), (true, "feature")] {
+        if by_name {
+            predictor.set_feature_names(names.clone()).unwrap();
+        }
+        let scores = predictor
+            .feature_importance_by_name(ImportanceType::TotalGain)
+            .unwrap();
+        assert_eq!(scores.len(), by_index.len());
+        for (fid, score) in by_index.iter() {
+            assert_eq!(scores[&format!("{}{}", prefix, fid)], *score);
+        }
+    }
+
+    // coefficients of linear models, as printed by the text dump
+    let linear = load_model("gblinear", "v47/binary-logistic", "model");
+    let scores = linear.feature_importance(ImportanceType::Weight).unwrap();
+    let dump = linear.dump_model(false, false);
+    let weights: Vec<f32> = dump[0]
+        .lines()
+        .skip_while(|line| *line != "weight:")
+        .skip(1)
+        .map(|line| line.parse().unwrap())
+        .collect();
+    assert_eq!(scores.len(), weights.len());
+    for (fid, weight) in weights.iter().enumerate() {
+        assert_approx_eq!(scores[&fid], *weight, 1e-5f32);
+    }
+    assert!(linear.feature_importance(ImportanceType::Gain).is_err());
+    assert!(load_model("gblinear", "v47/multi-softprob", "model")
+        .feature_importance(ImportanceType::Weight)
+        .is_err());
+}
+
+#[test]
+fn test_model_introspection() {
+    let predictor = load_model("gbtree", "v47/binary-logistic", "model");
+    let summary = predictor.summary();
+    assert_eq!(summary.booster, "gbtree");
+    assert_eq!(summary.objective, "binary:logistic");
+    assert_eq!(summary.num_feature, 126);
+    assert_eq!(summary.num_output_group, 1);
+    assert_eq!(summary.num_trees, predictor.num_trees());
+    assert_eq!(summary.num_trees, summary.num_boosted_rounds);
+    assert!(predictor.linear_weights().is_none());
+
+    // one dump line per reachable node
+    let dump = load_dump("expectation/gbtree/v47/binary-logistic.dump_stats");
+    assert_eq!(dump.len(), summary.num_trees);
+    let trees = predictor.trees();
+    for (tree, text) in trees.iter().zip(dump.iter()) {
+        assert_eq!(tree.group(), 0);
+        assert_eq!(tree.weight(), 1f32);
+        assert_eq!(tree.nodes().len(), text.lines().count());
+        assert_eq!(
+            tree.leaves().len(),
+            text.lines().filter(|line| line.contains("leaf=")).count()
+        );
+        assert_eq!(
+            tree.max_depth(),
+            text.lines()
+                .map(|line| line.chars().take_while(|c| *c == '\t').count())
+                .max()
+                .unwrap()
+        );
+    }
+    assert_eq!(
+        summary.num_nodes,
+        dump.iter().map(|text| text.lines().count()).sum::<usize>()
+    );
+    let mut used_features: Vec<usize> = predictor
+        .feature_importance(ImportanceType::Weight)
+        .unwrap()
+        .keys()
+        .copied()
+        .collect();
+    used_features.sort_unstable();
+    assert_eq!(summary.used_features, used_features);
+    assert_eq!(summary.num_trees_per_group, vec![summary.num_trees]);
+    assert_eq!(summary.depth_histogram.len(), summary.max_depth + 1);
+    assert_eq!(
+        summary.depth_histogram.iter().sum::<usize>(),
+        summary.num_trees
+    );
+    for (depth, count) in summary.depth_histogram.iter().enumerate() {
+        assert_eq!(
+            trees
+                .iter()
+                .filter(|tree| tree.max_depth() == depth)
+                .count(),
+            *count
+        );
+    }
+    let multi_class = load_model("gbtree", "v47/multi-softprob", "model").summary();
+    assert_eq!(
+        multi_class.num_trees_per_group.len(),
+        multi_class.num_output_group
+    );
+    assert!(multi_class
+        .num_trees_per_group
+        .iter()
+        .all(|count| *count * multi_class.num_output_group == multi_class.num_trees));
+    let linear = load_model("gblinear", "v47/multi-softprob", "model").summary();
+    assert_eq!(linear.num_trees_per_group, vec![0; linear.num_output_group]);
+    assert!(linear.depth_histogram.is_empty());
+
+    // 0:[f28<-9.53674316e-07] yes=1,no=2,missing=1,gain=4000.53101,cover=1628.25
+    let root = predictor.tree(0).unwrap().node(0).unwrap();
+    assert!(!root.is_leaf());
+    assert!(!root.is_categorical());
+    assert_eq!(root.parent(), None);
+    assert_eq!(root.split_index(), Some(28));
+    assert_eq!(root.split_condition(), Some(-9.536743e-07));
+    assert_eq!(root.left_child(), Some(1));
+    assert_eq!(root.right_child(), Some(2));
+    assert_eq!(root.default_child(), Some(1));
+    assert!(root.default_left());
+    assert_approx_eq!(root.loss_change(), 4000.531, 1e-3);
+    assert_approx_eq!(root.sum_hessian(), 1628.25, 1e-3);
+    assert_eq!(root.leaf_value(), None);
+    assert_eq!(
+        predictor.tree(0).unwrap().node(1).unwrap().parent(),
+        Some(0)
+    );
+    assert!(predictor.tree(trees.len()).is_none());
+
+    // walking the trees gives the leaves and the margin of predictions
+    let data = load_data("data/agaricus.txt.0.test");
+    for (_, map) in data.iter().take(50) {
+        let mut margin = predictor.base_score();
+        let mut leaves = vec![];
+        for tree in trees.iter() {
+            let mut node = tree.node(0).unwrap();
+            while !node.is_leaf() {
+                let next = match map.get(&node.split_index().unwrap()) {
+                    None => node.default_child(),
+                    Some(value) if *value < node.split_condition().unwrap() => node.left_child(),
+                    Some(_) => node.right_child(),
+                };
+                node = tree.node(next.unwrap()).unwrap();
+            }
+            leaves.push(node.id());
+            margin += node.leaf_value().unwrap();
+            assert_eq!(node.leaf_vector(), Some(&[node.leaf_value().unwrap()][..]));
+        }
+        assert_eq!(predictor.predict_leaf(map, 0), leaves);
+        assert_approx_eq!(predictor.predict(map, true, 0)[0], margin, 1e-5f32);
+    }
+
+    let predictor = load_model("gbtree", "v47/multi-softprob", "json");
+    let summary = predictor.summary();
+    assert_eq!(summary.objective, "multi:softprob");
+    assert_eq!(summary.num_output_group, 2);
+    assert_eq!(summary.num_trees, summary.num_boosted_rounds * 2);
+    for (i, tree) in predictor.trees().iter().enumerate() {
+        assert_eq!(tree.group(), i % 2);
+    }
+
+    let predictor = load_model("gbtree", "v200/categorical", "json");
+    let categorical: Vec<_> = predictor
+        .trees()
+        .iter()
+        .flat_map(|tree| tree.nodes())
+        .filter(|node| node.is_categorical())
+        .collect();
+    assert!(!categorical.is_empty());
+    for node in categorical {
+        assert_eq!(node.split_condition(), None);
+        assert!(!node.categories().unwrap().is_empty());
+    }
+
+    let predictor = load_model("gbtree", "v200/multi-target", "json");
+    for tree in predictor.trees() {
+        assert!(tree.size_leaf_vector() > 1);
+        for leaf in tree.leaves() {
+            assert_eq!(leaf.leaf_value(), None);
+            assert_eq!(leaf.leaf_vector().unwrap().len(), tree.size_leaf_vector());
+        }
+    }
+
+    let predictor = load_model("dart", "rank-pairwise", "model");
+    assert_eq!(predictor.booster(), "dart");
+    assert_eq!(predictor.objective(), "rank:pairwise");
+
+    let predictor = load_model("gblinear", "v47/multi-softprob", "model");
+    let summary = predictor.summary();
+    assert_eq!(summary.booster, "gblinear");
+    assert_eq!(summary.num_trees, 0);
+    assert_eq!(summary.max_depth, 0);
+    assert!(summary.used_features.is_empty());
+    let weights = predictor.linear_weights().unwrap();
+    assert_eq!(
+        weights.len(),
+        (summary.num_feature + 1) * summary.num_output_group
+    );
+}
+
+/// Asserts that both models have the same structure and make the same predictions
+fn assert_same_model(
+    expected: &TestPredictor,
+    actual: &TestPredictor,
+    data: &LinkedList<DataItem>,
+) {
+    assert_eq!(actual.summary(), expected.summary());
+    assert_eq!(actual.attributes(), expected.attributes());
+    assert_eq!(
+        actual
+            .dump_model_with_format(DumpFormat::Json, true, false)
+            .unwrap(),
+        expected
+            .dump_model_with_format(DumpFormat::Json, true, false)
+            .unwrap()
+    );
+    for (_, map) in data.iter().take(100) {
+        let predicted = actual.predict(map, true, 0);
+        let expected = expected.predict(map, true, 0);
+        assert_eq!(predicted.len(), expected.len());
+        for (predicted, expected) in predicted.iter().zip(expected.iter()) {
+            assert_approx_eq!(predicted, expected, 1e-6f32);
+        }
+    }
+}
+
+#[test]
+fn test_write_model() {
+    let mut paths = vec![];
+    let mut dirs = vec![get_resource("model")];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+    assert!(paths.len() > 30);
+
+    for path in paths.iter() {
+        let name = path.to_str().unwrap();
+        let bytes = std::fs::read(path).unwrap();
+        let predictor: TestPredictor = match path.extension().unwrap().to_str().unwrap() {
+            "json" => Predictor::read_from_json(&mut &bytes[..]).unwrap(),
+            "ubj" => Predictor::read_from_ubjson(&mut &bytes[..]).unwrap(),
+            _ => Predictor::read_from(&mut &bytes[..]).unwrap(),
+        };
+        let data_file = if name.contains("pairwise") {
+            "mq2008.test"
+        } else if name.contains("sms-spam") {
+            "sms-spam.test"
+        } else if name.contains("categorical") {
+            "categorical.test"
+        } else {
+            "agaricus.txt.0.test"
+        };
+        let data = load_data(&format!("data/{}", data_file));
+
+        // the binary format has neither categorical splits nor vector leaves
+        let mut binary = vec![];
+        if name.contains("categorical") || name.contains("multi-target") {
+            assert!(predictor.write_to(&mut binary).is_err(), "{}", name);
+        } else {
+            predictor.write_to(&mut binary).unwrap();
+            let reloaded: TestPredictor = Predictor::read_from(&mut &binary[..]).unwrap();
+            assert_same_model(&predictor, &reloaded, &data);
+            // older models starting with `binf` are written without this header
+            if name.ends_with(".model") && !bytes.starts_with(b"binf") {
+                assert!(binary == bytes, "{} is not written back as is", name);
+            }
+        }
+
+        // trees of the JSON format have a single root
+        let mut json = vec![];
+        if predictor.num_roots() > 1 {
+            assert!(predictor.write_json(&mut json).is_err(), "{}", name);
+            continue;
+        }
+        predictor.write_json(&mut json).unwrap();
+        let reloaded: TestPredictor = Predictor::read_from_json(&mut &json[..]).unwrap();
+        assert_same_model(&predictor, &reloaded, &data);
+        let mut json_again = vec![];
+        reloaded.write_json(&mut json_again).unwrap();
+        assert!(json_again == json, "{} is not written back as is", name);
+    }
+
+    // edited models are saved with their changes
+    let mut predictor = load_model("gbtree", "v47/binary-logistic", "model");
+    let names: Vec<String> = (0..126).map(|i| format!("feature{}", i)).collect();
+    predictor.set_feature_names(names.clone()).unwrap();
+    predictor.set_base_score(1.5);
+    let mut json = vec![];
+    predictor.write_json(&mut json).unwrap();
+    let reloaded: TestPredictor = Predictor::read_from_json(&mut &json[..]).unwrap();
+    assert_eq!(reloaded.feature_names(), &names[..]);
+    assert_approx_eq!(reloaded.base_score(), 1.5f32, 1e-6f32);
+
+    // attributes follow the booster of binary models
+    let mut bytes = std::fs::read(get_resource("model/gbtree/v47/binary-logistic.model")).unwrap();
+    bytes[12..16].copy_from_slice(&1i32.to_le_bytes());
+    bytes.extend_from_slice(&2u64.to_le_bytes());
+    append_string(&mut bytes, "best_iteration");
+    append_string(&mut bytes, "1");
+    append_string(&mut bytes, "best_score");
+    append_string(&mut bytes, "0.04");
+    let predictor: TestPredictor = Predictor::read_from(&mut &bytes[..]).unwrap();
+    let mut binary = vec![];
+    predictor.write_to(&mut binary).unwrap();
+    assert!(binary == bytes);
+    let mut json = vec![];
+    predictor.write_json(&mut json).unwrap();
+    let reloaded: TestPredictor = Predictor::read_from_json(&mut &json[..]).unwrap();
+    assert_eq!(reloaded.attributes(), predictor.attributes());
+    assert_eq!(reloaded.best_iteration(), Some(1));
+
+    // JSON has no NaN
+    let mut json = vec![];
+    assert!(load_model_with_nan_leaf().write_json(&mut json).is_err());
+}
+
+#[test]
+fn test_slice_model() {
+    let cases = [
+        (
+            "gbtree",
+            "v47/binary-logistic",
+            "model",
+            "agaricus.txt.0.test",
+        ),
+        (
+            "gbtree",
+            "v47/multi-softprob",
+            "json",
+            "agaricus.txt.0.test",
+        ),
+        ("gbtree", "v200/multi-target", "json", "agaricus.txt.0.test"),
+        (
+            "gbtree",
+            "v200/reg-quantileerror",
+            "json",
+            "agaricus.txt.0.test",
+        ),
+        ("dart", "rank-pairwise", "model", "mq2008.test"),
+    ];
+    for (model_type, model_name, extension, data_file) in cases.iter() {
+        let predictor = load_model(model_type, model_name, extension);
+        let data = load_data(&format!("data/{}", data_file));
+        let num_rounds = predictor.num_boosted_rounds();
+        assert!(num_rounds >= 2);
+        let base_score = predictor.base_score();
+        let round_output = |map: &TestMap, round: usize| -> Vec<f32> {
+            predictor
+                .predict_with(
+                    map,
+                    &PredictOptions {
+                        output_margin: true,
+                        iteration_range: (round, round + 1),
+                        ..Default::default()
+                    },
+                )
+                .unwrap()
+                .iter()
+                .map(|x| x - base_score)
+                .collect()
+        };
+
+        let truncated: Vec<TestPredictor> = (1..=num_rounds)
+            .map(|k| predictor.truncate(k).unwrap())
+            .collect();
+        let sliced = predictor.slice(1, num_rounds, 1).unwrap();
+        let stepped = predictor.slice(0, 0, 2).unwrap();
+        assert_eq!(sliced.num_boosted_rounds(), num_rounds - 1);
+        assert_eq!(stepped.num_boosted_rounds(), num_rounds.div_ceil(2));
+        for (_, map) in data.iter().take(100) {
+            for (k, model) in truncated.iter().enumerate() {
+                assert_eq!(model.num_boosted_rounds(), k + 1);
+                let expected = predictor.predict(map, false, k + 1);
+                let predicted = model.predict(map, false, 0);
+                for (predicted, expected) in predicted.iter().zip(expected.iter()) {
+                    assert_approx_eq!(predicted, expected, 1e-5f32);
+                }
+            }
+
+            let expected = predictor
+                .predict_with(
+                    map,
+                    &PredictOptions {
+                        output_margin: true,
+                        iteration_range: (1, num_rounds),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            for (predicted, expected) in sliced.predict(map, true, 0).iter().zip(expected.iter()) {
+                assert_approx_eq!(predicted, expected, 1e-5f32);
+            }
+
+            let mut expected = vec![base_score; predictor.num_output_group()];
+            for round in (0..num_rounds).step_by(2) {
+                for (x, y) in expected.iter_mut().zip(round_output(map, round).iter()) {
+                    *x += y;
+                }
+            }
+            for (predicted, expected) in stepped.predict(map, true, 0).iter().zip(expected.iter()) {
+                assert_approx_eq!(predicted, expected, 1e-5f32);
+            }
+        }
+    }
+
+    // dropping the only tree of a round drops the round
+    let predictor = load_model("gbtree", "v47/binary-logistic", "model");
+    let dropped = predictor.drop_trees(&[1]).unwrap();
+    assert_eq!(dropped.num_trees(), predictor.num_trees() - 1);
+    assert_eq!(
+        dropped.num_boosted_rounds(),
+        predictor.num_boosted_rounds() - 1
+    );
+    let data = load_data("data/agaricus.txt.0.test");
+    for (_, map) in data.iter().take(100) {
+        let tree_output = predictor
+            .predict_with(
+                map,
+                &PredictOptions {
+                    output_margin: true,
+                    iteration_range: (1, 2),
+                    ..Default::default()
+                },
+            )
+            .unwrap()[0]
+            - predictor.base_score();
+        assert_approx_eq!(
+            dropped.predict(map, true, 0)[0],
+            predictor.predict(map, true, 0)[0] - tree_output,
+            1e-5f32
+        );
+    }
+    assert!(predictor.drop_trees(&[predictor.num_trees()]).is_err());
+
+    // output groups are kept in the given order
+    let predictor = load_model("gbtree", "v200/reg-quantileerror", "json");
+    let kept = predictor.keep_output_groups(&[2, 0]).unwrap();
+    assert_eq!(kept.num_output_group(), 2);
+    assert_eq!(kept.quantile_alphas(), Some(&[0.9f32, 0.1][..]));
+    assert_eq!(kept.num_boosted_rounds(), predictor.num_boosted_rounds());
+    for (_, map) in data.iter().take(100) {
+        let expected = predictor.predict(map, false, 0);
+        assert_eq!(kept.predict(map, false, 0), vec![expected[2], expected[0]]);
+    }
+    assert!(predictor.keep_output_groups(&[]).is_err());
+    assert!(predictor.keep_output_groups(&[3]).is_err());
+    assert!(predictor.keep_output_groups(&[1, 1]).is_err());
+    let multi_target = load_model("gbtree", "v200/multi-target", "json");
+    assert!(multi_target.keep_output_groups(&[0]).is_err());
+
+    let linear = load_model("gblinear", "v47/multi-softprob", "model");
+    let kept = linear.keep_output_groups(&[1]).unwrap();
+    for (_, map) in data.iter().take(100) {
+        assert_eq!(
+            kept.predict(map, true, 0),
+            vec![linear.predict(map, true, 0)[1]]
+        );
+    }
+    assert!(linear.slice(0, 1, 1).is_err());
+    assert!(linear.drop_trees(&[0]).is_err());
+
+    let predictor = load_model("gbtree", "v47/binary-logistic", "model");
+    match predictor.slice(2, 1, 1) {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidIterationRange(2, 1) => {}
+            _ => panic!("unexpected error: {}", e),
+        },
+        Ok(_) => panic!("slice past its end must fail"),
+    }
+    assert!(predictor.slice(0, 0, 0).is_err());
+    assert!(predictor.truncate(0).is_err());
+
+    // sliced models are saved and read back
+    let truncated = predictor.truncate(1).unwrap();
+    let mut binary = vec![];
+    truncated.write_to(&mut binary).unwrap();
+    let reloaded: TestPredictor = Predictor::read_from(&mut &binary[..]).unwrap();
+    assert_eq!(reloaded.num_trees(), 1);
+    for (_, map) in data.iter().take(100) {
+        assert_eq!(
+            reloaded.predict(map, false, 0),
+            predictor.predict(map, false, 1)
+        );
+    }
+}
+
+#[test]
+fn test_merge_models() {
+    let data = load_data("data/agaricus.txt.0.test");
+    let full = load_model("gbtree", "v47/binary-logistic", "model");
+    let mut first_round = full.truncate(1).unwrap();
+    first_round.set_base_score(0.25);
+    let from_json = load_model("gbtree", "v47/binary-logistic", "json");
+
+    let averaged = Predictor::average(&[&full, &first_round]).unwrap();
+    let merged = Predictor::merge(&[&full, &first_round, &from_json], &[2f32, -1f32, 0.5]).unwrap();
+    assert_eq!(averaged.num_trees(), full.num_trees() + 1);
+    assert_eq!(averaged.num_boosted_rounds(), full.num_boosted_rounds() + 1);
+    assert_eq!(averaged.booster(), "gbtree");
+    assert_approx_eq!(averaged.base_score(), 0.125f32, 1e-6f32);
+    for (_, map) in data.iter() {
+        let margins = [
+            full.predict(map, true, 0)[0],
+            first_round.predict(map, true, 0)[0],
+            from_json.predict(map, true, 0)[0],
+        ];
+        assert_approx_eq!(
+            averaged.predict(map, true, 0)[0],
+            (margins[0] + margins[1]) / 2f32,
+            1e-5f32
+        );
+        assert_approx_eq!(
+            merged.predict(map, true, 0)[0],
+            2f32 * margins[0] - margins[1] + 0.5 * margins[2],
+            1e-5f32
+        );
+        // the rounds of the first model come first
+        assert_approx_eq!(
+            averaged.predict(map, true, full.num_boosted_rounds())[0],
+            margins[0] / 2f32 + first_round.base_score() / 2f32,
+            1e-5f32
+        );
+    }
+
+    // DART weights are kept, trees of other models have a weight of 1
+    let dart = load_model("dart", "rank-pairwise", "model");
+    let ranking = load_model("gbtree", "v47/rank-pairwise", "model");
+    let mixed = Predictor::average(&[&ranking, &dart]).unwrap();
+    assert_eq!(mixed.booster(), "dart");
+    let averaged = Predictor::average(&[&dart, &dart.slice(0, 0, 2).unwrap()]).unwrap();
+    assert_eq!(averaged.booster(), "dart");
+    let mq2008 = load_data("data/mq2008.test");
+    for (_, map) in mq2008.iter().take(100) {
+        assert_approx_eq!(
+            mixed.predict(map, true, 0)[0],
+            (ranking.predict(map, true, 0)[0] + dart.predict(map, true, 0)[0]) / 2f32,
+            1e-5f32
+        );
+        assert_approx_eq!(
+            averaged.predict(map, true, 0)[0],
+            (dart.predict(map, true, 0)[0] + dart.slice(0, 0, 2).unwrap().predict(map, true, 0)[0])
+                / 2f32,
+            1e-5f32
+        );
+    }
+
+    let multi = load_model("gbtree", "v47/multi-softprob", "model");
+    let averaged = Predictor::average(&[&multi, &multi]).unwrap();
+    for (_, map) in data.iter().take(100) {
+        let expected = multi.predict(map, false, 0);
+        for (x, y) in averaged.predict(map, false, 0).iter().zip(expected.iter()) {
+            assert_approx_eq!(x, y, 1e-5f32);
+        }
+    }
+
+    let logitraw = load_model("gbtree", "v47/binary-logitraw", "model");
+    let linear = load_model("gblinear", "v47/binary-logistic", "model");
+    assert!(Predictor::average(&[&full, &logitraw]).is_err());
+    assert!(Predictor::average(&[&full, &linear]).is_err());
+    assert!(Predictor::average(&[&full, &multi]).is_err());
+    assert!(Predictor::merge(&[&full, &full], &[1f32]).is_err());
+    assert!(Predictor::<TestMap>::average(&[]).is_err());
+}
+
+fn to_compact(predictor: &TestPredictor) -> CompactModel<Vec<u8>> {
+    let mut bytes = vec![];
+    predictor.write_compact(&mut bytes).unwrap();
+    CompactModel::from_bytes(bytes).unwrap()
+}
+
+#[test]
+fn test_compact_model() {
+    let models = [
+        (
+            "gbtree",
+            "v47/binary-logistic",
+            "model",
+            "agaricus.txt.0.test",
+        ),
+        (
+            "gbtree",
+            "v47/binary-logitraw",
+            "model",
+            "agaricus.txt.0.test",
+        ),
+        (
+            "gbtree",
+            "v47/multi-softprob",
+            "json",
+            "agaricus.txt.0.test",
+        ),
+        (
+            "gbtree",
+            "v40/multi-softmax",
+            "model",
+            "agaricus.txt.0.test",
+        ),
+        (
+            "gbtree",
+            "v200/reg-quantileerror",
+            "ubj",
+            "agaricus.txt.0.test",
+        ),
+        ("gbtree", "v47/rank-pairwise", "model", "mq2008.test"),
+        ("dart", "rank-pairwise", "model", "mq2008.test"),
+    ];
+    for (model_type, model_name, extension, data_name) in models.iter() {
+        let predictor = load_model(model_type, model_name, extension);
+        let compact = to_compact(&predictor);
+        assert_eq!(compact.num_feature(), predictor.num_feature());
+        assert_eq!(compact.num_output_group(), predictor.num_output_group());
+        assert_eq!(compact.num_trees(), predictor.num_trees());
+        assert_eq!(compact.num_boosted_rounds(), predictor.num_boosted_rounds());
+        assert_eq!(compact.objective(), predictor.objective());
+        assert_eq!(compact.base_score(), predictor.base_score());
+        for (_, map) in load_data(&format!("data/{}", data_name)).iter().take(200) {
+            for output_margin in [false, true].iter() {
+                assert_eq!(
+                    compact.predict(map, *output_margin, 0),
+                    predictor.predict(map, *output_margin, 0)
+                );
+            }
+            assert_eq!(
+                compact.predict(map, false, 2),
+                predictor.predict(map, false, 2)
+            );
+            assert_eq!(compact.predict_leaf(map, 0), predictor.predict_leaf(map, 0));
+        }
+    }
+
+    let unsupported = [
+        ("gblinear", "v47/binary-logistic", "model"),
+        ("gbtree", "v200/categorical", "json"),
+        ("gbtree", "v200/multi-target", "json"),
+        ("gbtree", "v40/multi-root", "model"),
+    ];
+    for (model_type, model_name, extension) in unsupported.iter() {
+        let predictor = load_model(model_type, model_name, extension);
+        assert!(predictor.write_compact(&mut vec![]).is_err());
+    }
+
+    // truncated or corrupted bytes are rejected up front
+    let mut bytes = vec![];
+    load_model("gbtree", "v47/binary-logistic", "model")
+        .write_compact(&mut bytes)
+        .unwrap();
+    assert!(CompactModel::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(CompactModel::from_bytes(&bytes[..8]).is_err());
+    let mut bad_magic = bytes.clone();
+    bad_magic[0] = b'Y';
+    assert!(CompactModel::from_bytes(bad_magic).is_err());
+    // point the left child of the root of the first tree back to itself,
+    // nodes are 16 bytes each and close the file
+    let num_nodes = u32::from_le_bytes([bytes[28], bytes[29], bytes[30], bytes[31]]) as usize;
+    let root = bytes.len() - 16 * num_nodes;
+    let mut cycle = bytes.clone();
+    cycle[root..root + 4].copy_from_slice(&0i32.to_le_bytes());
+    assert!(CompactModel::from_bytes(cycle).is_err());
+}
+
+#[test]
+fn test_compact_model_obj_function() {
+    let mut model = String::new();
+    open_resource_file("model/gbtree/v47/binary-logistic.json")
+        .unwrap()
+        .read_to_string(&mut model)
+        .unwrap();
+    let model = model.replace("\"binary:logistic\"", "\"custom:sigmoid\"");
+    let predictor = TestPredictor::read_from_json_with_obj_function(
+        &mut model.as_bytes(),
+        Some(Arc::new(CustomSigmoid)),
+    )
+    .unwrap();
+    let mut bytes = Vec::new();
+    predictor.write_compact(&mut bytes).unwrap();
+
+    // the compact model only holds the name of the objective
+    assert!(CompactModel::from_bytes(&bytes).is_err());
+    let compact =
+        CompactModel::from_bytes_with_obj_function(&bytes, Some(Arc::new(CustomSigmoid))).unwrap();
+    assert_eq!(compact.objective(), "custom:sigmoid");
+    for (_, map) in load_data("data/agaricus.txt.0.test").iter().take(200) {
+        assert_eq!(
+            compact.predict(map, false, 0),
+            predictor.predict(map, false, 0)
+        );
+    }
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_compact_model_mmap() {
+    let predictor = load_model("gbtree", "v47/multi-softprob", "model");
+    let path = std::env::temp_dir().join(format!("compact-{}.xgbc", std::process::id()));
+    predictor
+        .write_compact(&mut File::create(&path).unwrap())
+        .unwrap();
+    let compact = CompactModel::open(&path).unwrap();
+    for (_, map) in load_data("data/agaricus.txt.0.test").iter().take(200) {
+        assert_eq!(
+            compact.predict(map, false, 0),
+            predictor.predict(map, false, 0)
+        );
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_buffered_read() {
+    let data = load_data("data/sms-spam.test");
+    let expected = load_model("gbtree", "v47/sms-spam", "model");
+    let mut bytes = Vec::new();
+    open_resource_file("model/gbtree/v47/sms-spam.model")
+        .unwrap()
+        .read_to_end(&mut bytes)
+        .unwrap();
+
+    let from_slice: TestPredictor = Predictor::read_from_buffered(&mut bytes.as_slice()).unwrap();
+    // a small buffer splits nodes between fills
+    let mut small_buffer = BufReader::with_capacity(50, bytes.as_slice());
+    let from_small_buffer: TestPredictor =
+        Predictor::read_from_buffered(&mut small_buffer).unwrap();
+    let from_reader: TestPredictor = Predictor::read_from(&mut bytes.as_slice()).unwrap();
+    for predictor in [from_slice, from_small_buffer, from_reader].iter() {
+        assert_eq!(predictor.num_trees(), expected.num_trees());
+        for (_, map) in data.iter().take(200) {
+            assert_eq!(
+                predictor.predict(map, true, 0),
+                expected.predict(map, true, 0)
+            );
+            assert_eq!(
+                predictor.predict_leaf(map, 0),
+                expected.predict_leaf(map, 0)
+            );
+        }
+    }
+
+    // reading stops at the end of the model
+    let mut with_trailing_bytes = bytes.clone();
+    with_trailing_bytes.extend_from_slice(b"trailing");
+    let mut reader = with_trailing_bytes.as_slice();
+    let _: TestPredictor = Predictor::read_from_buffered(&mut reader).unwrap();
+    assert_eq!(reader, b"trailing");
+    let mut reader = with_trailing_bytes.as_slice();
+    let _: TestPredictor = Predictor::read_from(&mut reader).unwrap();
+    assert_eq!(reader, b"trailing");
+
+    // as do the evaluation metrics saved after the attributes since 0.6
+    let mut with_metrics = Vec::new();
+    expected.write_to(&mut with_metrics).unwrap();
+    with_metrics[16..20].copy_from_slice(&1i32.to_le_bytes());
+    with_metrics.extend_from_slice(&2i64.to_le_bytes());
+    for metric in [&b"auc"[..], &b"logloss"[..]].iter() {
+        with_metrics.extend_from_slice(&(metric.len() as i64).to_le_bytes());
+        with_metrics.extend_from_slice(metric);
+    }
+    with_metrics.extend_from_slice(b"trailing");
+    let mut reader = with_metrics.as_slice();
+    let _: TestPredictor = Predictor::read_from_buffered(&mut reader).unwrap();
+    assert_eq!(reader, b"trailing");
+    let mut reader = with_metrics.as_slice();
+    let _: TestPredictor = Predictor::read_from(&mut reader).unwrap();
+    assert_eq!(reader, b"trailing");
+
+    for size in [8, 200, bytes.len() / 2, bytes.len() - 1].iter() {
+        assert!(TestPredictor::read_from(&mut &bytes[..*size]).is_err());
+        assert!(TestPredictor::read_from_buffered(&mut &bytes[..*size]).is_err());
+    }
+}
+
+#[test]
+fn test_load_any_format() {
+    let data = load_data("data/agaricus.txt.0.test");
+    for (model_name, extension, format) in [
+        ("gbtree/v47/binary-logistic", "model", ModelFormat::Binary),
+        ("gbtree/v40/binary-logistic", "model", ModelFormat::Binary),
+        ("gbtree/v47/multi-softprob", "json", ModelFormat::Json),
+        ("gbtree/v47/multi-softprob", "ubj", ModelFormat::Ubjson),
+        ("gblinear/v47/binary-logistic", "model", ModelFormat::Binary),
+    ]
+    .iter()
+    {
+        let rel_path = format!("model/{}.{}", model_name, extension);
+        let bytes = std::fs::read(get_resource(&rel_path)).unwrap();
+        assert_eq!(ModelFormat::detect(&bytes), *format, "{}", rel_path);
+        assert_eq!(Compression::detect(&bytes), Compression::None);
+        let expected = load_model(
+            model_name.split('/').next().unwrap(),
+            &model_name[model_name.find('/').unwrap() + 1..],
+            extension,
+        );
+        let from_path: TestPredictor = Predictor::from_path(get_resource(&rel_path)).unwrap();
+        assert_same_model(&expected, &from_path, &data);
+        let from_bytes: TestPredictor = Predictor::from_bytes(&bytes).unwrap();
+        assert_same_model(&expected, &from_bytes, &data);
+    }
+    assert_eq!(
+        ModelFormat::detect(b"\n  {\"learner\": {}}"),
+        ModelFormat::Json
+    );
+    assert_eq!(ModelFormat::detect(b"{ \n\"learner\""), ModelFormat::Json);
+    assert_eq!(ModelFormat::detect(b"{\x00\x00?"), ModelFormat::Binary);
+    assert_eq!(ModelFormat::detect(b" {i"), ModelFormat::Binary);
+
+    // a binary model whose base_score starts like JSON
+    let mut bytes = std::fs::read(get_resource("model/gbtree/v47/binary-logistic.model")).unwrap();
+    bytes[0] = b'{';
+    bytes[1] = b'"';
+    assert_eq!(ModelFormat::detect(&bytes), ModelFormat::Json);
+    let read: TestPredictor = Predictor::from_bytes(&bytes).unwrap();
+    assert_eq!(read.num_trees(), 3);
+    assert!(TestPredictor::from_bytes(b"{\"learner\": 1}").is_err());
+    assert!(TestPredictor::from_path(get_resource("model/missing.model")).is_err());
+    assert!(TestPredictor::from_bytes(b"").is_err());
+
+    #[cfg(not(feature = "gzip"))]
+    assert!(TestPredictor::from_bytes(&[0x1f, 0x8b, 8, 0]).is_err());
+    #[cfg(not(feature = "zstd"))]
+    assert!(TestPredictor::from_bytes(&[0x28, 0xb5, 0x2f, 0xfd]).is_err());
+}
+
+fn append_utf(bytes: &mut Vec<u8>, s: &str) {
+    bytes.extend_from_slice(&(s.len() as u16).to_be_bytes());
+    bytes.extend_from_slice(s.as_bytes());
+}
+
+#[test]
+fn test_spark_model() {
+    let data = load_data("data/agaricus.txt.0.test");
+    let expected = load_model("gbtree", "v47/binary-logistic", "model");
+    let model = std::fs::read(get_resource("model/gbtree/v47/binary-logistic.model")).unwrap();
+
+    // xgboost4j-spark writes its parameters before the model
+    let mut classifier = vec![];
+    append_utf(&mut classifier, "_cls_");
+    append_utf(&mut classifier, "features");
+    append_utf(&mut classifier, "label");
+    append_utf(&mut classifier, "prediction");
+    append_utf(&mut classifier, "rawPrediction");
+    classifier.extend_from_slice(&2i32.to_be_bytes());
+    classifier.extend_from_slice(&0.25f64.to_be_bytes());
+    classifier.extend_from_slice(&0.75f64.to_be_bytes());
+    classifier.extend_from_slice(&model);
+    let mut regressor = vec![];
+    append_utf(&mut regressor, "_reg_");
+    append_utf(&mut regressor, &"f".repeat(300));
+    append_utf(&mut regressor, "y");
+    append_utf(&mut regressor, "y_hat");
+    regressor.extend_from_slice(&model);
+
+    let read: TestPredictor = Predictor::read_from(&mut classifier.as_slice()).unwrap();
+    assert_same_model(&expected, &read, &data);
+    let param = read.spark_model_param().unwrap();
+    assert_eq!(param.model_type, "_cls_");
+    assert_eq!(param.features_col, "features");
+    assert_eq!(param.label_col, "label");
+    assert_eq!(param.prediction_col, "prediction");
+    assert_eq!(param.raw_prediction_col.as_deref(), Some("rawPrediction"));
+    assert_eq!(param.thresholds, Some(vec![0.25, 0.75]));
+
+    // corrupted numbers of thresholds fail without allocating them
+    for num_thresholds in [-1i32, i32::MAX].iter() {
+        let mut corrupted = vec![];
+        append_utf(&mut corrupted, "_cls_");
+        append_utf(&mut corrupted, "features");
+        append_utf(&mut corrupted, "label");
+        append_utf(&mut corrupted, "prediction");
+        append_utf(&mut corrupted, "rawPrediction");
+        corrupted.extend_from_slice(&num_thresholds.to_be_bytes());
+        corrupted.extend_from_slice(&model);
+        assert!(TestPredictor::read_from(&mut corrupted.as_slice()).is_err());
+    }
+
+    assert_eq!(ModelFormat::detect(&regressor), ModelFormat::Binary);
+    let read: TestPredictor = Predictor::from_bytes(&regressor).unwrap();
+    assert_same_model(&expected, &read, &data);
+    let param = read.spark_model_param().unwrap();
+    assert_eq!(param.model_type, "_reg_");
+    assert_eq!(param.features_col.len(), 300);
+    assert_eq!(param.prediction_col, "y_hat");
+    assert_eq!(param.raw_prediction_col, None);
+    assert_eq!(param.thresholds, None);
+    assert!(expected.spark_model_param().is_none());
+}
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+#[test]
+fn test_compressed_model() {
+    use std::io::Write;
+    let data = load_data("data/agaricus.txt.0.test");
+    let expected = load_model("gbtree", "v47/multi-softprob", "json");
+    let json = std::fs::read(get_resource("model/gbtree/v47/multi-softprob.json")).unwrap();
+    let binary = std::fs::read(get_resource("model/gbtree/v47/multi-softprob.model")).unwrap();
+    for bytes in [json, binary].iter() {
+        let mut compressed = vec![];
+        #[cfg(feature = "gzip")]
+        {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(bytes).unwrap();
+            compressed.push((Compression::Gzip, encoder.finish().unwrap()));
+        }
+        #[cfg(feature = "zstd")]
+        compressed.push((
+            Compression::Zstd,
+            zstd::stream::encode_all(&bytes[..], 0).unwrap(),
+        ));
+        for (compression, bytes) in compressed.iter() {
+            assert_eq!(Compression::detect(bytes), *compression);
+            let read: TestPredictor = Predictor::from_bytes(bytes).unwrap();
+            assert_same_model(&expected, &read, &data);
+            assert!(TestPredictor::from_bytes(&bytes[..bytes.len() / 2]).is_err());
+        }
+    }
+}
+
+#[test]
+fn test_predictor_handle() {
+    let data = load_data("data/agaricus.txt.0.test");
+    let rows: Vec<TestMap> = data.iter().take(20).map(|(_, map)| map.clone()).collect();
+    let full = load_model("gbtree", "v47/binary-logistic", "model");
+    let expected: Vec<Vec<f32>> = rows.iter().map(|row| full.predict(row, false, 0)).collect();
+    let canary = Canary::new(rows.clone(), expected.clone(), 1e-6).unwrap();
+    let handle = PredictorHandle::new(load_model("gbtree", "v47/binary-logistic", "json"))
+        .with_canary(canary)
+        .unwrap();
+
+    // a model changing the predictions of the canary is not published
+    let in_flight = handle.get();
+    match handle.replace(full.truncate(1).unwrap()) {
+        Err(e) => match e.kind() {
+            ErrorKind::CanaryMismatch(0, _, _) => {}
+            _ => panic!("unexpected error: {}", e),
+        },
+        Ok(_) => panic!("canary must be checked"),
+    }
+    for incompatible in [
+        load_model("gbtree", "v47/multi-softprob", "model"),
+        load_model("gbtree", "v47/binary-logitraw", "model"),
+    ] {
+        match handle.replace(incompatible) {
+            Err(e) => match e.kind() {
+                ErrorKind::IncompatibleModel(_) => {}
+                _ => panic!("unexpected error: {}", e),
+            },
+            Ok(_) => panic!("model must be validated"),
+        }
+    }
+    // as is a model panicking on the canary, which leaves the handle usable
+    let panicking = TestPredictor::read_from_with_obj_function(
+        &mut open_resource_file("model/gbtree/v47/binary-logistic.model").unwrap(),
+        Some(Arc::new(PanickingObjFunction)),
+    )
+    .unwrap();
+    match handle.replace(panicking) {
+        Err(e) => match e.kind() {
+            ErrorKind::IncompatibleModel(_) => {}
+            _ => panic!("unexpected error: {}", e),
+        },
+        Ok(_) => panic!("canary must be checked"),
+    }
+    assert!(Arc::ptr_eq(&in_flight, &handle.get()));
+
+    // predictions running on other threads keep the model they started with
+    let previous = handle.replace(full).unwrap();
+    assert!(Arc::ptr_eq(&in_flight, &previous));
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                let predictor = handle.get();
+                for (row, expected) in rows.iter().zip(expected.iter()) {
+                    assert_eq!(&predictor.predict(row, false, 0), expected);
+                }
+            });
+        }
+        scope.spawn(|| {
+            let mut bytes = vec![];
+            handle.get().write_to(&mut bytes).unwrap();
+            for _ in 0..10 {
+                handle
+                    .replace(Predictor::from_bytes(&bytes).unwrap())
+                    .unwrap();
+            }
+        });
+    });
+    assert!(handle.reload().is_err());
+    assert!(Canary::new(rows, vec![], 0f32).is_err());
+}
+
+#[test]
+fn test_predictor_handle_reload() {
+    let path = std::env::temp_dir().join(format!("handle-{}.model", std::process::id()));
+    let model = std::fs::read(get_resource("model/gbtree/v47/binary-logistic.model")).unwrap();
+    std::fs::write(&path, &model).unwrap();
+    let handle: PredictorHandle<TestMap> = PredictorHandle::from_path(&path).unwrap();
+    assert!(!handle.reload_if_modified().unwrap());
+    let rounds = handle.get().num_boosted_rounds();
+
+    let set_modified = |seconds: u64| {
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(seconds))
+            .unwrap();
+    };
+    let mut truncated = vec![];
+    handle
+        .get()
+        .truncate(1)
+        .unwrap()
+        .write_to(&mut truncated)
+        .unwrap();
+    std::fs::write(&path, &truncated).unwrap();
+    set_modified(1_000_000);
+    assert!(handle.reload_if_modified().unwrap());
+    assert_eq!(handle.get().num_boosted_rounds(), 1);
+    assert!(!handle.reload_if_modified().unwrap());
+
+    // a broken file keeps the current model, and is tried again once fixed
+    std::fs::write(&path, &model[..100]).unwrap();
+    set_modified(2_000_000);
+    assert!(handle.reload_if_modified().is_err());
+    assert!(handle.reload().is_err());
+    assert_eq!(handle.get().num_boosted_rounds(), 1);
+    std::fs::write(&path, &model).unwrap();
+    set_modified(2_000_000);
+    assert!(handle.reload_if_modified().unwrap());
+    assert_eq!(handle.get().num_boosted_rounds(), rounds);
+
+    std::fs::write(&path, &truncated).unwrap();
+    handle.reload().unwrap();
+    assert_eq!(handle.get().num_boosted_rounds(), 1);
+    std::fs::remove_file(&path).unwrap();
+    assert!(handle.reload().is_err());
+}
+
+#[test]
+fn test_fingerprint_and_version() {
+    let same_models = [
+        vec![
+            "gbtree/v47/binary-logistic.model",
+            "gbtree/v47/binary-logistic.json",
+            "gbtree/v47/binary-logistic.ubj",
+        ],
+        vec![
+            "gbtree/v47/multi-softprob.model",
+            "gbtree/v47/multi-softprob.json",
+            "gbtree/v47/multi-softprob.ubj",
+        ],
+        vec!["dart/rank-pairwise.model", "dart/rank-pairwise.json"],
+        vec![
+            "gbtree/v200/categorical.json",
+            "gbtree/v200/categorical.ubj",
+        ],
+        vec![
+            "gbtree/v200/multi-target.json",
+            "gbtree/v200/multi-target.ubj",
+        ],
+        vec![
+            "gblinear/v47/multi-softprob.model",
+            "gblinear/v47/multi-softprob.json",
+        ],
+    ];
+    let mut fingerprints = vec![];
+    for paths in same_models.iter() {
+        let predictors: Vec<TestPredictor> = paths
+            .iter()
+            .map(|path| Predictor::from_path(get_resource(&format!("model/{}", path))).unwrap())
+            .collect();
+        let fingerprint = predictors[0].fingerprint();
+        for (path, predictor) in paths.iter().zip(predictors.iter()) {
+            assert_eq!(predictor.fingerprint(), fingerprint, "{}", path);
+        }
+        fingerprints.push(fingerprint);
+
+        // attributes and feature names are not part of the fingerprint, weights are
+        let mut predictor = predictors.into_iter().last().unwrap();
+        let mut json = vec![];
+        predictor.write_json(&mut json).unwrap();
+        let mut reloaded: TestPredictor = Predictor::from_bytes(&json).unwrap();
+        let names = (0..reloaded.num_feature())
+            .map(|i| format!("f{}", i))
+            .collect();
+        reloaded.set_feature_names(names).unwrap();
+        assert_eq!(reloaded.fingerprint(), fingerprint);
+        predictor.set_base_score(predictor.base_score() + 1f32);
+        assert_ne!(predictor.fingerprint(), fingerprint);
+    }
+    let full = load_model("gbtree", "v47/binary-logistic", "model");
+    fingerprints.push(load_model("gbtree", "v47/binary-logitraw", "model").fingerprint());
+    fingerprints.push(full.truncate(1).unwrap().fingerprint());
+    fingerprints.push(full.drop_trees(&[0]).unwrap().fingerprint());
+    fingerprints.push(Predictor::average(&[&full, &full]).unwrap().fingerprint());
+    let mut unique = fingerprints.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    assert_eq!(unique.len(), fingerprints.len());
+    // the hash itself must not change between releases
+    assert_eq!(full.fingerprint(), 0x5464_4e54_b18a_cb0c);
+
+    assert_eq!(full.version(), ModelVersion::Legacy);
+    assert_eq!(full.version().to_string(), "legacy");
+    let v40 = load_model("gbtree", "v40/binary-logistic", "model");
+    assert_eq!(v40.version(), ModelVersion::Binf);
+    let json = load_model("gbtree", "v47/binary-logistic", "ubj");
+    assert_eq!(json.version(), ModelVersion::Json(2, 0, 0));
+    assert_eq!(json.version().to_string(), "2.0.0");
+    // xgboost 1.x saves its version after `contain_eval_metrics`
+    let mut bytes = std::fs::read(get_resource("model/gbtree/v47/binary-logistic.model")).unwrap();
+    bytes[20..24].copy_from_slice(&1i32.to_le_bytes());
+    bytes[24..28].copy_from_slice(&3i32.to_le_bytes());
+    let v1: TestPredictor = Predictor::from_bytes(&bytes).unwrap();
+    assert_eq!(v1.version(), ModelVersion::Binary(1, 3));
+    assert_eq!(v1.version().to_string(), "1.3");
+    assert_eq!(v1.fingerprint(), full.fingerprint());
+}
+
+/// Expected output of the subtree of `node` knowing only the features of `known`
+fn conditional_expectation(tree: &Tree, nid: usize, map: &TestMap, known: &[usize]) -> f32 {
+    let node = tree.node(nid).unwrap();
+    if node.is_leaf() {
+        return node.leaf_value().unwrap();
+    }
+    let (left, right) = (node.left_child().unwrap(), node.right_child().unwrap());
+    let split_index = node.split_index().unwrap();
+    if known.contains(&split_index) {
+        let next = match map.get(&split_index) {
+            None => node.default_child().unwrap(),
+            Some(fvalue) if *fvalue < node.split_condition().unwrap() => left,
+            Some(_) => right,
+        };
+        return conditional_expectation(tree, next, map, known);
+    }
+    let cover = |nid: usize| tree.node(nid).unwrap().sum_hessian();
+    (conditional_expectation(tree, left, map, known) * cover(left)
+        + conditional_expectation(tree, right, map, known) * cover(right))
+        / cover(nid)
+}
+
+/// Shapley values of the features split on by `tree`, computed over all their subsets
+fn brute_force_shap(tree: &Tree, map: &TestMap) -> HashMap<usize, f32> {
+    let mut features: Vec<usize> = tree
+        .nodes()
+        .iter()
+        .filter_map(|n| n.split_index())
+        .collect();
+    features.sort_unstable();
+    features.dedup();
+    let m = features.len();
+    let factorial = |n: usize| (1..=n).map(|i| i as f64).product::<f64>();
+    let mut phi = HashMap::new();
+    for feature in features.iter() {
+        let others: Vec<usize> = features.iter().copied().filter(|f| f != feature).collect();
+        let mut value = 0f64;
+        for subset in 0..(1usize << others.len()) {
+            let mut known: Vec<usize> = (0..others.len())
+                .filter(|j| subset & (1 << j) != 0)
+                .map(|j| others[j])
+                .collect();
+            let size = known.len();
+            let without = conditional_expectation(tree, 0, map, &known) as f64;
+            known.push(*feature);
+            let with = conditional_expectation(tree, 0, map, &known) as f64;
+            value += factorial(size) * factorial(m - size - 1) / factorial(m) * (with - without);
+        }
+        phi.insert(*feature, value as f32);
+    }
+    phi
+}
+
+#[test]
+fn test_predict_contributions() {
+    let data = load_data("data/agaricus.txt.0.test");
+    // contributions sum up to the margin
+    for (model_type, model_name, extension, data) in [
+        ("gbtree", "v47/binary-logistic", "model", &data),
+        ("gbtree", "v47/multi-softprob", "json", &data),
+        ("gbtree", "v200/reg-quantileerror", "json", &data),
+        (
+            "gbtree",
+            "v200/categorical",
+            "json",
+            &load_data("data/categorical.test"),
+        ),
+        (
+            "dart",
+            "rank-pairwise",
+            "model",
+            &load_data("data/mq2008.test"),
+        ),
+        ("gblinear", "v47/multi-softprob", "model", &data),
+    ]
+    .iter()
+    {
+        let predictor = load_model(model_type, model_name, extension);
+        let num_columns = predictor.num_feature() + 1;
+        for (_, map) in data.iter().take(50) {
+            for ntree_limit in [0, 1].iter() {
+                let contribs = predictor.predict_contributions(map, *ntree_limit).unwrap();
+                let margins = predictor.predict(map, true, *ntree_limit);
+                assert_eq!(contribs.len(), margins.len() * num_columns);
+                for (group, margin) in contribs.chunks(num_columns).zip(margins.iter()) {
+                    assert_approx_eq!(group.iter().sum::<f32>(), margin, 1e-4f32);
+                }
+            }
+        }
+    }
+
+    // each tree matches the Shapley values computed from their definition
+    let predictor = load_model("gbtree", "v47/binary-logistic", "model");
+    for tree_index in 0..predictor.num_trees() {
+        let tree = predictor.tree(tree_index).unwrap();
+        let others: Vec<usize> = (0..predictor.num_trees())
+            .filter(|i| *i != tree_index)
+            .collect();
+        let single = predictor.drop_trees(&others).unwrap();
+        for (_, map) in data.iter().take(30) {
+            let contribs = single.predict_contributions(map, 0).unwrap();
+            let expected = brute_force_shap(&tree, map);
+            for (feature, contrib) in contribs[..predictor.num_feature()].iter().enumerate() {
+                let expected = expected.get(&feature).copied().unwrap_or(0f32);
+                assert_approx_eq!(contrib, expected, 1e-4f32);
+            }
+        }
+    }
+
+    let linear = load_model("gblinear", "v47/binary-logistic", "model");
+    let (_, map) = data.iter().next().unwrap();
+    let weights = linear.linear_weights().unwrap();
+    let contribs = linear.predict_contributions(map, 0).unwrap();
+    for (fid, fvalue) in map.iter() {
+        assert_eq!(contribs[*fid], fvalue * weights[*fid]);
+    }
+    assert!(linear.try_predict_leaf(map, 0).is_err());
+    let multi_target = load_model("gbtree", "v200/multi-target", "json");
+    assert!(multi_target.predict_contributions(map, 0).is_err());
+}
+
+fn run_cli(binary: &str, args: &[&str], stdin: &str) -> std::process::Output {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+    let mut child = Command::new(binary)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn parse_cli_output(output: &std::process::Output) -> Vec<Vec<f32>> {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout.clone())
+        .unwrap()
+        .lines()
+        .map(|line| {
+            line.trim_start_matches('[')
+                .trim_end_matches(']')
+                .split(',')
+                .map(|v| v.parse::<f32>().unwrap())
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn test_predict_cli() {
+    let data = load_data("data/agaricus.txt.0.test");
+    let model_path = get_resource("model/gbtree/v47/multi-softprob.model");
+    let model_path = model_path.to_str().unwrap();
+    let data_path = get_resource("data/agaricus.txt.0.test");
+    let data_path = data_path.to_str().unwrap();
+    let predictor = load_model("gbtree", "v47/multi-softprob", "model");
+
+    // libsvm rows from a file, on several threads
+    for (predict, output_format) in [
+        ("value", "csv"),
+        ("margin", "json"),
+        ("leaf", "csv"),
+        ("contrib", "json"),
+    ] {
+        let output = run_cli(
+            env!("CARGO_BIN_EXE_xgboost-predict"),
+            &[
+                "--predict",
+                predict,
+                "--output-format",
+                output_format,
+                "--ntree-limit=2",
+                "--threads",
+                "3",
+                model_path,
+                data_path,
+            ],
+            "",
+        );
+        let rows = parse_cli_output(&output);
+        assert_eq!(rows.len(), data.len());
+        for (row, (_, map)) in rows.iter().zip(data.iter()) {
+            let expected = match predict {
+                "value" => predictor.predict(map, false, 2),
+                "margin" => predictor.predict(map, true, 2),
+                "leaf" => predictor
+                    .predict_leaf(map, 2)
+                    .iter()
+                    .map(|&leaf| leaf as f32)
+                    .collect(),
+                _ => predictor.predict_contributions(map, 2).unwrap(),
+            };
+            assert_eq!(row, &expected);
+        }
+    }
+
+    // CSV rows from stdin, with a header, a label column and missing values
+    let num_feature = predictor.num_feature();
+    let mut csv = String::from("header\n");
+    for (label, map) in data.iter().take(20) {
+        let mut cells = vec![String::from("-1"); num_feature];
+        for (index, value) in map.iter() {
+            cells[*index] = value.to_string();
+        }
+        cells[0] = String::new();
+        cells.insert(1, label.to_string());
+        csv.push_str(&cells.join(","));
+        csv.push('\n');
+    }
+    let output = run_cli(
+        env!("CARGO_BIN_EXE_xgboost-predict"),
+        &[
+            "--input-format",
+            "csv",
+            "--csv-header",
+            "--label-column",
+            "1",
+            "--missing",
+            "-1",
+            model_path,
+            "-",
+        ],
+        &csv,
+    );
+    let rows = parse_cli_output(&output);
+    assert_eq!(rows.len(), 20);
+    for (row, (_, map)) in rows.iter().zip(data.iter()) {
+        let mut map = map.clone();
+        map.remove(&0);
+        assert_eq!(row, &predictor.predict(&map, false, 0));
+    }
+
+    // errors are reported with the line they come from
+    let output = run_cli(
+        env!("CARGO_BIN_EXE_xgboost-predict"),
+        &[model_path],
+        "0 1:1\n1 2:x\n",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("line 2"));
+    // linear models have no leaves
+    let linear_path = get_resource("model/gblinear/v47/binary-logistic.model");
+    let output = run_cli(
+        env!("CARGO_BIN_EXE_xgboost-predict"),
+        &["--predict", "leaf", linear_path.to_str().unwrap()],
+        "0 1:1\n",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("predict leaf index"));
+    let output = run_cli(
+        env!("CARGO_BIN_EXE_xgboost-predict"),
+        &["--predict", "probability", model_path],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(2));
+}
+
+fn cli_stdout(output: std::process::Output) -> String {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_model_cli() {
+    let binary = env!("CARGO_BIN_EXE_xgboost-model");
+    let model_path = get_resource("model/gbtree/v47/binary-logistic.model");
+    let model_path = model_path.to_str().unwrap();
+    let predictor = load_model("gbtree", "v47/binary-logistic", "model");
+    let summary = predictor.summary();
+
+    let output = cli_stdout(run_cli(binary, &["summary", model_path], ""));
+    let fingerprint = format!("fingerprint: {:016x}", predictor.fingerprint());
+    let trees = format!("trees: {}", summary.num_trees);
+    let used = format!(
+        "features used: {} of {}:",
+        summary.used_features.len(),
+        summary.num_feature
+    );
+    for expected in [
+        "format: binary",
+        "objective: binary:logistic",
+        &fingerprint,
+        &trees,
+        &used,
+    ] {
+        assert!(output.contains(expected), "{} in {}", expected, output);
+    }
+    let json_path = get_resource("model/gbtree/v47/multi-softprob.json");
+    let output = cli_stdout(run_cli(
+        binary,
+        &["summary", json_path.to_str().unwrap()],
+        "",
+    ));
+    assert!(output.contains("format: json\nversion: "));
+    assert!(output.contains("trees per group: 3, 3\n"));
+
+    // dumps match xgboost's
+    let output = cli_stdout(run_cli(binary, &["dump", "--with-stats", model_path], ""));
+    let mut expected = String::new();
+    open_resource_file("expectation/gbtree/v47/binary-logistic.dump_stats")
+        .unwrap()
+        .read_to_string(&mut expected)
+        .unwrap();
+    assert_eq!(output, expected);
+    let output = cli_stdout(run_cli(
+        binary,
+        &["dump", "--format=json", "--with-stats", model_path],
+        "",
+    ));
+    let trees: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(trees.as_array().unwrap().len(), summary.num_trees);
+    let output = cli_stdout(run_cli(
+        binary,
+        &["dump", "--format", "dot", "--tree", "1", model_path],
+        "",
+    ));
+    assert_eq!(output, predictor.to_graphviz(1, false, false).unwrap());
+
+    let output = cli_stdout(run_cli(
+        binary,
+        &["importance", "--type", "total_gain", model_path],
+        "",
+    ));
+    let expected = predictor
+        .feature_importance_by_name(ImportanceType::TotalGain)
+        .unwrap();
+    let mut previous = f32::INFINITY;
+    assert_eq!(output.lines().count(), expected.len());
+    for line in output.lines() {
+        let (name, score) = line.split_once('\t').unwrap();
+        let score: f32 = score.parse().unwrap();
+        assert_eq!(score, expected[name]);
+        assert!(score <= previous);
+        previous = score;
+    }
+
+    // conversions load back to the same model
+    let data = load_data("data/agaricus.txt.0.test");
+    let path = std::env::temp_dir().join(format!("cli-{}.json", std::process::id()));
+    cli_stdout(run_cli(
+        binary,
+        &["convert", model_path, path.to_str().unwrap()],
+        "",
+    ));
+    assert_eq!(
+        ModelFormat::detect(&std::fs::read(&path).unwrap()),
+        ModelFormat::Json
+    );
+    assert_same_model(&predictor, &TestPredictor::from_path(&path).unwrap(), &data);
+    cli_stdout(run_cli(
+        binary,
+        &[
+            "convert",
+            "--to",
+            "binary",
+            json_path.to_str().unwrap(),
+            path.to_str().unwrap(),
+        ],
+        "",
+    ));
+    assert_same_model(
+        &load_model("gbtree", "v47/multi-softprob", "json"),
+        &TestPredictor::from_path(&path).unwrap(),
+        &data,
+    );
+    cli_stdout(run_cli(
+        binary,
+        &[
+            "convert",
+            "--to",
+            "compact",
+            model_path,
+            path.to_str().unwrap(),
+        ],
+        "",
+    ));
+    let compact = CompactModel::from_bytes(std::fs::read(&path).unwrap()).unwrap();
+    for (_, map) in data.iter().take(20) {
+        assert_eq!(
+            compact.predict(map, false, 0),
+            predictor.predict(map, false, 0)
+        );
+    }
+    std::fs::remove_file(&path).unwrap();
+
+    // UBJSON can't be written
+    let ubj_path = std::env::temp_dir().join(format!("cli-{}.ubj", std::process::id()));
+    let output = run_cli(
+        binary,
+        &["convert", model_path, ubj_path.to_str().unwrap()],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!ubj_path.exists());
+
+    let output = run_cli(binary, &["dump", "--format", "svg", model_path], "");
+    assert_eq!(output.status.code(), Some(1));
+    let output = run_cli(binary, &["inspect", model_path], "");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+/// Load times of sms-spam.model, run with
+/// `cargo test --release --test apply_models -- --ignored --nocapture bench_load`
+#[test]
+#[ignore]
+fn bench_load() {
+    const LOADS: u32 = 2000;
+    let path = get_resource("model/gbtree/v47/sms-spam.model");
+    let bytes = std::fs::read(&path).unwrap();
+
+    let time = |name: &str, load: &dyn Fn() -> TestPredictor| {
+        let start = Instant::now();
+        for _ in 0..LOADS {
+            assert!(load().num_trees() > 0);
+        }
+        println!("{}: {:?} per load", name, start.elapsed() / LOADS);
+    };
+    time("read_from(File)", &|| {
+        Predictor::read_from(&mut File::open(&path).unwrap()).unwrap()
+    });
+    time("read_from_buffered(BufReader<File>)", &|| {
+        Predictor::read_from_buffered(&mut BufReader::new(File::open(&path).unwrap())).unwrap()
+    });
+    time("read_from(&[u8])", &|| {
+        Predictor::read_from(&mut bytes.as_slice()).unwrap()
+    });
+    time("read_from_buffered(&[u8])", &|| {
+        Predictor::read_from_buffered(&mut bytes.as_slice()).unwrap()
+    });
+}
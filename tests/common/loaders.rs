@@ -17,7 +17,7 @@ pub fn open_resource_file(rel_path: &str) -> Result<File> {
 }
 
 pub fn load_data(rel_path: &str) -> LinkedList<DataItem> {
-    let mut file = open_resource_file(rel_path).unwrap();
+    let file = open_resource_file(rel_path).unwrap();
     let reader = BufReader::new(file);
     let mut result = LinkedList::<DataItem>::new();
 
@@ -39,7 +39,7 @@ pub fn load_data(rel_path: &str) -> LinkedList<DataItem> {
 }
 
 pub fn load_expectation(rel_path: &str) -> LinkedList<Vec<f32>> {
-    let mut file = open_resource_file(rel_path).unwrap();
+    let file = open_resource_file(rel_path).unwrap();
     let reader = BufReader::new(file);
     let mut result = LinkedList::<Vec<f32>>::new();
     for line in reader.lines() {
@@ -2,11 +2,11 @@ pub mod loaders;
 pub mod tasks;
 
 pub mod types {
-    use std::collections::{HashMap, LinkedList};
     use xgboost_predictor::fvec::FVecMap;
     use xgboost_predictor::predictor::Predictor;
 
     pub type TestMap = FVecMap<f32>;
     pub type DataItem = (usize, TestMap);
     pub type TestPredictor = Predictor<TestMap>;
+    pub type PredictFn = Box<dyn Fn(&TestPredictor, &TestMap) -> Vec<f32>>;
 }
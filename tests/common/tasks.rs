@@ -1,8 +1,10 @@
+use xgboost_predictor::predictor::PredictOptions;
+
 use crate::common::types::*;
 
 pub struct PredictionTask {
     pub expectation_suffix: String,
-    pub predict: Box<dyn Fn(&TestPredictor, &TestMap) -> Vec<f32>>,
+    pub predict: PredictFn,
 }
 
 pub fn predict() -> PredictionTask {
@@ -75,3 +77,77 @@ pub fn predict_leaf_with_ntree(n: usize) -> PredictionTask {
         }),
     }
 }
+
+pub fn predict_with_root_index(root_index: usize) -> PredictionTask {
+    PredictionTask {
+        expectation_suffix: format!("predict_root{}", root_index),
+        predict: Box::new(move |predictor: &TestPredictor, map: &TestMap| {
+            predictor
+                .predict_with(
+                    map,
+                    &PredictOptions {
+                        root_index,
+                        ..Default::default()
+                    },
+                )
+                .unwrap()
+        }),
+    }
+}
+
+pub fn predict_leaf_with_root_index(root_index: usize) -> PredictionTask {
+    PredictionTask {
+        expectation_suffix: format!("leaf_root{}", root_index),
+        predict: Box::new(move |predictor: &TestPredictor, map: &TestMap| {
+            predictor
+                .predict_leaf_with(
+                    map,
+                    &PredictOptions {
+                        root_index,
+                        ..Default::default()
+                    },
+                )
+                .unwrap()
+                .into_iter()
+                .map(|x| x as f32)
+                .collect()
+        }),
+    }
+}
+
+pub fn predict_with_iteration_range(begin: usize, end: usize, suffix: &str) -> PredictionTask {
+    PredictionTask {
+        expectation_suffix: suffix.to_string(),
+        predict: Box::new(move |predictor: &TestPredictor, map: &TestMap| {
+            predictor
+                .predict_with(
+                    map,
+                    &PredictOptions {
+                        iteration_range: (begin, end),
+                        ..Default::default()
+                    },
+                )
+                .unwrap()
+        }),
+    }
+}
+
+pub fn predict_leaf_with_iteration_range(begin: usize, end: usize, suffix: &str) -> PredictionTask {
+    PredictionTask {
+        expectation_suffix: suffix.to_string(),
+        predict: Box::new(move |predictor: &TestPredictor, map: &TestMap| {
+            predictor
+                .predict_leaf_with(
+                    map,
+                    &PredictOptions {
+                        iteration_range: (begin, end),
+                        ..Default::default()
+                    },
+                )
+                .unwrap()
+                .into_iter()
+                .map(|x| x as f32)
+                .collect()
+        }),
+    }
+}
@@ -0,0 +1,30 @@
+"""Rewrites the JSON and UBJSON models converted from the binary models of the same name
+with xgboost 2.x, which loads each binary model and saves it again:
+
+- gbtree/v47/binary-logistic.json and .ubj
+- gbtree/v47/multi-softprob.json and .ubj
+- gblinear/v47/multi-softprob.json
+- dart/rank-pairwise.json
+
+The files committed were converted without xgboost, they carry `version [2, 0, 0]` and
+an empty `iteration_indptr`. The models are the same, so the expectations of the binary
+models still apply.
+
+Requires xgboost 2.x. Run from tests/resources.
+"""
+
+import xgboost as xgb
+
+assert xgb.__version__.startswith("2."), "xgboost 2.x is required"
+
+MODELS = [
+    ("model/gbtree/v47/binary-logistic", ["json", "ubj"]),
+    ("model/gbtree/v47/multi-softprob", ["json", "ubj"]),
+    ("model/gblinear/v47/multi-softprob", ["json"]),
+    ("model/dart/rank-pairwise", ["json"]),
+]
+
+for name, extensions in MODELS:
+    booster = xgb.Booster(model_file="%s.model" % name)
+    for extension in extensions:
+        booster.save_model("%s.%s" % (name, extension))
@@ -0,0 +1,143 @@
+"""Writes the models of model/gbtree/v200 and their expectations with xgboost 2.x:
+
+- categorical: categorical splits on f0 and f2 of data/categorical.test
+- multi-target: 3 targets with vector leaves (`multi_strategy="multi_output_tree"`)
+- reg-quantileerror: `reg:quantileerror` with 3 quantiles
+
+The files committed in v200 were written by hand in the JSON and UBJSON layout of
+xgboost 2.0, not by xgboost itself. Running this script replaces them with models
+trained by xgboost. The trees then differ, so the assertions of tests/apply_models.rs
+on the v200 models (number of rounds, categories) may need updating with them.
+
+Requires xgboost 2.x and numpy. Run from tests/resources.
+"""
+
+import json
+
+import numpy as np
+import xgboost as xgb
+
+assert xgb.__version__.startswith("2."), "xgboost 2.x is required"
+
+MODEL_DIR = "model/gbtree/v200"
+EXPECTATION_DIR = "expectation/gbtree/v200"
+
+
+def load_libsvm(path, num_feature):
+    """Rows of `path` with absent features as NaN, as the tests read them"""
+    labels, rows = [], []
+    for line in open(path):
+        tokens = line.split()
+        labels.append(float(tokens[0]))
+        row = [np.nan] * num_feature
+        for token in tokens[1:]:
+            index, value = token.split(":")
+            row[int(index)] = float(value)
+        rows.append(row)
+    return np.array(rows, dtype=np.float32), np.array(labels, dtype=np.float32)
+
+
+def write_rows(path, rows):
+    with open(path, "w") as out:
+        for row in rows:
+            out.write(",".join(str(value) for value in np.atleast_1d(row)) + "\n")
+
+
+def write_dump(path, booster, dump_format):
+    with open(path, "w") as out:
+        dump = booster.get_dump(with_stats=True, dump_format=dump_format)
+        for i, tree in enumerate(dump):
+            out.write("booster[%d]:\n%s" % (i, tree))
+            if not tree.endswith("\n"):
+                out.write("\n")
+
+
+def save(name, booster, dmatrix, expectations):
+    booster.save_model("%s/%s.json" % (MODEL_DIR, name))
+    booster.save_model("%s/%s.ubj" % (MODEL_DIR, name))
+    if "predict" in expectations:
+        write_rows("%s/%s.predict" % (EXPECTATION_DIR, name), booster.predict(dmatrix))
+    if "predict_ntree" in expectations:
+        write_rows(
+            "%s/%s.predict_ntree" % (EXPECTATION_DIR, name),
+            booster.predict(dmatrix, iteration_range=(0, 1)),
+        )
+    if "leaf" in expectations:
+        write_rows(
+            "%s/%s.leaf" % (EXPECTATION_DIR, name),
+            booster.predict(dmatrix, pred_leaf=True).astype(np.int64),
+        )
+
+
+def check_leaf_vectors(name, features):
+    """Walks the trees saved in `name`.json, reading the leaf vector of node i from
+    base_weights[i * size_leaf_vector:(i + 1) * size_leaf_vector] as the crate does,
+    and compares the margins with xgboost's"""
+    model = json.load(open("%s/%s.json" % (MODEL_DIR, name)))["learner"]
+    num_target = int(model["learner_model_param"]["num_target"])
+    base_score = float(model["learner_model_param"]["base_score"])
+    margins = np.full((len(features), num_target), base_score)
+    for tree in model["gradient_booster"]["model"]["trees"]:
+        assert int(tree["tree_param"]["size_leaf_vector"]) == num_target
+        for row, margin in zip(features, margins):
+            nid = 0
+            while tree["left_children"][nid] != -1:
+                value = row[tree["split_indices"][nid]]
+                if np.isnan(value):
+                    go_left = tree["default_left"][nid]
+                else:
+                    go_left = value < tree["split_conditions"][nid]
+                nid = tree["left_children" if go_left else "right_children"][nid]
+            margin += tree["base_weights"][nid * num_target : (nid + 1) * num_target]
+    return margins
+
+
+params = {"base_score": 0.5, "max_depth": 2, "tree_method": "hist"}
+
+features, labels = load_libsvm("data/categorical.test", 3)
+categorical = xgb.DMatrix(
+    features, label=labels, feature_types=["c", "q", "c"], enable_categorical=True
+)
+booster = xgb.train(
+    dict(params, objective="reg:squarederror", max_cat_to_onehot=1),
+    categorical,
+    num_boost_round=3,
+)
+save("categorical", booster, categorical, ["predict", "leaf"])
+write_dump("%s/categorical.dump_stats" % EXPECTATION_DIR, booster, "text")
+write_dump("%s/categorical.dump_json_stats" % EXPECTATION_DIR, booster, "json")
+write_dump("%s/categorical.dump_dot_stats" % EXPECTATION_DIR, booster, "dot")
+
+features, labels = load_libsvm("data/agaricus.txt.0.test", 127)
+targets = np.stack([labels, 1 - labels, 2 * labels + features[:, 1]], axis=1)
+multi_target = xgb.DMatrix(features, label=np.nan_to_num(targets))
+booster = xgb.train(
+    dict(params, objective="reg:squarederror", multi_strategy="multi_output_tree"),
+    multi_target,
+    num_boost_round=2,
+)
+save("multi-target", booster, multi_target, ["predict", "predict_ntree", "leaf"])
+assert np.allclose(
+    check_leaf_vectors("multi-target", features),
+    booster.predict(multi_target, output_margin=True),
+    atol=1e-5,
+), "leaf vectors are not laid out as the crate reads them"
+
+agaricus = xgb.DMatrix(features, label=labels)
+booster = xgb.train(
+    dict(params, objective="reg:quantileerror", quantile_alpha=[0.1, 0.5, 0.9]),
+    agaricus,
+    num_boost_round=2,
+)
+save("reg-quantileerror", booster, agaricus, ["predict"])
+# the output groups follow the order of quantile_alpha, as the crate reads them
+saved = json.load(open("%s/reg-quantileerror.json" % MODEL_DIR))["learner"]["objective"]
+assert json.loads(saved["quantile_loss_param"]["quantile_alpha"]) == [0.1, 0.5, 0.9]
+quantiles = booster.predict(agaricus)
+for alpha, column in zip([0.1, 0.5, 0.9], quantiles.T):
+    single = xgb.train(
+        dict(params, objective="reg:quantileerror", quantile_alpha=alpha),
+        agaricus,
+        num_boost_round=2,
+    )
+    assert np.allclose(single.predict(agaricus), column, atol=1e-5), alpha
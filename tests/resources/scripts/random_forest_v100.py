@@ -0,0 +1,64 @@
+"""Writes model/gbtree/v100/random-forest.model, a binary model in the format of
+xgboost 1.0 with num_parallel_tree = 2, from the trees of v47/binary-logistic.model.
+
+Each tree is saved twice with halved leaves, so each boosting round of two parallel
+trees predicts as the tree it comes from and the expectations of
+v47/binary-logistic apply. Run from tests/resources.
+"""
+
+import struct
+
+SOURCE = "model/gbtree/v47/binary-logistic.model"
+TARGET = "model/gbtree/v100/random-forest.model"
+NUM_PARALLEL_TREE = 2
+
+data = open(SOURCE, "rb").read()
+
+# learner parameters: base_score, num_feature, num_class, saved_with_pbuffer, reserved[30]
+header = bytearray(data[:136])
+(saved_with_pbuffer,) = struct.unpack_from("<i", header, 12)
+assert saved_with_pbuffer == 0, "attributes are not copied"
+# reserved[1] and reserved[2] hold the version of xgboost 1.0 and later
+struct.pack_into("<ii", header, 20, 1, 0)
+pos = 136
+names = []
+for _ in range(2):
+    (length,) = struct.unpack_from("<Q", data, pos)
+    names.append(data[pos : pos + 8 + length])
+    pos += 8 + length
+
+# booster parameters: num_trees, num_roots or num_parallel_tree, ...
+gbtree_param = bytearray(data[pos : pos + 160])
+num_trees, num_roots = struct.unpack_from("<ii", gbtree_param, 0)
+assert num_roots <= 1
+struct.pack_into("<ii", gbtree_param, 0, num_trees * NUM_PARALLEL_TREE, NUM_PARALLEL_TREE)
+pos += 160
+
+trees = []
+for _ in range(num_trees):
+    # tree parameters: num_roots, num_nodes, num_deleted, max_depth, num_feature,
+    # size_leaf_vector, reserved[31]
+    (num_nodes,) = struct.unpack_from("<i", data, pos + 4)
+    tree = bytearray(data[pos : pos + 148 + num_nodes * (20 + 16)])
+    for node in range(num_nodes):
+        # nodes: parent, cleft, cright, sindex, leaf value or split condition
+        offset = 148 + node * 20
+        (cleft,) = struct.unpack_from("<i", tree, offset + 4)
+        if cleft == -1:
+            (leaf,) = struct.unpack_from("<f", tree, offset + 16)
+            struct.pack_into("<f", tree, offset + 16, leaf / NUM_PARALLEL_TREE)
+    trees.append(bytes(tree))
+    pos += len(tree)
+
+tree_info = struct.unpack_from("<%di" % num_trees, data, pos)
+pos += 4 * num_trees
+assert pos == len(data), "unexpected data after the trees"
+
+with open(TARGET, "wb") as out:
+    out.write(header)
+    out.writelines(names)
+    out.write(gbtree_param)
+    for tree in trees:
+        out.writelines([tree] * NUM_PARALLEL_TREE)
+    out.write(struct.pack("<%di" % (num_trees * NUM_PARALLEL_TREE),
+                          *[g for g in tree_info for _ in range(NUM_PARALLEL_TREE)]))